@@ -8,14 +8,270 @@
 //!
 //! Generally, the access system just applies to [Users].
 //!
+//! The [RoleHasPermission] and [CanAccess] traits tie together the system-wide
+//! access system here and the per-company access system (see [Member]) so that
+//! generic code can ask "can this actor do X in this scope?" the same way
+//! regardless of which permission system is being checked.
+//!
+//! Company permissions apply company-wide. Processes and resources can also
+//! carry an [Acl] that narrows down which members (or company roles) can act on
+//! that specific object.
+//!
 //! [err_priv]: ../error/enum.Error.html#variant.InsufficientPrivileges
 //! [Users]: ../models/user/struct.User.html
+//! [RoleHasPermission]: trait.RoleHasPermission.html
+//! [CanAccess]: trait.CanAccess.html
+//! [Member]: ../models/member/struct.Member.html
+//! [Acl]: struct.Acl.html
 
 use crate::{
     error::{Error, Result},
+    models::{
+        company::Permission as CompanyPermission,
+        lib::agent::{Agent, AgentID},
+        member::{Member, MemberID},
+    },
 };
+use getset::Getters;
 use serde::{Serialize, Deserialize};
 
+/// The permission an access check required, tagged with the permission system
+/// it belongs to.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "system", content = "permission", rename_all = "snake_case")]
+pub enum RequiredPermission {
+    /// A system-wide permission, granted by a user's roles
+    System(Permission),
+    /// A company permission, granted to a member
+    Company(CompanyPermission),
+}
+
+impl From<Permission> for RequiredPermission {
+    fn from(perm: Permission) -> Self {
+        RequiredPermission::System(perm)
+    }
+}
+
+impl From<CompanyPermission> for RequiredPermission {
+    fn from(perm: CompanyPermission) -> Self {
+        RequiredPermission::Company(perm)
+    }
+}
+
+/// Why an access check failed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DenyReason {
+    /// The actor doesn't hold the required permission
+    MissingPermission,
+    /// The actor is inactive
+    Inactive,
+    /// The actor has been deleted
+    Deleted,
+    /// The actor is acting on behalf of a different user (for instance, the
+    /// member record given belongs to someone other than the caller)
+    WrongUser,
+    /// The actor (or the object being acted on) belongs to a different company
+    WrongCompany,
+    /// The company acting isn't a participant in the agreement given
+    AgreementParticipantMismatch,
+    /// The object being acted on is owned by someone else
+    NotOwner,
+    /// The object being acted on has an [Acl](struct.Acl.html) that doesn't
+    /// grant the actor the required permission
+    ObjectAcl,
+    /// The company's [bylaws](../models/company/struct.Bylaw.html) only allow
+    /// this action to be taken by a vote
+    RequiresVote,
+}
+
+/// Describes a failed access check: which check failed, the permission that
+/// was required (if any), who was denied, and why. This is what
+/// [Error::InsufficientPrivileges][err_priv] carries, and it serializes to
+/// JSON so it can be handed back to API consumers as-is.
+///
+/// [err_priv]: ../error/enum.Error.html#variant.InsufficientPrivileges
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct AccessDenied {
+    /// Why access was denied
+    reason: DenyReason,
+    /// The permission that was required, if the check was permission-based
+    permission: Option<RequiredPermission>,
+    /// The actor that was denied, if known
+    actor: Option<AgentID>,
+}
+
+impl AccessDenied {
+    /// Create a new access denial
+    pub fn new(reason: DenyReason, permission: Option<RequiredPermission>, actor: Option<AgentID>) -> Self {
+        Self {
+            reason,
+            permission,
+            actor,
+        }
+    }
+
+    /// Create an access denial for a specific actor that isn't tied to a
+    /// permission (ie, an ownership check).
+    pub fn for_actor<T: Into<AgentID>>(reason: DenyReason, actor: T) -> Self {
+        Self::new(reason, None, Some(actor.into()))
+    }
+}
+
+impl std::fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.reason())?;
+        if let Some(permission) = self.permission() {
+            write!(f, " (requires {:?})", permission)?;
+        }
+        if let Some(actor) = self.actor() {
+            write!(f, " for {:?}", actor)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<AccessDenied> for Error {
+    fn from(denied: AccessDenied) -> Self {
+        Error::InsufficientPrivileges(denied)
+    }
+}
+
+/// Implemented by anything that grants a set of permissions, such as a system
+/// [Role](enum.Role.html).
+pub trait RoleHasPermission {
+    /// The type of permission this role grants.
+    type Permission: Clone + Into<RequiredPermission>;
+
+    /// Determine if this role grants the given permission.
+    fn can(&self, permission: &Self::Permission) -> bool;
+}
+
+/// Implemented by objects that hold roles (users, members) and can therefore
+/// be checked for access.
+pub trait CanAccess: Agent {
+    /// The type of role this object holds.
+    type Role: RoleHasPermission;
+    /// The scope that access is checked in. For instance, a member's roles only
+    /// apply within a specific company, whereas a user's roles apply
+    /// system-wide.
+    type Scope;
+
+    /// Return the roles this object holds within the given scope.
+    fn access_roles(&self, scope: &Self::Scope) -> Vec<Self::Role>;
+
+    /// Determine if this object's roles apply in the given scope.
+    fn in_scope(&self, scope: &Self::Scope) -> bool;
+
+    /// Determines if this object can perform an action in the given scope.
+    /// Inactive (or deleted) objects have no access.
+    fn can_access(&self, scope: &Self::Scope, permission: &<Self::Role as RoleHasPermission>::Permission) -> bool {
+        if !self.is_active() || !self.in_scope(scope) {
+            return false;
+        }
+        self.access_roles(scope).iter().any(|role| role.can(permission))
+    }
+
+    /// Check if this object can perform an action in the given scope, returning
+    /// [Error::InsufficientPrivileges][err_priv] (with the reason) if not.
+    ///
+    /// [err_priv]: ../error/enum.Error.html#variant.InsufficientPrivileges
+    fn check_access(&self, scope: &Self::Scope, permission: &<Self::Role as RoleHasPermission>::Permission) -> Result<()> {
+        let reason = if self.is_deleted() {
+            DenyReason::Deleted
+        } else if !self.is_active() {
+            DenyReason::Inactive
+        } else if !self.in_scope(scope) {
+            DenyReason::WrongCompany
+        } else if !self.access_roles(scope).iter().any(|role| role.can(permission)) {
+            DenyReason::MissingPermission
+        } else {
+            return Ok(());
+        };
+        Err(AccessDenied::new(reason, Some(permission.clone().into()), Some(self.agent_id())))?
+    }
+}
+
+/// Who an [AclEntry](struct.AclEntry.html) applies to.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AclSubject {
+    /// A specific member
+    Member(MemberID),
+    /// Any member holding the named company role
+    Role(String),
+}
+
+/// Grants a set of company permissions on a single object to a member (or to
+/// everyone holding a company role).
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct AclEntry {
+    /// Who this entry applies to
+    subject: AclSubject,
+    /// The actions the subject may perform on the object
+    permissions: Vec<CompanyPermission>,
+}
+
+impl AclEntry {
+    /// Create a new ACL entry
+    pub fn new(subject: AclSubject, permissions: Vec<CompanyPermission>) -> Self {
+        Self {
+            subject,
+            permissions,
+        }
+    }
+
+    /// Determine if this entry applies to the given member.
+    pub fn applies_to(&self, member: &Member) -> bool {
+        match self.subject() {
+            AclSubject::Member(member_id) => member.id() == member_id,
+            AclSubject::Role(name) => member.roles().iter().any(|role| role == name),
+        }
+    }
+}
+
+/// A per-object access control list, which narrows down *who* within a company
+/// can perform certain actions on a specific process, resource, or resource
+/// spec.
+///
+/// ACLs only restrict: a member still needs the company permission for an
+/// action. Any action that at least one entry mentions is limited to the
+/// members that an entry grants it to, and actions no entry mentions are left
+/// alone. So an ACL with a single `Role("tool library") => [Use]` entry means
+/// only members of the tool library can use the object, but anyone with the
+/// `Consume` permission can still consume it.
+#[derive(Clone, Debug, Default, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Acl {
+    /// The entries in this list
+    entries: Vec<AclEntry>,
+}
+
+impl Acl {
+    /// Create a new ACL
+    pub fn new(entries: Vec<AclEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Determine if this ACL restricts the given action at all.
+    pub fn restricts(&self, permission: &CompanyPermission) -> bool {
+        self.entries().iter()
+            .any(|entry| entry.permissions().iter().any(|perm| perm.can(permission)))
+    }
+
+    /// Determine if this ACL lets the given member perform an action.
+    pub fn allows(&self, member: &Member, permission: &CompanyPermission) -> bool {
+        if !self.restricts(permission) {
+            return true;
+        }
+        self.entries().iter()
+            .filter(|entry| entry.applies_to(member))
+            .any(|entry| entry.permissions().iter().any(|perm| perm.can(permission)))
+    }
+}
+
 /// Define the system-wide permissions.
 ///
 /// Note there may be per-model permissions that are handled separately.
@@ -31,12 +287,15 @@ pub enum Permission {
     CompanyAdminDelete,
     CompanyAdminUpdate,
     CompanyCreate,
+    CompanyPayroll,
     CompanyUpdateAgreements,
+    CompanyUpdateBylaws,
     CompanyUpdateCommitments,
     CompanyUpdateIntents,
     CompanyUpdateMembers,
     CompanyUpdateResources,
     CompanyUpdateResourceSpecs,
+    CompanyUpdateRoles,
     CompanyUpdateProcesses,
     CompanyUpdateProcessSpecs,
 
@@ -54,6 +313,11 @@ pub enum Permission {
     UserSetRoles,
     UserUpdate,
 
+    ResourceGroupCreate,
+    ResourceGroupDelete,
+    ResourceGroupLink,
+    ResourceGroupUpdate,
+
     ResourceSpecCreate,
     ResourceSpecDelete,
     ResourceSpecUpdate,
@@ -61,6 +325,11 @@ pub enum Permission {
     OccupationCreate,
     OccupationDelete,
     OccupationUpdate,
+
+    ProposalClose,
+    ProposalCloseSystemic,
+    ProposalCreate,
+    ProposalVote,
 }
 
 /// Define the system-wide roles users can have.
@@ -108,12 +377,15 @@ impl Role {
                     Permission::UserUpdate,
                     Permission::UserDelete,
                     Permission::CompanyCreate,
+                    Permission::CompanyPayroll,
                     Permission::CompanyUpdateAgreements,
+                    Permission::CompanyUpdateBylaws,
                     Permission::CompanyUpdateCommitments,
                     Permission::CompanyUpdateIntents,
                     Permission::CompanyUpdateMembers,
                     Permission::CompanyUpdateResourceSpecs,
                     Permission::CompanyUpdateResources,
+                    Permission::CompanyUpdateRoles,
                     Permission::CompanyUpdateProcessSpecs,
                     Permission::CompanyUpdateProcesses,
                     Permission::ResourceSpecCreate,
@@ -124,6 +396,9 @@ impl Role {
                     Permission::AccountDelete,
                     Permission::EventCreate,
                     Permission::EventUpdate,
+                    Permission::ProposalClose,
+                    Permission::ProposalCreate,
+                    Permission::ProposalVote,
                 ]
             }
             Role::Guest => {
@@ -133,9 +408,13 @@ impl Role {
             }
         }
     }
+}
+
+impl RoleHasPermission for Role {
+    type Permission = Permission;
 
     /// Determine if a role has a specific permission.
-    pub fn can(&self, perm: &Permission) -> bool {
+    fn can(&self, perm: &Permission) -> bool {
         for p in &self.permissions() {
             match p {
                 Permission::All => {
@@ -163,13 +442,22 @@ pub fn guest_check(perm: Permission) -> Result<()> {
     if (Role::Guest).can(&perm) {
         Ok(())
     } else {
-        Err(Error::InsufficientPrivileges)
+        Err(AccessDenied::new(DenyReason::MissingPermission, Some(perm.into()), None).into())
     }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::{
+        models::{
+            company::{CompanyID, Permission as CompanyPermission},
+            member::MemberID,
+            occupation::OccupationID,
+            user::UserID,
+        },
+        util::{self, test::*},
+    };
 
     #[test]
     fn permissions_work() {
@@ -192,5 +480,75 @@ pub mod tests {
         assert!(comp_admin.can(&Permission::CompanyAdminUpdate));
         assert!(comp_admin.can(&Permission::CompanyAdminDelete));
     }
-}
 
+    #[test]
+    fn can_access() {
+        fn check<A: CanAccess>(actor: &A, scope: &A::Scope, permission: &<A::Role as RoleHasPermission>::Permission) -> bool {
+            actor.can_access(scope, permission) && actor.check_access(scope, permission).is_ok()
+        }
+
+        let now = util::time::now();
+        let user = make_user(&UserID::create(), None, &now);
+        assert!(check(&user, &(), &Permission::CompanyCreate));
+        assert!(!check(&user, &(), &Permission::CompanyAdminDelete));
+        let denied = AccessDenied::new(DenyReason::MissingPermission, Some(RequiredPermission::System(Permission::CompanyAdminDelete)), Some(user.agent_id()));
+        assert_eq!(user.check_access(&(), &Permission::CompanyAdminDelete), Err(Error::InsufficientPrivileges(denied)));
+
+        let user_id = UserID::create();
+        let company_id = CompanyID::create();
+        let member = make_member_worker(&MemberID::create(), &user_id, &company_id, &OccupationID::create(), vec![CompanyPermission::MemberCreate], &now);
+        let scope = make_company(&company_id, "jerry's widgets", &now);
+        assert!(check(&member, &scope, &CompanyPermission::MemberCreate));
+        assert!(!check(&member, &scope, &CompanyPermission::MemberDelete));
+        assert!(!check(&member, &make_company(&CompanyID::create(), "larry's chairs", &now), &CompanyPermission::MemberCreate));
+
+        let mut member2 = member.clone();
+        member2.set_permissions(vec![CompanyPermission::All]);
+        assert!(check(&member2, &scope, &CompanyPermission::MemberDelete));
+        member2.set_active(false);
+        assert!(!check(&member2, &scope, &CompanyPermission::MemberDelete));
+    }
+
+    #[test]
+    fn acl_allows() {
+        let now = util::time::now();
+        let company_id = CompanyID::create();
+        let member1 = make_member_worker(&MemberID::create(), &UserID::create(), &company_id, &OccupationID::create(), vec![CompanyPermission::All], &now);
+        let mut member2 = make_member_worker(&MemberID::create(), &UserID::create(), &company_id, &OccupationID::create(), vec![CompanyPermission::All], &now);
+        let member3 = make_member_worker(&MemberID::create(), &UserID::create(), &company_id, &OccupationID::create(), vec![CompanyPermission::All], &now);
+        member2.set_roles(vec!["tool library".into()]);
+
+        let acl = Acl::new(vec![
+            AclEntry::new(AclSubject::Member(member1.id().clone()), vec![CompanyPermission::Use, CompanyPermission::Consume]),
+            AclEntry::new(AclSubject::Role("tool library".into()), vec![CompanyPermission::Use]),
+        ]);
+        assert!(acl.restricts(&CompanyPermission::Use));
+        assert!(!acl.restricts(&CompanyPermission::Cite));
+        assert!(acl.allows(&member1, &CompanyPermission::Use));
+        assert!(acl.allows(&member1, &CompanyPermission::Consume));
+        assert!(acl.allows(&member2, &CompanyPermission::Use));
+        assert!(!acl.allows(&member2, &CompanyPermission::Consume));
+        assert!(!acl.allows(&member3, &CompanyPermission::Use));
+        // nobody is restricted from actions the ACL doesn't mention
+        assert!(acl.allows(&member3, &CompanyPermission::Cite));
+
+        // an `All` entry restricts every action
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Member(member1.id().clone()), vec![CompanyPermission::All])]);
+        assert!(acl.allows(&member1, &CompanyPermission::Cite));
+        assert!(!acl.allows(&member2, &CompanyPermission::Cite));
+        assert!(Acl::default().allows(&member3, &CompanyPermission::Cite));
+    }
+
+    #[test]
+    fn access_denied_serializes() {
+        let member_id = MemberID::new("9c4e4e4e-9c27-4bd9-8b4f-c8a8bd2d1b3c");
+        let denied = AccessDenied::new(DenyReason::MissingPermission, Some(CompanyPermission::Transfer.into()), Some(member_id.into()));
+        let json = serde_json::to_string(&denied).unwrap();
+        assert_eq!(json, r#"{"reason":"missing_permission","permission":{"system":"company","permission":"Transfer"},"actor":{"member":"9c4e4e4e-9c27-4bd9-8b4f-c8a8bd2d1b3c"}}"#);
+        let denied2: AccessDenied = serde_json::from_str(&json).unwrap();
+        assert_eq!(denied2, denied);
+
+        let json = serde_json::to_string(&AccessDenied::new(DenyReason::Inactive, Some(Permission::UserUpdate.into()), None)).unwrap();
+        assert_eq!(json, r#"{"reason":"inactive","permission":{"system":"system","permission":"UserUpdate"},"actor":null}"#);
+    }
+}
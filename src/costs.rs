@@ -95,13 +95,25 @@
 //! What products are defined as raw/semi-raw materials (aka "resources") is a
 //! systemwide, collective decision. It will be a function of governance, not
 //! code.
+//!
+//! Since resource specs are namespaced per-company, the iron from two
+//! different mines shows up as two different entries in the `resource` bucket.
+//! Specs that count as the same resource are linked to a [resource group],
+//! which lets the bucket be summed per-resource (`Costs::resource_by_group`)
+//! and valued using the group's per-unit cost (`Costs::resource_group_value`).
+//!
+//! [resource group]: ../models/resource_group/index.html
 
 use costs_derive::Costs;
 use crate::{
     error::{Error, Result},
     models::{
         currency::CurrencyID,
+        ModelRef,
+        lib::basis_model::Model,
         occupation::OccupationID,
+        resource_group::{ResourceGroup, ResourceGroupID},
+        resource_group_link::ResourceGroupLink,
         resource_spec::ResourceSpecID,
     },
 };
@@ -131,19 +143,19 @@ pub struct Costs {
     /// Stores resource content. Resources are ResourceSpec instances that have
     /// a resource tracking information attached, so we link to them via their
     /// ResourceSpecID
-    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty")]
+    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty", serialize_with = "crate::models::lib::diff::serialize_decimal_map")]
     resource: HashMap<ResourceSpecID, Decimal>,
     /// Stores labor *as is has been paid in credits* per-occupation. In other
     /// words, we don't track raw hours here, but rather the social labor value
     /// as negotiated between workers and their companies.
-    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty")]
+    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty", serialize_with = "crate::models::lib::diff::serialize_decimal_map")]
     labor: HashMap<OccupationID, Decimal>,
     /// Stores raw labor hours per-occupation. This information might be more
     /// useful in the future, as it's a measure of the occupation-time that went
     /// into building something, as opposed to the credits paid out. Cases where
     /// this might be handy is a system where all wages are 0, but we still want
     /// to track labor content.
-    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty")]
+    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty", serialize_with = "crate::models::lib::diff::serialize_decimal_map")]
     labor_hours: HashMap<OccupationID, Decimal>,
     /// Stores currency values of products. This is a strange one to have in a
     /// moneyless system, but supports the banking process of the system by
@@ -153,7 +165,7 @@ pub struct Costs {
     /// (or how many credits to destroy if being purchased internally). The idea
     /// is that in a hopeful future, this bucket will be obsolete and always
     /// empty as currency-based markets are phased out.
-    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty")]
+    #[serde(default = "Default::default", skip_serializing_if = "std::collections::HashMap::is_empty", serialize_with = "crate::models::lib::diff::serialize_decimal_map")]
     currency: HashMap<CurrencyID, Decimal>,
 }
 
@@ -162,6 +174,61 @@ impl Costs {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Sum up the `resource` bucket by [resource group][1], using `links` to
+    /// find which group each resource spec belongs to. Resource specs that
+    /// aren't in a group are left out, as are deleted links and links to
+    /// deleted groups.
+    ///
+    /// Only the links for resource specs we actually have costs for are looked
+    /// at. Returns an error if one of those specs is linked to more than one
+    /// group, or is linked to a group that isn't in `groups`.
+    ///
+    /// [1]: ../models/resource_group/index.html
+    pub fn resource_by_group(&self, groups: &[ResourceGroup], links: &[ResourceGroupLink]) -> Result<HashMap<ResourceGroupID, Decimal>> {
+        let mut spec_groups: HashMap<&ResourceSpecID, &ResourceGroupID> = HashMap::new();
+        let links = links.iter()
+            .filter(|link| !link.is_deleted() && self.resource().contains_key(link.resource_spec_id()));
+        for link in links {
+            let group = groups.iter()
+                .find(|group| group.id() == link.group_id())
+                .ok_or_else(|| Error::ModelNotFound(ModelRef::ResourceGroup(link.group_id().clone())))?;
+            if group.is_deleted() {
+                continue;
+            }
+            match spec_groups.get(link.resource_spec_id()) {
+                Some(group_id) if *group_id != link.group_id() => {
+                    Err(Error::ResourceGroupConflict(link.resource_spec_id().clone()))?;
+                }
+                _ => {
+                    spec_groups.insert(link.resource_spec_id(), link.group_id());
+                }
+            }
+        }
+        let mut grouped = HashMap::new();
+        for (spec_id, amount) in self.resource() {
+            if let Some(group_id) = spec_groups.get(spec_id) {
+                *grouped.entry((*group_id).clone()).or_insert(Decimal::zero()) += *amount;
+            }
+        }
+        Ok(grouped)
+    }
+
+    /// Find the credit value of the `resource` bucket, valuing each resource
+    /// group at its `credit_cost_per_unit`. Resources that aren't in a group,
+    /// or whose group is inactive or deleted, don't add anything.
+    ///
+    /// Returns an error if a link we need points to a group that isn't in
+    /// `groups` (see [resource_by_group](#method.resource_by_group)).
+    pub fn resource_group_value(&self, groups: &[ResourceGroup], links: &[ResourceGroupLink]) -> Result<Decimal> {
+        let mut value = Decimal::zero();
+        for (group_id, amount) in self.resource_by_group(groups, links)? {
+            if let Some(group) = groups.iter().find(|group| group.id() == &group_id).filter(|group| group.is_active()) {
+                value += amount * group.credit_cost_per_unit();
+            }
+        }
+        Ok(value)
+    }
 }
 
 /// A standard interface around moving costs from one object to another.
@@ -212,6 +279,10 @@ pub(crate) trait CostMover {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        models::resource_group_link::ResourceGroupLinkID,
+        util::{self, test::*},
+    };
 
     #[test]
     fn add() {
@@ -621,5 +692,56 @@ mod tests {
         assert_eq!(rec.costs, Costs::new_with_labor("firefighter", dec!(12.1) - dec!(12.0)));
         assert_eq!(proc.costs, Costs::new_with_labor("firefighter", dec!(12.0)));
     }
-}
 
+    #[test]
+    fn resource_by_group() {
+        let now = util::time::now();
+        let iron = make_resource_group(&ResourceGroupID::new("iron"), "iron", dec!(2), &now);
+        let mut silicon = make_resource_group(&ResourceGroupID::new("silicon"), "silicon", dec!(0.5), &now);
+        let mut links = vec![
+            make_resource_group_link(&ResourceGroupLinkID::create(), iron.id(), &"mine1-iron".into(), &now),
+            make_resource_group_link(&ResourceGroupLinkID::create(), iron.id(), &"mine2-iron".into(), &now),
+            make_resource_group_link(&ResourceGroupLinkID::create(), silicon.id(), &"sand".into(), &now),
+        ];
+        let mut costs = Costs::new_with_labor("miner", dec!(10));
+        costs.track_resource("mine1-iron", dec!(3));
+        costs.track_resource("mine2-iron", dec!(1.5));
+        costs.track_resource("sand", dec!(4));
+        costs.track_resource("widgets", dec!(7));
+
+        let groups = vec![iron.clone(), silicon.clone()];
+        let grouped = costs.resource_by_group(&groups, &links).unwrap();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped.get(iron.id()), Some(&dec!(4.5)));
+        assert_eq!(grouped.get(silicon.id()), Some(&dec!(4)));
+        assert_eq!(Costs::new_with_labor("miner", dec!(10)).resource_by_group(&groups, &links).unwrap(), HashMap::new());
+
+        assert_eq!(costs.resource_group_value(&groups, &links).unwrap(), dec!(4.5) * dec!(2) + dec!(4) * dec!(0.5));
+        silicon.set_active(false);
+        assert_eq!(costs.resource_group_value(&[iron.clone(), silicon.clone()], &links).unwrap(), dec!(9));
+        let res = costs.resource_group_value(std::slice::from_ref(&iron), &links);
+        assert_eq!(res, Err(Error::ModelNotFound(ModelRef::ResourceGroup(silicon.id().clone()))));
+
+        // deleted links don't count
+        links[1].set_deleted(Some(now));
+        assert_eq!(costs.resource_by_group(&groups, &links).unwrap().get(iron.id()), Some(&dec!(3)));
+
+        // a spec can't be in two groups...
+        let mut links2 = links.clone();
+        links2.push(make_resource_group_link(&ResourceGroupLinkID::create(), silicon.id(), &"mine1-iron".into(), &now));
+        assert_eq!(costs.resource_by_group(&groups, &links2), Err(Error::ResourceGroupConflict("mine1-iron".into())));
+        // ...but that only matters for costs that include the spec
+        let mut sand = Costs::new();
+        sand.track_resource("sand", dec!(2));
+        assert_eq!(sand.resource_by_group(&groups, &links2).unwrap().get(silicon.id()), Some(&dec!(2)));
+
+        // links to deleted groups don't count (or conflict)
+        let mut deleted = make_resource_group(&ResourceGroupID::new("old iron"), "old iron", dec!(1), &now);
+        deleted.set_deleted(Some(now));
+        links.push(make_resource_group_link(&ResourceGroupLinkID::create(), deleted.id(), &"mine1-iron".into(), &now));
+        links.push(make_resource_group_link(&ResourceGroupLinkID::create(), deleted.id(), &"widgets".into(), &now));
+        let grouped = costs.resource_by_group(&[iron.clone(), silicon.clone(), deleted.clone()], &links).unwrap();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped.get(iron.id()), Some(&dec!(3)));
+    }
+}
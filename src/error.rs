@@ -2,8 +2,11 @@
 //! conditions that can arise while interacting with the system.
 
 use crate::{
+    access::AccessDenied,
     models::{
+        ModelRef,
         event::EventError,
+        resource_spec::ResourceSpecID,
     },
 };
 use thiserror::Error;
@@ -12,23 +15,96 @@ use thiserror::Error;
 /// which an expectation is not met or a problem occurs.
 #[derive(Error, Debug, PartialEq)]
 pub enum Error {
+    /// When we try to act on an agreement that hasn't been accepted by all of
+    /// its participants.
+    #[error("agreement is not binding (not all participants have accepted it)")]
+    AgreementNotBinding,
+    /// When accepting an agreement whose revision isn't the one the signer
+    /// reviewed (it was edited in the meantime).
+    #[error("agreement has changed since it was reviewed")]
+    AgreementRevisionMismatch,
+    /// The account given doesn't match the one we expected (for instance, a
+    /// worker's compensation pays into a different account).
+    #[error("account does not match the expected account")]
+    AccountMismatch,
     /// There was an error while using a builder (likely an internal error)
     #[error("error building object {0}")]
     BuilderFailed(String),
+    /// When a bylaw's requirement doesn't make sense (ie, requiring zero
+    /// approvals).
+    #[error("bylaw is invalid")]
+    BylawInvalid,
+    /// When a proposal runs a transaction using a permission the company's
+    /// bylaws govern, but the proposal's votes don't meet the bylaw's
+    /// requirement.
+    #[error("proposal does not satisfy the company's bylaws")]
+    BylawNotSatisfied,
     /// When we try to perform an operation that would erase costs (such as
     /// trying to delete a Process that has non-zero costs).
     #[error("cannot erase costs")]
     CannotEraseCosts,
+    /// When we try to perform an operation that would erase credits (such as
+    /// deleting an account that has a non-zero balance).
+    #[error("cannot erase credits")]
+    CannotEraseCredits,
     /// When you try to do something that requires a commitment but the given
     /// commitment doesn't match the action being performed.
     #[error("commitment is invalid")]
     CommitmentInvalid,
+    /// A company role with the given name already exists (or existed, and was
+    /// deleted).
+    #[error("company role {0} already exists")]
+    CompanyRoleExists(String),
+    /// The company has no role with the given name.
+    #[error("company role {0} not found")]
+    CompanyRoleNotFound(String),
+    /// The worker's compensation isn't a salary (or is missing the estimated
+    /// hours per week needed to estimate their labor).
+    #[error("the worker's compensation must be a salary with estimated hours")]
+    CompensationNotSalaried,
+    /// A currency was given a `decimal_scale` we can't represent.
+    #[error("invalid currency decimal scale {0}")]
+    CurrencyDecimalScaleInvalid(u32),
+    /// We tried to build a date that doesn't exist.
+    #[error("invalid date {0}")]
+    DateInvalid(String),
+    /// A delegation must be between two different members and must expire
+    /// after it starts.
+    #[error("delegation is invalid")]
+    DelegationInvalid,
+    /// We tried to revoke a delegation that doesn't exist.
+    #[error("delegation not found")]
+    DelegationNotFound,
     /// An error while processing an event.
     #[error("event error {0:?}")]
     Event(#[from] EventError),
-    /// You don't have permission to perform this action
-    #[error("insufficient privileges")]
-    InsufficientPrivileges,
+    /// When a document being imported (ie, ValueFlows JSON-LD) can't be
+    /// mapped onto our models.
+    #[error("import failed: {0}")]
+    ImportFailed(String),
+    /// You don't have permission to perform this action. Carries the details
+    /// of which check failed and why.
+    #[error("insufficient privileges: {0}")]
+    InsufficientPrivileges(AccessDenied),
+    /// When a log entry doesn't follow the entry before it (its `prev_hash`
+    /// doesn't match). Holds the entry's sequence number.
+    #[error("log entry {0} does not follow the previous entry")]
+    LogChainBroken(u64),
+    /// When a log entry's contents don't match its hash (it was edited).
+    /// Holds the entry's sequence number.
+    #[error("log entry {0} does not match its hash")]
+    LogHashMismatch(u64),
+    /// When a log (or a log entry) can't be read or parsed.
+    #[error("error reading log: {0}")]
+    LogReadFailed(String),
+    /// When a log entry isn't the next one in the sequence, meaning entries
+    /// are missing or out of order. Holds the expected and found sequence
+    /// numbers.
+    #[error("expected log entry {0}, found {1}")]
+    LogSequenceMismatch(u64, u64),
+    /// When a log entry can't be written.
+    #[error("error writing log: {0}")]
+    LogWriteFailed(String),
     /// We get this when trying to pull a measure out of a resource and come up
     /// blank, for instance when using `consume` on a resource that hasn't had
     /// its quantities initialized via `produce`/`raise`/`transfer`/etc.
@@ -41,6 +117,14 @@ pub enum Error {
     /// The given `Member` must be a `MemberWorker` class
     #[error("the member given must be a worker (not company, user, etc)")]
     MemberMustBeWorker,
+    /// When a [Store](../store/trait.Store.html) is asked to create a model
+    /// that it already has.
+    #[error("model {0:?} already exists")]
+    ModelExists(ModelRef),
+    /// When a [Store](../store/trait.Store.html) is asked to update or delete
+    /// a model that it doesn't have.
+    #[error("model {0:?} not found")]
+    ModelNotFound(ModelRef),
     /// We're missing required fields in a call
     #[error("fields missing {0:?}")]
     MissingFields(Vec<String>),
@@ -71,6 +155,53 @@ pub enum Error {
     /// match expectation.
     #[error("Op does not match expectation")]
     OpMismatch,
+    /// When we try to run payroll for a pay period that hasn't ended yet.
+    #[error("pay period has not ended")]
+    PayPeriodNotEnded,
+    /// When we try to vote on (or close) a proposal that has already closed.
+    #[error("proposal is closed")]
+    ProposalClosed,
+    /// When the electorate a proposal is tallied against doesn't fit the
+    /// proposal (ie, a list of voters for a company proposal, or members for a
+    /// systemic one).
+    #[error("electorate does not match the proposal")]
+    ProposalElectorateInvalid,
+    /// When a proposal's voting rules or closing date don't make sense.
+    #[error("proposal is invalid")]
+    ProposalInvalid,
+    /// When we try to close a proposal before its voting period has ended.
+    #[error("proposal is still open")]
+    ProposalStillOpen,
+    /// When the transaction run for a passing proposal isn't the transaction
+    /// that was proposed.
+    #[error("transaction run does not match the proposal")]
+    ProposalTransactionMismatch,
+    /// When a resource spec is linked to more than one resource group, so we
+    /// can't tell which group its costs belong to.
+    #[error("resource spec {0:?} is in more than one resource group")]
+    ResourceGroupConflict(ResourceSpecID),
+    /// When reverting a set of modifications that updated or deleted a model,
+    /// but we weren't given the copy of the model from before it was applied.
+    #[error("missing the original copy of model {0:?}")]
+    RevertMissingOriginal(ModelRef),
+    /// When applying a modification whose model has changed since it was read
+    /// (another transaction got there first). Holds the model, the version we
+    /// expected, and the version found.
+    #[error("model {0:?} has been modified (expected version {1}, found {2})")]
+    VersionConflict(ModelRef, u64, u64),
+    /// When an update or delete doesn't say which version of the model it
+    /// expects to replace (for instance, one deserialized without its
+    /// `expected_version`), so we can't check it for conflicts.
+    #[error("modification of model {0:?} is missing its expected version")]
+    VersionMissing(ModelRef),
+    /// When delegating a vote would create a loop of delegations (ie, A
+    /// delegates to B, who delegates back to A).
+    #[error("vote delegation creates a cycle")]
+    VoteDelegationCycle,
+    /// When a vote delegation doesn't make sense, like delegating to yourself
+    /// or to someone outside the company.
+    #[error("vote delegation is invalid")]
+    VoteDelegationInvalid,
     /// When we try to convert an AgentID to another ID type but it fails (like
     /// `let company_id: CompanyID = AgentID::UserID(user_id).try_from()?;`).
     #[error("AgentID is the wrong type")]
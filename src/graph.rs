@@ -0,0 +1,592 @@
+//! Renders the economic network as a directed graph, so we can draw pictures
+//! of how costs flow between companies.
+//!
+//! [build] takes a set of models and turns processes and resources into
+//! nodes, and events into edges labeled with their action and the costs they
+//! moved. Nodes are grouped into clusters by the company that runs them (a
+//! process's company, or the company that has custody of a resource). Events
+//! that don't have a process or resource on one end (ie, a member's labor
+//! going into a process) link to the agent instead, which gets its own node.
+//!
+//! The graph can be rendered as [GraphViz DOT][dot] via [Graph::to_dot] or as
+//! a JSON object of `nodes`, `edges`, and `clusters` (for web visualizers) via
+//! [Graph::to_json].
+//!
+//! ```rust
+//! use basis_core::{
+//!     graph::{self, GraphFilter},
+//!     models::company::CompanyID,
+//! };
+//! use chrono::{TimeZone, Utc};
+//!
+//! let filter = GraphFilter::new()
+//!     .since(Utc.ymd(2020, 1, 1).and_hms(0, 0, 0))
+//!     .until(Utc.ymd(2021, 1, 1).and_hms(0, 0, 0))
+//!     .company(CompanyID::new("widgetco"));
+//! let graph = graph::build(&vec![], &filter);
+//! assert_eq!(graph.to_dot(), "digraph basis {\n    rankdir=LR;\n}\n");
+//! assert_eq!(graph.to_json()["nodes"].as_array().unwrap().len(), 0);
+//! ```
+//!
+//! [dot]: https://graphviz.org/doc/info/lang.html
+//! [build]: fn.build.html
+//! [Graph::to_dot]: struct.Graph.html#method.to_dot
+//! [Graph::to_json]: struct.Graph.html#method.to_json
+
+use chrono::{DateTime, Utc};
+use crate::{
+    costs::Costs,
+    models::{
+        Model,
+        company::CompanyID,
+        lib::agent::AgentID,
+    },
+};
+use getset::Getters;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Limits which parts of the network end up in the graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphFilter {
+    /// Only include events happening at or after this time
+    since: Option<DateTime<Utc>>,
+    /// Only include events happening before this time
+    until: Option<DateTime<Utc>>,
+    /// Only include the processes and resources of this company, along with
+    /// the events that touch them
+    company: Option<CompanyID>,
+}
+
+impl GraphFilter {
+    /// Create a filter that lets everything through
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only include events happening at or after `since`
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only include events happening before `until`
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Only include the parts of the network that touch the given company
+    pub fn company(mut self, company_id: CompanyID) -> Self {
+        self.company = Some(company_id);
+        self
+    }
+
+    /// Whether an event happening at `time` passes the filter
+    fn in_range(&self, time: &DateTime<Utc>) -> bool {
+        self.since.map(|since| time >= &since).unwrap_or(true) &&
+            self.until.map(|until| time < &until).unwrap_or(true)
+    }
+
+    /// Whether a node run by `company_id` passes the filter
+    fn in_company(&self, company_id: Option<&CompanyID>) -> bool {
+        match self.company.as_ref() {
+            Some(filter_id) => company_id == Some(filter_id),
+            None => true,
+        }
+    }
+}
+
+/// What a node in the graph represents.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    /// A process
+    Process,
+    /// A resource
+    Resource,
+    /// An agent (company, member, or user) on one end of an event
+    Agent,
+}
+
+/// A node in the graph.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Node {
+    /// The node's ID, ie `process/1234`
+    id: String,
+    /// What the node represents
+    kind: NodeKind,
+    /// A human-readable label (ie, the process's name)
+    label: String,
+    /// The company (cluster) this node belongs to, if any
+    company: Option<CompanyID>,
+}
+
+/// An edge in the graph, which represents an event.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Edge {
+    /// The event's ID
+    id: String,
+    /// The ID of the node the edge starts at
+    source: String,
+    /// The ID of the node the edge ends at
+    target: String,
+    /// The event's action, ie `consume`
+    action: String,
+    /// The costs the event moved, if any
+    costs: Option<Costs>,
+    /// A human-readable label, made up of the action and costs
+    label: String,
+}
+
+/// A group of nodes belonging to one company.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Cluster {
+    /// The company's ID
+    id: CompanyID,
+    /// The company's name
+    label: String,
+}
+
+/// A directed graph of the economic network.
+#[derive(Clone, Debug, Default, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Graph {
+    /// The graph's nodes (processes, resources, and agents)
+    nodes: Vec<Node>,
+    /// The graph's edges (events)
+    edges: Vec<Edge>,
+    /// The companies that nodes are grouped into
+    clusters: Vec<Cluster>,
+}
+
+impl Graph {
+    /// Add a node, unless we already have it
+    fn add_node(&mut self, node: Node) {
+        if !self.nodes.iter().any(|existing| existing.id == node.id) {
+            self.nodes.push(node);
+        }
+    }
+
+    /// Render the graph as GraphViz DOT.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph basis {\n    rankdir=LR;\n");
+        let node_line = |node: &Node, indent: &str| {
+            let shape = match node.kind {
+                NodeKind::Process => "box",
+                NodeKind::Resource => "ellipse",
+                NodeKind::Agent => "diamond",
+            };
+            format!("{}{} [label={}, shape={}];\n", indent, quote(&node.id), quote(&node.label), shape)
+        };
+        for cluster in &self.clusters {
+            dot.push_str(&format!("    subgraph {} {{\n", quote(&format!("cluster_{}", cluster.id.as_str()))));
+            dot.push_str(&format!("        label={};\n", quote(&cluster.label)));
+            for node in self.nodes.iter().filter(|node| node.company.as_ref() == Some(&cluster.id)) {
+                dot.push_str(&node_line(node, "        "));
+            }
+            dot.push_str("    }\n");
+        }
+        for node in self.nodes.iter().filter(|node| node.company.is_none()) {
+            dot.push_str(&node_line(node, "    "));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!("    {} -> {} [label={}];\n", quote(&edge.source), quote(&edge.target), quote(&edge.label)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the graph as a JSON object of `nodes`, `edges`, and `clusters`.
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// Quote a string for use as a DOT ID.
+fn quote(val: &str) -> String {
+    format!("\"{}\"", val.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Turn a set of costs into lines like `labor.machinist: 10`, sorted.
+fn costs_lines(costs: &Costs) -> Vec<String> {
+    let buckets = [
+        ("resource", costs.resource().iter().map(|(k, v)| (k.as_str(), v)).collect::<Vec<_>>()),
+        ("labor", costs.labor().iter().map(|(k, v)| (k.as_str(), v)).collect::<Vec<_>>()),
+        ("labor_hours", costs.labor_hours().iter().map(|(k, v)| (k.as_str(), v)).collect::<Vec<_>>()),
+        ("currency", costs.currency().iter().map(|(k, v)| (k.as_str(), v)).collect::<Vec<_>>()),
+    ];
+    let mut lines = Vec::new();
+    for (bucket, vals) in buckets.iter() {
+        let mut bucket_lines = vals.iter()
+            .filter(|(_, val)| !val.is_zero())
+            .map(|(key, val)| format!("{}.{}: {}", bucket, key, val.normalize()))
+            .collect::<Vec<_>>();
+        bucket_lines.sort();
+        lines.extend(bucket_lines);
+    }
+    lines
+}
+
+/// Build a graph of the network out of a set of models (processes,
+/// resources, events, companies, and agents; anything else is ignored).
+///
+/// The time range in the filter applies to events. The graph contains every
+/// process and resource belonging to the filtered company (or all of them if
+/// no company is given), and any nodes at either end of the events that pass
+/// the filter. When filtering by company, only events that touch one of the
+/// company's nodes are included.
+pub fn build(models: &[Model], filter: &GraphFilter) -> Graph {
+    let mut labels = HashMap::new();
+    let mut companies = HashMap::new();
+    for model in models {
+        match model {
+            Model::Company(company) => {
+                labels.insert(format!("company/{}", company.id().as_str()), company.inner().name().clone());
+                companies.insert(company.id().clone(), company.inner().name().clone());
+            }
+            Model::Member(member) => { labels.insert(format!("member/{}", member.id().as_str()), member.id().as_str().to_string()); }
+            Model::Process(process) => { labels.insert(format!("process/{}", process.id().as_str()), process.inner().name().clone()); }
+            Model::Resource(resource) => {
+                let name = resource.inner().name().clone().unwrap_or_else(|| resource.id().as_str().to_string());
+                labels.insert(format!("resource/{}", resource.id().as_str()), name);
+            }
+            Model::User(user) => { labels.insert(format!("user/{}", user.id().as_str()), user.name().clone()); }
+            _ => {}
+        }
+    }
+    // which company each process/resource belongs to
+    let mut owners = HashMap::new();
+    for model in models {
+        match model {
+            Model::Process(process) => { owners.insert(format!("process/{}", process.id().as_str()), process.company_id().clone()); }
+            Model::Resource(resource) => {
+                let owner = match (resource.in_custody_of(), resource.inner().primary_accountable()) {
+                    (AgentID::CompanyID(company_id), _) => Some(company_id),
+                    (_, Some(AgentID::CompanyID(company_id))) => Some(company_id),
+                    _ => None,
+                };
+                if let Some(owner) = owner {
+                    owners.insert(format!("resource/{}", resource.id().as_str()), owner.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    let make_node = |id: String, kind: NodeKind| {
+        let company = match &kind {
+            NodeKind::Agent => id.strip_prefix("company/").map(CompanyID::from),
+            _ => owners.get(&id).cloned(),
+        };
+        let label = labels.get(&id).cloned()
+            .unwrap_or_else(|| id.split_once('/').map(|(_, id)| id).unwrap_or(&id).to_string());
+        Node { id, kind, label, company }
+    };
+
+    let mut graph = Graph::default();
+    for model in models {
+        let id = match model {
+            Model::Process(process) => format!("process/{}", process.id().as_str()),
+            Model::Resource(resource) => format!("resource/{}", resource.id().as_str()),
+            _ => continue,
+        };
+        let node = make_node(id, if matches!(model, Model::Process(_)) { NodeKind::Process } else { NodeKind::Resource });
+        if filter.in_company(node.company.as_ref()) {
+            graph.add_node(node);
+        }
+    }
+    for model in models {
+        let event = match model {
+            Model::Event(event) => event,
+            _ => continue,
+        };
+        let inner = event.inner();
+        let time = inner.has_point_in_time()
+            .or(*inner.has_beginning())
+            .unwrap_or(*event.created());
+        if !filter.in_range(&time) {
+            continue;
+        }
+        let agent_node = |agent: &AgentID| {
+            let id = match agent {
+                AgentID::CompanyID(id) => format!("company/{}", id.as_str()),
+                AgentID::MemberID(id) => format!("member/{}", id.as_str()),
+                AgentID::UserID(id) => format!("user/{}", id.as_str()),
+            };
+            make_node(id, NodeKind::Agent)
+        };
+        let source = match (inner.output_of(), inner.resource_inventoried_as()) {
+            (Some(process_id), _) => make_node(format!("process/{}", process_id.as_str()), NodeKind::Process),
+            (None, Some(resource_id)) => make_node(format!("resource/{}", resource_id.as_str()), NodeKind::Resource),
+            (None, None) => agent_node(inner.provider()),
+        };
+        let target = match (inner.input_of(), inner.to_resource_inventoried_as(), inner.output_of(), inner.resource_inventoried_as()) {
+            (Some(process_id), ..) => make_node(format!("process/{}", process_id.as_str()), NodeKind::Process),
+            (None, Some(resource_id), ..) => make_node(format!("resource/{}", resource_id.as_str()), NodeKind::Resource),
+            // a process producing (or modifying) a resource
+            (None, None, Some(_), Some(resource_id)) => make_node(format!("resource/{}", resource_id.as_str()), NodeKind::Resource),
+            _ => agent_node(inner.receiver()),
+        };
+        if !filter.in_company(source.company.as_ref()) && !filter.in_company(target.company.as_ref()) {
+            continue;
+        }
+        let action = serde_json::to_value(inner.action()).ok()
+            .and_then(|action| action.as_str().map(String::from))
+            .unwrap_or_default();
+        let mut label = vec![action.clone()];
+        if let Some(costs) = event.move_costs() {
+            label.extend(costs_lines(costs));
+        }
+        graph.edges.push(Edge {
+            id: event.id().as_str().to_string(),
+            source: source.id.clone(),
+            target: target.id.clone(),
+            action,
+            costs: event.move_costs().clone(),
+            label: label.join("\n"),
+        });
+        graph.add_node(source);
+        graph.add_node(target);
+    }
+    let mut clusters: Vec<CompanyID> = Vec::new();
+    for company_id in graph.nodes.iter().filter_map(|node| node.company.as_ref()) {
+        if !clusters.contains(company_id) {
+            clusters.push(company_id.clone());
+        }
+    }
+    graph.clusters = clusters.into_iter()
+        .map(|company_id| {
+            let label = companies.get(&company_id).cloned().unwrap_or_else(|| company_id.as_str().to_string());
+            Cluster { id: company_id, label }
+        })
+        .collect();
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{
+            agreement::AgreementID,
+            event::{Event, EventID},
+            lib::agent::Agent,
+            member::MemberID,
+            occupation::OccupationID,
+            process::ProcessID,
+            resource::ResourceID,
+            resource_spec::ResourceSpecID,
+            user::UserID,
+        },
+        util::{self, test::*},
+    };
+    use chrono::Duration;
+    use om2::{Measure, Unit};
+    use rust_decimal_macros::*;
+    use serde_json::json;
+    use url::Url;
+    use vf_rs::vf;
+
+    type EventInner = vf::EconomicEvent<Url, AgentID, ProcessID, AgentID, AgreementID, (), ResourceSpecID, ResourceID, EventID>;
+
+    fn make_event(id: &str, inner: EventInner, costs: Option<Costs>, now: &DateTime<Utc>) -> Model {
+        Event::builder()
+            .id(id)
+            .inner(inner)
+            .move_costs(costs)
+            .active(true)
+            .created(*now)
+            .updated(*now)
+            .build().unwrap()
+            .into()
+    }
+
+    fn make_models(now: &DateTime<Utc>) -> Vec<Model> {
+        let earlier = *now - Duration::days(7);
+        let widgetco = make_company(&"widgetco".into(), "widget co", now);
+        let gadgetco = make_company(&"gadgetco".into(), "gadget \"co\"", now);
+        let user = make_user(&UserID::new("jerry"), None, now);
+        let member = make_member_worker(&MemberID::new("jerry-worker"), user.id(), widgetco.id(), &OccupationID::new("machinist"), vec![], now);
+        let process = make_process(&"make-widgets".into(), widgetco.id(), "make widgets", &Costs::new(), now);
+        let process2 = make_process(&"make-gadgets".into(), gadgetco.id(), "make gadgets", &Costs::new(), now);
+        let mut iron = make_resource(&"iron".into(), widgetco.id(), &Measure::new(dec!(10), Unit::Kilogram), &Costs::new_with_resource("iron", dec!(10)), now);
+        iron.inner_mut().set_name(Some("iron".into()));
+        let widgets = make_resource(&"widgets".into(), widgetco.id(), &Measure::new(dec!(0), Unit::One), &Costs::new(), now);
+        let widgets2 = make_resource(&"gadgetco-widgets".into(), gadgetco.id(), &Measure::new(dec!(0), Unit::One), &Costs::new(), now);
+
+        let consume = make_event(
+            "consume-iron",
+            vf::EconomicEvent::builder()
+                .action(vf::Action::Consume)
+                .input_of(Some(process.id().clone()))
+                .provider(widgetco.agent_id())
+                .receiver(widgetco.agent_id())
+                .resource_inventoried_as(Some(iron.id().clone()))
+                .has_point_in_time(*now)
+                .build().unwrap(),
+            Some(Costs::new_with_resource("iron", dec!(4.0))),
+            now,
+        );
+        let work = make_event(
+            "work",
+            vf::EconomicEvent::builder()
+                .action(vf::Action::Work)
+                .input_of(Some(process.id().clone()))
+                .provider(member.agent_id())
+                .receiver(widgetco.agent_id())
+                .has_point_in_time(*now)
+                .build().unwrap(),
+            Some(Costs::new_with_labor("machinist", dec!(12))),
+            now,
+        );
+        let produce = make_event(
+            "produce-widgets",
+            vf::EconomicEvent::builder()
+                .action(vf::Action::Produce)
+                .output_of(Some(process.id().clone()))
+                .provider(widgetco.agent_id())
+                .receiver(widgetco.agent_id())
+                .resource_inventoried_as(Some(widgets.id().clone()))
+                .has_point_in_time(*now)
+                .build().unwrap(),
+            Some(Costs::new_with_resource("iron", dec!(4.0)) + Costs::new_with_labor("machinist", dec!(12))),
+            now,
+        );
+        let transfer = make_event(
+            "transfer-widgets",
+            vf::EconomicEvent::builder()
+                .action(vf::Action::Transfer)
+                .provider(widgetco.agent_id())
+                .receiver(gadgetco.agent_id())
+                .resource_inventoried_as(Some(widgets.id().clone()))
+                .to_resource_inventoried_as(Some(widgets2.id().clone()))
+                .has_point_in_time(*now)
+                .build().unwrap(),
+            Some(Costs::new_with_labor("machinist", dec!(6))),
+            now,
+        );
+        let old_consume = make_event(
+            "consume-widgets",
+            vf::EconomicEvent::builder()
+                .action(vf::Action::Consume)
+                .input_of(Some(process2.id().clone()))
+                .provider(gadgetco.agent_id())
+                .receiver(gadgetco.agent_id())
+                .resource_inventoried_as(Some(widgets2.id().clone()))
+                .has_point_in_time(earlier)
+                .build().unwrap(),
+            None,
+            &earlier,
+        );
+        vec![
+            widgetco.into(), gadgetco.into(), user.into(), member.into(),
+            process.into(), process2.into(), iron.into(), widgets.into(), widgets2.into(),
+            consume, work, produce, transfer, old_consume,
+        ]
+    }
+
+    fn edges(graph: &Graph) -> Vec<(&str, &str, &str)> {
+        graph.edges().iter()
+            .map(|edge| (edge.source().as_str(), edge.target().as_str(), edge.label().as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn builds() {
+        let now = util::time::now();
+        let models = make_models(&now);
+        let graph = build(&models, &GraphFilter::new());
+        let nodes = graph.nodes().iter()
+            .map(|node| (node.id().as_str(), node.label().as_str(), node.company().as_ref().map(|id| id.as_str())))
+            .collect::<Vec<_>>();
+        assert_eq!(nodes, vec![
+            ("process/make-widgets", "make widgets", Some("widgetco")),
+            ("process/make-gadgets", "make gadgets", Some("gadgetco")),
+            ("resource/iron", "iron", Some("widgetco")),
+            ("resource/widgets", "widgets", Some("widgetco")),
+            ("resource/gadgetco-widgets", "gadgetco-widgets", Some("gadgetco")),
+            ("member/jerry-worker", "jerry-worker", None),
+        ]);
+        assert_eq!(graph.nodes()[5].kind(), &NodeKind::Agent);
+        assert_eq!(edges(&graph), vec![
+            ("resource/iron", "process/make-widgets", "consume\nresource.iron: 4"),
+            ("member/jerry-worker", "process/make-widgets", "work\nlabor.machinist: 12"),
+            ("process/make-widgets", "resource/widgets", "produce\nresource.iron: 4\nlabor.machinist: 12"),
+            ("resource/widgets", "resource/gadgetco-widgets", "transfer\nlabor.machinist: 6"),
+            ("resource/gadgetco-widgets", "process/make-gadgets", "consume"),
+        ]);
+        let clusters = graph.clusters().iter()
+            .map(|cluster| (cluster.id().as_str(), cluster.label().as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(clusters, vec![("widgetco", "widget co"), ("gadgetco", "gadget \"co\"")]);
+    }
+
+    #[test]
+    fn filters() {
+        let now = util::time::now();
+        let models = make_models(&now);
+
+        let graph = build(&models, &GraphFilter::new().since(now - Duration::days(1)));
+        assert_eq!(graph.edges().len(), 4);
+        assert!(graph.edges().iter().all(|edge| edge.id() != "consume-widgets"));
+        let graph = build(&models, &GraphFilter::new().until(now - Duration::days(1)));
+        assert_eq!(edges(&graph), vec![("resource/gadgetco-widgets", "process/make-gadgets", "consume")]);
+        // the time range only applies to events
+        assert_eq!(graph.nodes().len(), 5);
+
+        let graph = build(&models, &GraphFilter::new().company("gadgetco".into()));
+        let nodes = graph.nodes().iter().map(|node| node.id().as_str()).collect::<Vec<_>>();
+        assert_eq!(nodes, vec!["process/make-gadgets", "resource/gadgetco-widgets", "resource/widgets"]);
+        assert_eq!(edges(&graph), vec![
+            ("resource/widgets", "resource/gadgetco-widgets", "transfer\nlabor.machinist: 6"),
+            ("resource/gadgetco-widgets", "process/make-gadgets", "consume"),
+        ]);
+        assert_eq!(graph.clusters().len(), 2);
+
+        let graph = build(&models, &GraphFilter::new().company("gadgetco".into()).since(now - Duration::days(1)));
+        assert_eq!(edges(&graph), vec![("resource/widgets", "resource/gadgetco-widgets", "transfer\nlabor.machinist: 6")]);
+    }
+
+    #[test]
+    fn renders() {
+        let now = util::time::now();
+        let models = make_models(&now);
+        let graph = build(&models, &GraphFilter::new().company("gadgetco".into()));
+        assert_eq!(graph.to_dot(), r#"digraph basis {
+    rankdir=LR;
+    subgraph "cluster_gadgetco" {
+        label="gadget \"co\"";
+        "process/make-gadgets" [label="make gadgets", shape=box];
+        "resource/gadgetco-widgets" [label="gadgetco-widgets", shape=ellipse];
+    }
+    subgraph "cluster_widgetco" {
+        label="widget co";
+        "resource/widgets" [label="widgets", shape=ellipse];
+    }
+    "resource/widgets" -> "resource/gadgetco-widgets" [label="transfer\nlabor.machinist: 6"];
+    "resource/gadgetco-widgets" -> "process/make-gadgets" [label="consume"];
+}
+"#);
+        let json = graph.to_json();
+        assert_eq!(json["nodes"][0], json!({
+            "id": "process/make-gadgets",
+            "kind": "process",
+            "label": "make gadgets",
+            "company": "gadgetco",
+        }));
+        assert_eq!(json["edges"][0], json!({
+            "id": "transfer-widgets",
+            "source": "resource/widgets",
+            "target": "resource/gadgetco-widgets",
+            "action": "transfer",
+            "costs": {"labor": {"machinist": 6.0}},
+            "label": "transfer\nlabor.machinist: 6",
+        }));
+        assert_eq!(json["edges"][1]["costs"], Value::Null);
+        assert_eq!(json["clusters"][1], json!({"id": "widgetco", "label": "widget co"}));
+    }
+}
@@ -0,0 +1,442 @@
+//! Models link to each other by ID (a process's `company_id`, a member's
+//! subject and object, an event's `input_of`), but nothing stops those IDs from
+//! pointing at models that don't exist. This module checks a collection of
+//! models for broken links, and is meant to be run against exported data (ie,
+//! in CI) rather than as part of any transaction.
+//!
+//! [validate] reports, for each link between models:
+//!
+//! - links to models that aren't in the collection (`IssueKind::Dangling`)
+//! - links to models that are deleted (`IssueKind::Deleted`) or inactive
+//!   (`IssueKind::Inactive`)
+//! - `AgentID` links that point at the wrong kind of agent
+//!   (`IssueKind::WrongAgentType`), like a resource in the custody of a user
+//!
+//! Some links are historical: they record what something pointed at when it
+//! happened (an event's process, an audit record's user), so it's expected
+//! that the models they point to are deleted or deactivated later on. These,
+//! and any links from models that are deleted themselves, are only checked for
+//! dangling references and agent types.
+//!
+//! Vote users and members are created on the fly when a vote runs a
+//! transaction and are never stored, so links to them are not checked.
+//!
+//! [validate]: fn.validate.html
+
+use crate::models::{
+    Model,
+    ModelRef,
+    lib::agent::AgentID,
+    member::MemberClass,
+    proposal::ProposalScope,
+};
+use getset::Getters;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+/// What's wrong with a link between two models.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum IssueKind {
+    /// The linked model doesn't exist
+    Dangling,
+    /// The linked model has been deleted
+    Deleted,
+    /// The linked model is inactive
+    Inactive,
+    /// An `AgentID` link points to the wrong kind of agent
+    WrongAgentType,
+}
+
+/// A problem with one of a model's links.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Issue {
+    /// The model doing the linking
+    model: ModelRef,
+    /// The field holding the link, ie `inner.input_of` or `participants[1]`
+    field: String,
+    /// The model being linked to
+    target: ModelRef,
+    /// What's wrong with the link
+    kind: IssueKind,
+}
+
+/// The kinds of agent an `AgentID` field may hold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Agents {
+    /// Only companies
+    Company,
+    /// Companies or members
+    CompanyOrMember,
+    /// Only users
+    User,
+    /// Users or members
+    UserOrMember,
+}
+
+impl Agents {
+    /// Whether the given agent is allowed
+    fn allows(&self, agent: &AgentID) -> bool {
+        matches!(
+            (self, agent),
+            (Agents::Company, AgentID::CompanyID(_)) |
+            (Agents::CompanyOrMember, AgentID::CompanyID(_)) |
+            (Agents::CompanyOrMember, AgentID::MemberID(_)) |
+            (Agents::User, AgentID::UserID(_)) |
+            (Agents::UserOrMember, AgentID::UserID(_)) |
+            (Agents::UserOrMember, AgentID::MemberID(_))
+        )
+    }
+}
+
+/// A link from a model to another model.
+struct Link {
+    /// The field holding the link
+    field: String,
+    /// The model being linked to
+    target: ModelRef,
+    /// Whether the link is historical (only checked for dangling)
+    historical: bool,
+    /// Whether the link passed its agent type check (always `true` for
+    /// non-agent links)
+    agent_ok: bool,
+}
+
+/// Collects the links a model makes.
+struct Links {
+    /// The links collected so far
+    links: Vec<Link>,
+    /// Whether the links we're adding are historical
+    historical: bool,
+}
+
+impl Links {
+    fn new() -> Self {
+        Self { links: Vec::new(), historical: false }
+    }
+
+    /// Add a link to a model by its ID
+    fn id<F: Into<String>, T: Into<ModelRef> + Clone>(&mut self, field: F, id: &T) {
+        self.links.push(Link { field: field.into(), target: id.clone().into(), historical: self.historical, agent_ok: true });
+    }
+
+    /// Add an optional link to a model by its ID
+    fn maybe_id<F: Into<String>, T: Into<ModelRef> + Clone>(&mut self, field: F, id: &Option<T>) {
+        if let Some(id) = id {
+            self.id(field, id);
+        }
+    }
+
+    /// Add a link to an agent, noting whether it's the right kind of agent
+    fn agent<F: Into<String>>(&mut self, field: F, agent: &AgentID, allowed: Agents) {
+        self.links.push(Link { field: field.into(), target: agent.clone().into(), historical: self.historical, agent_ok: allowed.allows(agent) });
+    }
+
+    /// Add an optional link to an agent
+    fn maybe_agent<F: Into<String>>(&mut self, field: F, agent: &Option<AgentID>, allowed: Agents) {
+        if let Some(agent) = agent {
+            self.agent(field, agent, allowed);
+        }
+    }
+
+    /// Add a list of links to agents
+    fn agents(&mut self, field: &str, agents: &[AgentID], allowed: Agents) {
+        for (i, agent) in agents.iter().enumerate() {
+            self.agent(format!("{}[{}]", field, i), agent, allowed);
+        }
+    }
+}
+
+/// Grab all the links a model makes to other models.
+fn links(model: &Model) -> Vec<Link> {
+    let mut links = Links::new();
+    match model {
+        Model::Account(account) => {
+            links.id("user_id", account.user_id());
+        }
+        Model::Agreement(agreement) => {
+            links.agents("participants", agreement.participants(), Agents::CompanyOrMember);
+            links.historical = true;
+            for (i, signature) in agreement.signatures().iter().enumerate() {
+                links.agent(format!("signatures[{}].participant", i), signature.participant(), Agents::CompanyOrMember);
+                links.id(format!("signatures[{}].user_id", i), signature.user_id());
+                links.id(format!("signatures[{}].member_id", i), signature.member_id());
+            }
+        }
+        Model::AuditRecord(record) => {
+            links.historical = true;
+            if !record.vote() {
+                links.id("user_id", record.user_id());
+                links.maybe_id("member_id", record.member_id());
+            }
+            links.maybe_id("company_id", record.company_id());
+            for (i, affected) in record.affected().iter().enumerate() {
+                links.id(format!("affected[{}]", i), affected);
+            }
+        }
+        Model::Ballot(ballot) => {
+            links.historical = true;
+            links.id("proposal_id", ballot.proposal_id());
+            links.agent("voter", ballot.voter(), Agents::UserOrMember);
+        }
+        Model::Commitment(commitment) => {
+            let inner = commitment.inner();
+            links.maybe_id("inner.clause_of", inner.clause_of());
+            links.agents("inner.in_scope_of", inner.in_scope_of(), Agents::Company);
+            links.maybe_id("inner.input_of", inner.input_of());
+            links.maybe_id("inner.output_of", inner.output_of());
+            links.agent("inner.provider", inner.provider(), Agents::CompanyOrMember);
+            links.agent("inner.receiver", inner.receiver(), Agents::CompanyOrMember);
+            links.maybe_id("inner.resource_conforms_to", inner.resource_conforms_to());
+            links.maybe_id("inner.resource_inventoried_as", inner.resource_inventoried_as());
+        }
+        Model::Company(_) => {}
+        Model::Currency(_) => {}
+        Model::Event(event) => {
+            let inner = event.inner();
+            links.historical = true;
+            links.agents("inner.in_scope_of", inner.in_scope_of(), Agents::Company);
+            links.maybe_id("inner.input_of", inner.input_of());
+            links.maybe_id("inner.output_of", inner.output_of());
+            links.agent("inner.provider", inner.provider(), Agents::CompanyOrMember);
+            links.agent("inner.receiver", inner.receiver(), Agents::CompanyOrMember);
+            links.maybe_id("inner.realization_of", inner.realization_of());
+            links.maybe_id("inner.resource_conforms_to", inner.resource_conforms_to());
+            links.maybe_id("inner.resource_inventoried_as", inner.resource_inventoried_as());
+            links.maybe_id("inner.to_resource_inventoried_as", inner.to_resource_inventoried_as());
+            links.maybe_id("inner.triggered_by", inner.triggered_by());
+        }
+        Model::Intent(intent) => {
+            let inner = intent.inner();
+            links.agents("inner.in_scope_of", inner.in_scope_of(), Agents::Company);
+            links.maybe_id("inner.input_of", inner.input_of());
+            links.maybe_id("inner.output_of", inner.output_of());
+            links.maybe_agent("inner.provider", inner.provider(), Agents::CompanyOrMember);
+            links.maybe_agent("inner.receiver", inner.receiver(), Agents::CompanyOrMember);
+            links.maybe_id("inner.resource_conforms_to", inner.resource_conforms_to());
+            links.maybe_id("inner.resource_inventoried_as", inner.resource_inventoried_as());
+        }
+        Model::Member(member) => {
+            // vote members are never stored, and neither are their users
+            if !member.vote() {
+                let subject = match member.class() {
+                    MemberClass::Company(_) => Agents::Company,
+                    MemberClass::User(_) | MemberClass::Worker(_) => Agents::User,
+                };
+                links.agent("inner.subject", member.inner().subject(), subject);
+            }
+            links.agent("inner.object", member.inner().object(), Agents::Company);
+            if let MemberClass::Worker(worker) = member.class() {
+                links.id("class.occupation", worker.occupation());
+                if let Some(compensation) = worker.compensation() {
+                    links.id("class.compensation.pay_into", compensation.pay_into());
+                }
+            }
+            for (i, delegation) in member.delegations().iter().enumerate() {
+                links.id(format!("delegations[{}].grantor", i), delegation.grantor());
+            }
+            for (i, proxy) in member.vote_proxies().iter().enumerate() {
+                links.id(format!("vote_proxies[{}].delegate", i), proxy.delegate());
+            }
+        }
+        Model::Occupation(_) => {}
+        Model::Payroll(payroll) => {
+            links.historical = true;
+            links.id("member_id", payroll.member_id());
+            links.id("company_id", payroll.company_id());
+            links.id("account_id", payroll.account_id());
+            for (i, event_id) in payroll.events().iter().enumerate() {
+                links.id(format!("events[{}]", i), event_id);
+            }
+        }
+        Model::Process(process) => {
+            links.id("company_id", process.company_id());
+            links.maybe_id("inner.based_on", process.inner().based_on());
+            links.agents("inner.in_scope_of", process.inner().in_scope_of(), Agents::Company);
+        }
+        Model::ProcessSpec(spec) => {
+            links.id("company_id", spec.company_id());
+        }
+        Model::Proposal(proposal) => {
+            if let ProposalScope::Company(company_id) = proposal.scope() {
+                links.id("scope", company_id);
+            }
+        }
+        Model::Resource(resource) => {
+            let inner = resource.inner();
+            links.id("inner.conforms_to", inner.conforms_to());
+            links.maybe_id("inner.contained_in", inner.contained_in());
+            links.maybe_agent("inner.primary_accountable", inner.primary_accountable(), Agents::Company);
+            links.maybe_id("inner.stage", inner.stage());
+            links.agent("in_custody_of", resource.in_custody_of(), Agents::Company);
+        }
+        Model::ResourceGroup(_) => {}
+        Model::ResourceGroupLink(link) => {
+            links.id("group_id", link.group_id());
+            links.id("resource_spec_id", link.resource_spec_id());
+        }
+        Model::ResourceSpec(spec) => {
+            links.id("company_id", spec.company_id());
+        }
+        Model::User(_) => {}
+    }
+    links.links
+}
+
+/// Check the links between a collection of models, returning any issues found
+/// (in the order of the models given).
+///
+/// ```rust
+/// use basis_core::{
+///     integrity::{self, IssueKind},
+///     models::{Op, company::CompanyID, user::UserID},
+///     transactions,
+/// };
+/// use chrono::Utc;
+///
+/// let now = Utc::now();
+/// let mods = transactions::user::create(UserID::create(), "andrew@lyonbros.com", "andrew", true, &now).unwrap();
+/// let models = mods.into_vec().into_iter().map(|modification| modification.into_pair().1).collect::<Vec<_>>();
+/// assert_eq!(integrity::validate(&models), vec![]);
+///
+/// // the audit record points at a user that isn't there
+/// let issues = integrity::validate(&models[1..]);
+/// assert_eq!(issues[0].kind(), &IssueKind::Dangling);
+/// assert_eq!(issues[0].field(), "user_id");
+/// ```
+pub fn validate(models: &[Model]) -> Vec<Issue> {
+    let index = models.iter()
+        .map(|model| (ModelRef::from(model), model))
+        .collect::<HashMap<_, _>>();
+    let mut issues = Vec::new();
+    for model in models {
+        let model_ref = ModelRef::from(model);
+        let source_deleted = model.is_deleted();
+        for link in links(model) {
+            let mut issue = |kind: IssueKind| {
+                issues.push(Issue { model: model_ref.clone(), field: link.field.clone(), target: link.target.clone(), kind });
+            };
+            if !link.agent_ok {
+                issue(IssueKind::WrongAgentType);
+            }
+            match index.get(&link.target) {
+                None => issue(IssueKind::Dangling),
+                Some(_) if link.historical || source_deleted => {}
+                Some(target) if target.is_deleted() => issue(IssueKind::Deleted),
+                Some(target) if !target.is_active() => issue(IssueKind::Inactive),
+                Some(_) => {}
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::Role,
+        costs::Costs,
+        models::{
+            Modifications,
+            account::AccountID,
+            company::CompanyID,
+            lib::basis_model::Model as BasisModel,
+            member::{Compensation, Member, MemberClass, MemberID, MemberWorker},
+            occupation::OccupationID,
+            process::ProcessID,
+            resource::ResourceID,
+            resource_spec::ResourceSpecID,
+            user::UserID,
+        },
+        transactions,
+        util::{self, test::*},
+    };
+    use om2::{Measure, NumericUnion, Unit};
+    use rust_decimal_macros::*;
+    use std::convert::TryInto;
+
+    fn issue(model: ModelRef, field: &str, target: ModelRef, kind: IssueKind) -> Issue {
+        Issue { model, field: field.into(), target, kind }
+    }
+
+    fn models(mods: Modifications) -> Vec<Model> {
+        mods.into_vec().into_iter().map(|modification| modification.into_pair().1).collect()
+    }
+
+    #[test]
+    fn validates() {
+        let now = util::time::now();
+        let user = make_user(&UserID::create(), None, &now);
+        let company_id = CompanyID::create();
+        let member_id = MemberID::create();
+        let occupation_id = OccupationID::create();
+        let founder = transactions::company::Founder::new(member_id.clone(), MemberClass::Worker(MemberWorker::new(occupation_id.clone(), None)), true);
+        let mut all = models(transactions::company::create(&user, company_id.clone(), "jerry's widgets", "jerry@widgets.expert", true, founder, &now).unwrap());
+        all.push(user.clone().into());
+        let admin = make_user(&UserID::create(), Some(vec![Role::SuperAdmin]), &now);
+        all.push(models(transactions::occupation::create(&admin, occupation_id.clone(), "widgetmaker", "", true, &now).unwrap())[0].clone());
+        let process = make_process(&ProcessID::create(), &company_id, "make widgets", &Costs::new(), &now);
+        all.push(process.clone().into());
+        assert_eq!(validate(&all), vec![]);
+
+        // dangling references
+        let mut all2 = all.clone();
+        let mut member = all2[1].clone();
+        if let Model::Member(member) = &mut member {
+            let account_id = AccountID::create();
+            member.set_class(MemberClass::Worker(MemberWorker::new(occupation_id.clone(), Some(Compensation::new_hourly(dec!(32), account_id)))));
+            member.inner_mut().set_object(CompanyID::create().into());
+        }
+        all2[1] = member;
+        let issues = validate(&all2);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].model(), &ModelRef::Member(member_id.clone()));
+        assert_eq!(issues[0].field(), "inner.object");
+        assert_eq!(issues[0].kind(), &IssueKind::Dangling);
+        assert_eq!(issues[1].field(), "class.compensation.pay_into");
+        assert_eq!(issues[1].kind(), &IssueKind::Dangling);
+
+        // deleted and inactive references
+        let mut all3 = all.clone();
+        let mut company = all3[0].clone();
+        if let Model::Company(company) = &mut company {
+            company.set_deleted(Some(now));
+        }
+        all3[0] = company;
+        let mut occupation = all3[4].clone();
+        if let Model::Occupation(occupation) = &mut occupation {
+            BasisModel::set_active(occupation, false);
+        }
+        all3[4] = occupation;
+        let issues = validate(&all3);
+        assert_eq!(issues, vec![
+            issue(ModelRef::Member(member_id.clone()), "inner.object", ModelRef::Company(company_id.clone()), IssueKind::Deleted),
+            issue(ModelRef::Member(member_id.clone()), "class.occupation", ModelRef::Occupation(occupation_id.clone()), IssueKind::Inactive),
+            issue(ModelRef::Process(process.id().clone()), "company_id", ModelRef::Company(company_id.clone()), IssueKind::Deleted),
+        ]);
+
+        // deleted models (and historical links, like the audit record's) can
+        // point at deleted models
+        let mut process2 = process.clone();
+        process2.set_deleted(Some(now));
+        all3[5] = process2.into();
+        assert_eq!(validate(&all3).len(), 2);
+
+        // agent types
+        let mut all4 = all.clone();
+        let mut resource2 = make_resource(&ResourceID::create(), &company_id, &Measure::new(NumericUnion::Integer(5), Unit::One), &Costs::new(), &now);
+        resource2.inner_mut().set_conforms_to(ResourceSpecID::create());
+        resource2.set_in_custody_of(user.id().clone().into());
+        all4.push(resource2.clone().into());
+        let mut member: Member = all4[1].clone().try_into().unwrap();
+        member.inner_mut().set_subject(company_id.clone().into());
+        all4[1] = member.into();
+        let issues = validate(&all4);
+        assert_eq!(issues, vec![
+            issue(ModelRef::Member(member_id.clone()), "inner.subject", ModelRef::Company(company_id.clone()), IssueKind::WrongAgentType),
+            issue(ModelRef::Resource(resource2.id().clone()), "inner.conforms_to", ModelRef::ResourceSpec(resource2.inner().conforms_to().clone()), IssueKind::Dangling),
+            issue(ModelRef::Resource(resource2.id().clone()), "in_custody_of", ModelRef::User(user.id().clone()), IssueKind::WrongAgentType),
+        ]);
+    }
+}
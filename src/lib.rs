@@ -44,4 +44,9 @@ pub mod models;
 pub mod costs;
 pub mod transactions;
 pub mod system;
+pub mod store;
+pub mod log;
+pub mod integrity;
+pub mod valueflows;
+pub mod graph;
 
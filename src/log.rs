@@ -0,0 +1,377 @@
+//! An append-only log of [Modifications], for replicating changes between
+//! nodes.
+//!
+//! Each entry in the log wraps one set of modifications along with a sequence
+//! number, a timestamp, the hash of the entry before it, and a hash of its own
+//! contents. Since each entry's hash covers the previous entry's hash, the log
+//! forms a chain: editing, removing, or reordering any entry breaks the chain
+//! from that point on, which [LogVerifier] detects.
+//!
+//! The log is stored as newline-delimited JSON, one entry per line. Hashes are
+//! SHA-256 (hex encoded) over the entry's canonical encoding, which is JSON
+//! with object keys sorted and no whitespace. Entries keep the modifications
+//! exactly as they were written, so verifying a log never depends on how the
+//! modifications deserialize.
+//!
+//! ```rust
+//! use basis_core::{
+//!     log::{LogReader, LogVerifier, LogWriter},
+//!     models::user::UserID,
+//!     transactions,
+//! };
+//! use chrono::Utc;
+//!
+//! let now = Utc::now();
+//! let mut writer = LogWriter::new(Vec::new());
+//! let mods = transactions::user::create(UserID::create(), "andrew@lyonbros.com", "andrew", true, &now).unwrap();
+//! writer.write(&mods, &now).unwrap();
+//!
+//! let log = writer.into_inner();
+//! let mut verifier = LogVerifier::new();
+//! for entry in LogReader::new(log.as_slice()) {
+//!     let entry = entry.unwrap();
+//!     verifier.verify(&entry).unwrap();
+//!     assert_eq!(entry.modifications().unwrap(), mods);
+//! }
+//! ```
+//!
+//! [Modifications]: ../models/struct.Modifications.html
+//! [LogVerifier]: struct.LogVerifier.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    error::{Error, Result},
+    models::Modifications,
+};
+use getset::Getters;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, Write};
+
+/// The `prev_hash` of the first entry in a log.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// A single entry in the modification log.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// The entry's position in the log, starting at 0
+    #[getset(get = "pub")]
+    seq: u64,
+    /// When the entry was written
+    #[getset(get = "pub")]
+    timestamp: DateTime<Utc>,
+    /// The hash of the previous entry (`GENESIS_HASH` for the first entry)
+    #[getset(get = "pub")]
+    prev_hash: String,
+    /// The hash of this entry's contents (everything but this field)
+    #[getset(get = "pub")]
+    hash: String,
+    /// The serialized `Modifications` this entry records
+    modifications: Value,
+}
+
+impl LogEntry {
+    /// Create a new entry, following the entry with the given sequence number
+    /// and hash.
+    fn new(seq: u64, timestamp: DateTime<Utc>, prev_hash: String, modifications: &Modifications) -> Result<Self> {
+        let modifications = serde_json::to_value(modifications)
+            .map_err(|e| Error::LogWriteFailed(e.to_string()))?;
+        let mut entry = Self { seq, timestamp, prev_hash, hash: String::new(), modifications };
+        entry.hash = entry.compute_hash();
+        Ok(entry)
+    }
+
+    /// Deserialize the modifications this entry records.
+    pub fn modifications(&self) -> Result<Modifications> {
+        serde_json::from_value(self.modifications.clone())
+            .map_err(|e| Error::LogReadFailed(e.to_string()))
+    }
+
+    /// Hash the canonical encoding of this entry's contents.
+    fn compute_hash(&self) -> String {
+        let contents = json!({
+            "seq": self.seq,
+            "timestamp": self.timestamp,
+            "prev_hash": self.prev_hash,
+            "modifications": self.modifications,
+        });
+        Sha256::digest(canonical_json(&contents).as_bytes()).iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Encode a value as JSON with object keys sorted and no whitespace.
+///
+/// We sort the keys ourselves rather than relying on `serde_json`'s maps
+/// happening to be sorted, which stops being true if anything in the
+/// dependency graph enables its `preserve_order` feature.
+pub(crate) fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys = map.keys().collect::<Vec<_>>();
+            keys.sort();
+            let fields = keys.into_iter()
+                .map(|key| format!("{}:{}", Value::from(key.as_str()), canonical_json(&map[key])))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(vals) => {
+            let vals = vals.iter().map(canonical_json).collect::<Vec<_>>();
+            format!("[{}]", vals.join(","))
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Appends entries to a log.
+pub struct LogWriter<W: Write> {
+    /// Where we're writing to
+    writer: W,
+    /// The sequence number of the next entry
+    seq: u64,
+    /// The hash of the last entry written
+    prev_hash: String,
+}
+
+impl<W: Write> LogWriter<W> {
+    /// Start a new log.
+    pub fn new(writer: W) -> Self {
+        Self { writer, seq: 0, prev_hash: GENESIS_HASH.into() }
+    }
+
+    /// Continue an existing log, given the last entry in it (which should be
+    /// verified first).
+    pub fn resume(writer: W, last: &LogEntry) -> Self {
+        Self { writer, seq: last.seq() + 1, prev_hash: last.hash().clone() }
+    }
+
+    /// Write a set of modifications to the log, returning the new entry.
+    pub fn write(&mut self, modifications: &Modifications, now: &DateTime<Utc>) -> Result<LogEntry> {
+        let entry = LogEntry::new(self.seq, *now, self.prev_hash.clone(), modifications)?;
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| Error::LogWriteFailed(e.to_string()))?;
+        writeln!(self.writer, "{}", line)
+            .map_err(|e| Error::LogWriteFailed(e.to_string()))?;
+        self.seq += 1;
+        self.prev_hash = entry.hash().clone();
+        Ok(entry)
+    }
+
+    /// Consume the writer, returning the underlying `Write` object
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads entries from a log. Note that reading doesn't verify anything, so
+/// entries should be passed through a [LogVerifier] before being trusted.
+///
+/// [LogVerifier]: struct.LogVerifier.html
+pub struct LogReader<R: BufRead> {
+    /// The lines of the log we're reading from
+    lines: std::io::Lines<R>,
+}
+
+impl<R: BufRead> LogReader<R> {
+    /// Read a log.
+    pub fn new(reader: R) -> Self {
+        Self { lines: reader.lines() }
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(Error::LogReadFailed(e.to_string()))),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(serde_json::from_str(&line).map_err(|e| Error::LogReadFailed(e.to_string())));
+        }
+    }
+}
+
+/// Checks that log entries form an unbroken chain, in order.
+#[derive(Clone, Debug)]
+pub struct LogVerifier {
+    /// The sequence number of the next entry we expect
+    seq: u64,
+    /// The hash of the last entry verified
+    prev_hash: String,
+}
+
+impl LogVerifier {
+    /// Verify a log from the beginning.
+    pub fn new() -> Self {
+        Self { seq: 0, prev_hash: GENESIS_HASH.into() }
+    }
+
+    /// Verify the entries of a log that come after the given (trusted) entry.
+    pub fn resume(last: &LogEntry) -> Self {
+        Self { seq: last.seq() + 1, prev_hash: last.hash().clone() }
+    }
+
+    /// Verify the next entry in the log. Entries must be verified in order.
+    ///
+    /// Returns `Error::LogSequenceMismatch` if the entry isn't the next one in
+    /// the sequence (entries were removed or reordered),
+    /// `Error::LogHashMismatch` if the entry's contents don't match its hash
+    /// (it was edited), or `Error::LogChainBroken` if the entry doesn't follow
+    /// the previous one (the previous entry was edited and rehashed, or
+    /// entries were spliced in from elsewhere).
+    pub fn verify(&mut self, entry: &LogEntry) -> Result<()> {
+        if entry.seq() != &self.seq {
+            Err(Error::LogSequenceMismatch(self.seq, *entry.seq()))?;
+        }
+        if entry.hash() != &entry.compute_hash() {
+            Err(Error::LogHashMismatch(*entry.seq()))?;
+        }
+        if entry.prev_hash() != &self.prev_hash {
+            Err(Error::LogChainBroken(*entry.seq()))?;
+        }
+        self.seq += 1;
+        self.prev_hash = entry.hash().clone();
+        Ok(())
+    }
+
+    /// Read and verify an entire log, returning its entries.
+    pub fn verify_all<R: BufRead>(&mut self, reader: LogReader<R>) -> Result<Vec<LogEntry>> {
+        let mut entries = Vec::new();
+        for entry in reader {
+            let entry = entry?;
+            self.verify(&entry)?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+impl Default for LogVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::user::UserID,
+        transactions,
+        util::{self, test::*},
+    };
+
+    fn make_log(now: &DateTime<Utc>) -> (Vec<Modifications>, Vec<u8>) {
+        let user = make_user(&UserID::create(), None, now);
+        let mods = vec![
+            transactions::user::create(UserID::create(), "andrew@lyonbros.com", "andrew", true, now).unwrap(),
+            transactions::user::update(&user, user.clone(), None, Some("jerry".into()), None, now).unwrap(),
+            transactions::user::update(&user, user.clone(), None, Some("larry".into()), None, now).unwrap(),
+        ];
+        let mut writer = LogWriter::new(Vec::new());
+        for set in &mods {
+            writer.write(set, now).unwrap();
+        }
+        (mods, writer.into_inner())
+    }
+
+    fn entries(log: &[u8]) -> Vec<LogEntry> {
+        LogReader::new(log).map(|entry| entry.unwrap()).collect()
+    }
+
+    fn to_log(entries: &[LogEntry]) -> Vec<u8> {
+        entries.iter()
+            .map(|entry| format!("{}\n", serde_json::to_string(entry).unwrap()))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn writes_and_verifies() {
+        let now = util::time::now();
+        let (mods, log) = make_log(&now);
+        let entries = LogVerifier::new().verify_all(LogReader::new(log.as_slice())).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].prev_hash(), GENESIS_HASH);
+        assert_eq!(entries[1].prev_hash(), entries[0].hash());
+        assert_eq!(entries[2].seq(), &2);
+        assert_eq!(entries[2].timestamp(), &now);
+        for (entry, set) in entries.iter().zip(mods.iter()) {
+            assert_eq!(&entry.modifications().unwrap(), set);
+        }
+
+        // pick the log back up where we left off
+        let mut writer = LogWriter::resume(log.clone(), &entries[2]);
+        writer.write(&mods[0], &now).unwrap();
+        let log2 = writer.into_inner();
+        assert_eq!(LogVerifier::new().verify_all(LogReader::new(log2.as_slice())).unwrap().len(), 4);
+        let mut verifier = LogVerifier::resume(&entries[2]);
+        assert_eq!(verifier.verify(&self::entries(&log2)[3]), Ok(()));
+    }
+
+    #[test]
+    fn canonical_encoding() {
+        let value = json!({"b": [{"z": 1, "a": "x\"y"}, null], "a": {"d": true, "c": 1.5}});
+        assert_eq!(canonical_json(&value), r#"{"a":{"c":1.5,"d":true},"b":[{"a":"x\"y","z":1},null]}"#);
+
+        // pin the hash so changes to the encoding can't slip by unnoticed
+        let entry = LogEntry {
+            seq: 3,
+            timestamp: "2020-06-01T12:00:00Z".parse().unwrap(),
+            prev_hash: GENESIS_HASH.into(),
+            hash: String::new(),
+            modifications: json!({"modifications": [{"op": "delete", "model": {"User": {"name": "frog", "id": "1234"}}}]}),
+        };
+        assert_eq!(entry.compute_hash(), "f3898a2bdc035eba7cd3220bc48dea646de26f05aba4bc7c080798e3468603f6");
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let now = util::time::now();
+        let (_, log) = make_log(&now);
+        let verify = |entries: &Vec<LogEntry>| {
+            LogVerifier::new().verify_all(LogReader::new(to_log(entries).as_slice()))
+        };
+
+        // gaps
+        let mut gap = entries(&log);
+        gap.remove(1);
+        assert_eq!(verify(&gap), Err(Error::LogSequenceMismatch(1, 2)));
+
+        // reordering
+        let mut reordered = entries(&log);
+        reordered.swap(1, 2);
+        assert_eq!(verify(&reordered), Err(Error::LogSequenceMismatch(1, 2)));
+
+        // edits
+        let mut edited = entries(&log);
+        edited[1].modifications = json!({"modifications": []});
+        assert_eq!(verify(&edited), Err(Error::LogHashMismatch(1)));
+        let mut edited = entries(&log);
+        edited[2].timestamp = now + chrono::Duration::seconds(1);
+        assert_eq!(verify(&edited), Err(Error::LogHashMismatch(2)));
+
+        // edits that rehash the entry still break the chain
+        let mut rehashed = entries(&log);
+        rehashed[1].modifications = json!({"modifications": []});
+        rehashed[1].hash = rehashed[1].compute_hash();
+        assert_eq!(verify(&rehashed), Err(Error::LogChainBroken(2)));
+
+        // renumbering and rehashing after removing an entry
+        let mut renumbered = entries(&log);
+        renumbered.remove(1);
+        renumbered[1].seq = 1;
+        renumbered[1].hash = renumbered[1].compute_hash();
+        assert_eq!(verify(&renumbered), Err(Error::LogChainBroken(1)));
+
+        let res = LogReader::new("{\"seq\": 0}\n".as_bytes()).next().unwrap();
+        assert!(matches!(res, Err(Error::LogReadFailed(_))));
+    }
+}
@@ -1,20 +1,39 @@
 //! Accounts are a place to hold credits earned through labor. Think of them
 //! like a bank account or crypto wallet.
+//!
+//! Accounts are owned by a single user, and only that user can make changes to
+//! them. Workers point their [Compensation] at an account so their wages have
+//! somewhere to go.
+//!
+//! [Compensation]: ../member/struct.Compensation.html
 
 use crate::{
-    models:: {
+    models::{
         user::UserID,
-    }
+    },
 };
+use rust_decimal::prelude::*;
 
 basis_model! {
+    /// The `Account` model holds a user's credit balance.
     pub struct Account {
         id: <<AccountID>>,
+        /// The user that owns this account
         user_id: UserID,
+        /// The account's name, ie "Main account"
         name: String,
+        /// A description of what this account is used for
         description: String,
-        balance: f64,
+        /// The number of credits in this account
+        #[serde(serialize_with = "crate::models::lib::diff::serialize_decimal")]
+        balance: Decimal,
     }
     AccountBuilder
 }
 
+impl Account {
+    /// Determines if the given user owns this account.
+    pub fn is_owned_by(&self, user_id: &UserID) -> bool {
+        self.user_id() == user_id
+    }
+}
@@ -1,14 +1,56 @@
 //! Agreements respresent a larger transaction between two agents. Think of an
 //! agreement like an order, and that order can be made up of multiple
 //! deliverables, modeled as `Commitment`s and `EconomicEvent`s.
+//!
+//! An agreement only becomes binding once every participant has accepted the
+//! current revision of it. Any change to the content of the agreement bumps its
+//! revision and clears all existing signatures, meaning the participants must
+//! sign off again.
 
+use chrono::{DateTime, Utc};
 use crate::{
     models::{
-        lib::agent::AgentID,
+        lib::{
+            agent::AgentID,
+            basis_model::Model,
+        },
+        member::MemberID,
+        user::UserID,
     },
 };
+use getset::Getters;
+use serde::{Serialize, Deserialize};
 use vf_rs::vf;
 
+/// Records a participant's acceptance of a specific revision of an agreement.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Signature {
+    /// The participant this signature is made on behalf of
+    participant: AgentID,
+    /// The user that signed
+    user_id: UserID,
+    /// The member record the user signed with
+    member_id: MemberID,
+    /// The revision of the agreement that was accepted
+    revision: u64,
+    /// When the signature happened
+    signed: DateTime<Utc>,
+}
+
+impl Signature {
+    /// Create a new signature
+    pub fn new(participant: AgentID, user_id: UserID, member_id: MemberID, revision: u64, signed: DateTime<Utc>) -> Self {
+        Self {
+            participant,
+            user_id,
+            member_id,
+            revision,
+            signed,
+        }
+    }
+}
+
 basis_model! {
     /// An agreement between two or more parties. This model is a very thin
     /// wrapper around the [ValueFlows Agreement][vfagreement] object. It has no
@@ -23,9 +65,14 @@ basis_model! {
         inner: vf::Agreement,
         /// A list of the participants in the agreement. This allows quickly
         /// checking to see if an event or commitment is part of an agreement.
-        /// Note that this might also allow the storage layer to have a list of
-        /// signatures needed in order to materially change the agreement.
         participants: Vec<AgentID>,
+        /// The acceptances recorded by the participants of this agreement.
+        signatures: Vec<Signature>,
+        /// Incremented each time the content of the agreement changes. A
+        /// signature only counts if it was made against the current revision.
+        #[builder(default)]
+        #[serde(default)]
+        revision: u64,
     }
     AgreementBuilder
 }
@@ -35,5 +82,87 @@ impl Agreement {
     pub fn has_participant(&self, agent_id: &AgentID) -> bool {
         self.participants().contains(agent_id)
     }
+
+    /// Determines if the given participant has accepted the current revision of
+    /// this agreement.
+    pub fn has_signed(&self, agent_id: &AgentID) -> bool {
+        self.signatures().iter()
+            .any(|sig| sig.participant() == agent_id && sig.revision() == self.revision())
+    }
+
+    /// An agreement is binding when it has participants and every one of them
+    /// has accepted the current revision.
+    pub fn is_binding(&self) -> bool {
+        self.is_active() &&
+            !self.participants().is_empty() &&
+            self.participants().iter().all(|participant| self.has_signed(participant))
+    }
+
+    /// Record a signature, replacing any previous signature made by the same
+    /// participant.
+    pub(crate) fn sign(&mut self, signature: Signature) {
+        self.signatures_mut().retain(|sig| sig.participant() != signature.participant());
+        self.signatures_mut().push(signature);
+    }
+
+    /// Mark the content of this agreement as changed, which bumps the revision
+    /// and voids all existing signatures.
+    pub(crate) fn reset_signatures(&mut self) {
+        let revision = self.revision() + 1;
+        self.set_revision(revision);
+        self.set_signatures(vec![]);
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::company::CompanyID,
+        util::{self, test::*},
+    };
+
+    #[test]
+    fn binding() {
+        let now = util::time::now();
+        let company1: AgentID = CompanyID::create().into();
+        let company2: AgentID = CompanyID::create().into();
+        let mut agreement = make_agreement(&AgreementID::create(), &vec![company1.clone(), company2.clone()], "order 1234", "widgets", &now);
+        assert!(agreement.is_binding());
+
+        agreement.reset_signatures();
+        assert_eq!(agreement.revision(), &1);
+        assert!(!agreement.has_signed(&company1));
+        assert!(!agreement.is_binding());
+
+        agreement.sign(Signature::new(company1.clone(), UserID::create(), MemberID::create(), 1, now));
+        assert!(agreement.has_signed(&company1));
+        assert!(!agreement.is_binding());
+
+        // a signature for an old revision doesn't count
+        agreement.sign(Signature::new(company2.clone(), UserID::create(), MemberID::create(), 0, now));
+        assert!(!agreement.has_signed(&company2));
+        assert!(!agreement.is_binding());
+
+        agreement.sign(Signature::new(company2.clone(), UserID::create(), MemberID::create(), 1, now));
+        assert_eq!(agreement.signatures().len(), 2);
+        assert!(agreement.is_binding());
+
+        let mut agreement2 = agreement.clone();
+        agreement2.set_active(false);
+        assert!(!agreement2.is_binding());
+
+        let mut agreement3 = agreement.clone();
+        agreement3.set_participants(vec![]);
+        assert!(!agreement3.is_binding());
+    }
+    #[test]
+    fn loads_old_agreements() {
+        // saved before agreements needed signatures
+        let agreement: Agreement = serde_json::from_str(r#"{"id":"order","inner":{"created":"2020-06-01T00:00:00Z","name":"order 1234","note":"widgets"},"participants":[{"company":"widgets"}],"active":true,"created":"2020-06-01T00:00:00Z","updated":"2020-06-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(agreement.participants(), &vec![CompanyID::new("widgets").into()]);
+        assert_eq!(agreement.signatures(), &vec![]);
+        assert_eq!(agreement.revision(), &0);
+        assert!(!agreement.is_binding());
+    }
+}
@@ -0,0 +1,91 @@
+//! Audit records describe *who* ran a transaction and on whose authority,
+//! complementing the [Modifications] that describe *what* changed.
+//!
+//! Every transaction appends an `AuditRecord` (as an `Op::Create`) to the end of
+//! the modifications it returns. The record notes the calling user, the member
+//! they acted as and the company they acted within (if any), the permissions
+//! the transaction required, and the IDs of the models it touched. Records also
+//! note whether the caller was a [Vote] user, so changes made through
+//! governance can be told apart from ordinary ones.
+//!
+//! [Modifications]: ../struct.Modifications.html
+//! [Vote]: ../../system/vote/struct.Vote.html
+
+use crate::{
+    access::RequiredPermission,
+    models::{
+        ModelRef,
+        company::CompanyID,
+        member::MemberID,
+        user::UserID,
+    },
+};
+
+basis_model! {
+    /// A record of a transaction having been run. The `created` field holds
+    /// the time the transaction ran.
+    pub struct AuditRecord {
+        id: <<AuditRecordID>>,
+        /// The user that ran the transaction
+        user_id: UserID,
+        /// The member the user acted as, if the transaction was run within a
+        /// company
+        member_id: Option<MemberID>,
+        /// The company the transaction was run within, if any
+        company_id: Option<CompanyID>,
+        /// The transaction that was run, ie `resource::update`
+        transaction: String,
+        /// The permissions the transaction required of the caller/member
+        permissions: Vec<RequiredPermission>,
+        /// The models created, updated, or deleted by the transaction
+        affected: Vec<ModelRef>,
+        /// Whether the caller was a [Vote](../../system/vote/struct.Vote.html)
+        /// user
+        vote: bool,
+    }
+    AuditRecordBuilder
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        access::Permission,
+        models::{
+            Op,
+            ModelRef,
+            company::CompanyID,
+            user::UserID,
+        },
+        system::vote::Vote,
+        transactions,
+        util::{self, test::*},
+    };
+
+    #[test]
+    fn transactions_are_audited() {
+        let now = util::time::now();
+        let user_id = UserID::create();
+        let mods = transactions::user::create(user_id.clone(), "jerry@thatsjerry.com", "jerry", true, &now).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let record = mods[1].clone().expect_op::<super::AuditRecord>(Op::Create).unwrap();
+        assert_eq!(record.user_id(), &user_id);
+        assert_eq!(record.member_id(), &None);
+        assert_eq!(record.company_id(), &None);
+        assert_eq!(record.transaction(), "user::create");
+        assert_eq!(record.permissions(), &vec![Permission::UserCreate.into()]);
+        assert_eq!(record.affected(), &vec![ModelRef::User(user_id.clone())]);
+        assert_eq!(record.vote(), &false);
+        assert_eq!(record.created(), &now);
+
+        let company_id = CompanyID::create();
+        let company = make_company(&company_id, "jerry's widgets", &now);
+        let voter = Vote::systemic(UserID::create(), &now).unwrap();
+        let mods = transactions::company::update(voter.user(), None, company, Some("jerry's WIDGETS".into()), None, None, &now).unwrap().into_vec();
+        let record = mods.last().unwrap().clone().expect_op::<super::AuditRecord>(Op::Create).unwrap();
+        assert_eq!(record.user_id(), voter.user().id());
+        assert_eq!(record.company_id(), &Some(company_id.clone()));
+        assert_eq!(record.transaction(), "company::update");
+        assert_eq!(record.affected(), &vec![ModelRef::Company(company_id.clone())]);
+        assert_eq!(record.vote(), &true);
+    }
+}
@@ -0,0 +1,42 @@
+//! A ballot is a single voter's choice on a [Proposal]. Systemic proposals are
+//! voted on by users, and company proposals are voted on by the company's
+//! members.
+//!
+//! See the [proposal transactions][1] for casting ballots.
+//!
+//! [Proposal]: ../proposal/struct.Proposal.html
+//! [1]: ../../transactions/ballot/index.html
+
+use crate::{
+    models::{
+        lib::agent::AgentID,
+        proposal::ProposalID,
+    },
+};
+use serde::{Serialize, Deserialize};
+
+/// How a voter voted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BallotChoice {
+    /// In favor of the proposal
+    Yes,
+    /// Against the proposal
+    No,
+    /// Counts toward quorum, but not for or against the proposal
+    Abstain,
+}
+
+basis_model! {
+    /// A vote cast on a proposal.
+    pub struct Ballot {
+        id: <<BallotID>>,
+        /// The proposal being voted on
+        proposal_id: ProposalID,
+        /// The voter. This is a `UserID` for systemic proposals and a
+        /// `MemberID` for company proposals.
+        voter: AgentID,
+        /// The voter's choice
+        choice: BallotChoice,
+    }
+    BallotBuilder
+}
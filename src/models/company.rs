@@ -12,13 +12,16 @@
 //! [access]: ../../access/
 
 use crate::{
+    access::RoleHasPermission,
     costs::Costs,
     models::{
         lib::agent::{Agent, AgentID},
         process::Process,
+        proposal::{Tally, VoteRules},
         resource::Resource,
     },
 };
+use getset::{Getters, Setters};
 use serde::{Serialize, Deserialize};
 use vf_rs::vf;
 
@@ -40,6 +43,9 @@ pub enum Permission {
     /// Can update agreements (orders)
     AgreementUpdate,
 
+    /// Can change the company's bylaws
+    BylawsUpdate,
+
     /// Can cite a resource
     Cite,
 
@@ -93,6 +99,9 @@ pub enum Permission {
     /// Can move resources internally within the company
     MoveResource,
 
+    /// Can run payroll for the company's workers
+    Payroll,
+
     /// Can pick up (for delivery) a resource
     Pickup,
 
@@ -113,6 +122,9 @@ pub enum Permission {
     /// Can produce a resource
     Produce,
 
+    /// Can propose transactions for the company's members to vote on
+    ProposalCreate,
+
     /// Can raise resource quantities within the company
     Raise,
 
@@ -129,6 +141,12 @@ pub enum Permission {
     ResourceSpecDelete,
     /// Can update a resource spec
     ResourceSpecUpdate,
+    /// Can create company roles
+    RoleCreate,
+    /// Can delete company roles
+    RoleDelete,
+    /// Can update company roles' permissions
+    RoleUpdate,
 
     /// Transfer ownership/custody to another agent
     Transfer,
@@ -146,6 +164,93 @@ pub enum Permission {
     WorkAdmin,
 }
 
+/// A named set of permissions defined by a company. Members are assigned roles
+/// by name, which means changing a role's permissions changes them for every
+/// member holding that role.
+#[derive(Clone, Debug, PartialEq, Getters, Setters, Serialize, Deserialize)]
+#[getset(get = "pub", set = "pub(crate)")]
+pub struct Role {
+    /// The role's name, ie "Coordinator". Unique within a company.
+    name: String,
+    /// The permissions this role grants
+    permissions: Vec<Permission>,
+}
+
+impl Role {
+    /// Create a new role
+    pub fn new<T: Into<String>>(name: T, permissions: Vec<Permission>) -> Self {
+        Self {
+            name: name.into(),
+            permissions,
+        }
+    }
+}
+
+impl RoleHasPermission for Role {
+    type Permission = Permission;
+
+    fn can(&self, permission: &Permission) -> bool {
+        self.permissions().iter().any(|perm| perm.can(permission))
+    }
+}
+
+/// What it takes for a company's members to use a permission governed by a
+/// [Bylaw].
+///
+/// [Bylaw]: struct.Bylaw.html
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BylawRequirement {
+    /// A proposal that passes under the given voting rules
+    Vote(VoteRules),
+    /// A proposal with at least this many yes votes (ie, 2 of the 5 board
+    /// members)
+    Approvals(u64),
+}
+
+/// A rule a company sets for itself, marking a permission as too sensitive for
+/// any one member to use alone. A governed permission can only be used by a
+/// [Vote] member, created when a [proposal] meeting the bylaw's requirement
+/// passes.
+///
+/// [Vote]: ../../system/vote/struct.Vote.html
+/// [proposal]: ../proposal/index.html
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Bylaw {
+    /// The permission this bylaw governs (`All` governs every permission)
+    permission: Permission,
+    /// What it takes to use the permission
+    requirement: BylawRequirement,
+}
+
+impl Bylaw {
+    /// Create a new bylaw
+    pub fn new(permission: Permission, requirement: BylawRequirement) -> Self {
+        Self {
+            permission,
+            requirement,
+        }
+    }
+
+    /// Determine if a proposal's final tally meets this bylaw's requirement.
+    pub fn is_satisfied_by(&self, tally: &Tally) -> bool {
+        match self.requirement() {
+            BylawRequirement::Vote(rules) => tally.passes(rules),
+            BylawRequirement::Approvals(required) => tally.yes() >= required,
+        }
+    }
+}
+
+impl RoleHasPermission for Permission {
+    type Permission = Permission;
+
+    /// A permission granted directly acts as a role containing only itself
+    /// (or everything, in the case of `All`).
+    fn can(&self, permission: &Permission) -> bool {
+        self == &Permission::All || self == permission
+    }
+}
+
 basis_model! {
     /// A company is a group of one or more people working together for a common
     /// purpose.
@@ -160,11 +265,29 @@ basis_model! {
         inner: vf::Agent,
         /// Primary email address
         email: String,
+        /// The roles this company has defined for its members
+        roles: Vec<Role>,
+        /// The names of roles that have been deleted. Members may still list
+        /// these names, so they can't be used for new roles (which would hand
+        /// the new role's permissions to the old role's holders).
+        retired_roles: Vec<String>,
+        /// The permissions this company's members can only use by vote
+        bylaws: Vec<Bylaw>,
     }
     CompanyBuilder
 }
 
 impl Company {
+    /// Find one of this company's roles by name.
+    pub fn role(&self, name: &str) -> Option<&Role> {
+        self.roles().iter().find(|role| role.name() == name)
+    }
+
+    /// Find the bylaw (if any) governing the given permission.
+    pub fn bylaw(&self, permission: &Permission) -> Option<&Bylaw> {
+        self.bylaws().iter().find(|bylaw| bylaw.permission().can(permission))
+    }
+
     /// Calculate the total costs for this company, given a set of processes and
     /// resources that belong to the company.
     pub fn total_costs(&self, processes: &Vec<Process>, resources: &Vec<Resource>) -> Costs {
@@ -236,5 +359,13 @@ mod tests {
         expected_costs.track_labor("marketer", dec!(123.4));
         assert_eq!(costs, expected_costs);
     }
+    #[test]
+    fn loads_old_companies() {
+        // saved before companies had roles or bylaws
+        let company: Company = serde_json::from_str(r#"{"id":"widgets","inner":{"name":"widgets inc"},"email":"jerry@widgets.biz","active":true,"created":"2020-06-01T00:00:00Z","updated":"2020-06-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(company.id(), &CompanyID::new("widgets"));
+        assert_eq!(company.roles(), &vec![]);
+        assert_eq!(company.retired_roles(), &Vec::<String>::new());
+        assert_eq!(company.bylaws(), &vec![]);
+    }
 }
-
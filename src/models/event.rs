@@ -232,18 +232,20 @@ impl EventProcessResult {
         self.modifications.push(Op::Create, resource);
     }
 
-    /// Push a process to modify into the result set
+    /// Push a process to modify into the result set, along with the original
+    /// copy of it (so we can diff the two)
     #[allow(dead_code)]
-    fn modify_process(&mut self, mut process: Process) {
+    fn modify_process(&mut self, original: Process, mut process: Process) {
         process.set_updated(self.process_time.clone());
-        self.modifications.push(Op::Update, process);
+        self.modifications.push_update(original, process);
     }
 
-    /// Push a resource to modify into the result set
+    /// Push a resource to modify into the result set, along with the original
+    /// copy of it (so we can diff the two)
     #[allow(dead_code)]
-    fn modify_resource(&mut self, mut resource: Resource) {
+    fn modify_resource(&mut self, original: Resource, mut resource: Resource) {
         resource.set_updated(self.process_time.clone());
-        self.modifications.push(Op::Update, resource);
+        self.modifications.push_update(original, resource);
     }
 }
 
@@ -442,6 +444,7 @@ impl Event {
             }
             Action::Work => {
                 let mut input_process = state.input_of.clone().ok_or(EventError::MissingInputProcess)?;
+                let original_process = input_process.clone();
                 let member = state.provider.clone().ok_or(EventError::MissingProvider)?;
                 let occupation_id = member.occupation_id().ok_or(Error::MemberMustBeWorker)?;
                 let move_costs = self.move_costs().as_ref().ok_or(EventError::MissingCosts)?;
@@ -468,7 +471,7 @@ impl Event {
                 costs.track_labor(occupation_id.clone(), occupation_costs);
                 costs.track_labor_hours(occupation_id.clone(), hours);
                 input_process.receive_costs(&costs)?;
-                res.modify_process(input_process);
+                res.modify_process(original_process, input_process);
             }
             _ => {
                 default_resource()?;
@@ -664,13 +667,13 @@ impl Event {
         }
 
         // save our changes, if we have them
-        if process != process_clone { res.modify_process(process.unwrap()); }
-        if process2 != process2_clone { res.modify_process(process2.unwrap()); }
-        if resource != resource_clone { res.modify_resource(resource.unwrap()); }
+        if process != process_clone { res.modify_process(process_clone.unwrap(), process.unwrap()); }
+        if process2 != process2_clone { res.modify_process(process2_clone.unwrap(), process2.unwrap()); }
+        if resource != resource_clone { res.modify_resource(resource_clone.unwrap(), resource.unwrap()); }
         if resource2_is_create {
             res.create_resource(resource2.unwrap());
         } else if resource2 != resource2_clone {
-            res.modify_resource(resource2.unwrap());
+            res.modify_resource(resource2_clone.unwrap(), resource2.unwrap());
         }
 
         Ok(res.into_modifications())
@@ -1000,6 +1003,8 @@ mod tests {
     /// costs).
     fn check_process_mods(fields_allowed_to_change: Vec<&'static str>, process_new: &Process, process_previous: &Process) {
         let mod_process = |mut process: Process| {
+            // modifications always bump the version
+            process.set_version(0);
             for field in &fields_allowed_to_change {
                 match *field {
                     "costs" => { process.set_costs(Costs::new()); }
@@ -1022,6 +1027,8 @@ mod tests {
     /// exception added in this function).
     fn check_resource_mods(fields_allowed_to_change: Vec<&'static str>, resource_new: &Resource, resource_previous: &Resource) {
         let mod_resource = |mut resource: Resource| {
+            // modifications always bump the version
+            resource.set_version(0);
             for field in &fields_allowed_to_change {
                 match *field {
                     "costs" => { resource.set_costs(Costs::new()); }
@@ -1101,6 +1108,18 @@ mod tests {
         assert_eq!(resource.costs(), &Costs::new_with_labor("machinist", dec!(4.91)));
         check_resource_mods(vec!["costs", "accounting_quantity", "onhand_quantity"], &resource, state.resource.as_ref().unwrap());
 
+        // updates note which fields (including nested ones) changed
+        let diff = mods[0].diff().unwrap();
+        let costs_diff = diff.iter().find(|diff| diff.path() == "costs.labor.machinist").unwrap();
+        assert_eq!(costs_diff.old(), &serde_json::Value::Null);
+        assert_eq!(costs_diff.new_value(), &serde_json::json!("30"));
+        let costs_diff = mods[1].diff().unwrap().iter().find(|diff| diff.path() == "costs.labor.machinist").unwrap();
+        assert_eq!(costs_diff.new_value(), &serde_json::json!("4.91"));
+        let paths = mods[1].diff().unwrap().iter().map(|diff| diff.path()).collect::<Vec<_>>();
+        assert!(paths.contains(&"costs.labor.machinist"));
+        assert!(paths.contains(&"inner.accounting_quantity.has_numerical_value.integer"));
+        assert!(!paths.contains(&"inner.name"));
+
         let mut event = make_event(vf::Action::Consume, &company_id, &company_id, &state, &now);
         event.inner_mut().set_resource_quantity(Some(Measure::new(NumericUnion::Decimal(dec!(5)), Unit::One)));
         event.set_move_costs(Some(Costs::new_with_labor("machinist", dec!(100.000001))));
@@ -1357,6 +1376,7 @@ mod tests {
         resource2_clone.set_costs(Costs::new_with_labor("machinist", dec!(30.0)));
         resource2_clone.set_created(now4.clone());
         resource2_clone.set_updated(now4.clone());
+        resource2_clone.set_version(0);
         assert_eq!(resource5.id(), event.inner().to_resource_inventoried_as().as_ref().unwrap());
         assert_eq!(resource5, resource2_clone);
     }
@@ -1468,6 +1488,7 @@ mod tests {
         resource2_clone.set_costs(Costs::new_with_labor("machinist", dec!(30.0)));
         resource2_clone.set_created(now4.clone());
         resource2_clone.set_updated(now4.clone());
+        resource2_clone.set_version(0);
         assert_eq!(resource5.id(), event.inner().to_resource_inventoried_as().as_ref().unwrap());
         assert_eq!(resource5, resource2_clone);
     }
@@ -1519,6 +1540,7 @@ mod tests {
         resource2_clone.set_costs(Costs::new_with_labor("machinist", dec!(30.0)));
         resource2_clone.set_created(now4.clone());
         resource2_clone.set_updated(now4.clone());
+        resource2_clone.set_version(0);
         assert_eq!(resource5.id(), event.inner().to_resource_inventoried_as().as_ref().unwrap());
         assert_eq!(resource5, resource2_clone);
     }
@@ -1569,6 +1591,7 @@ mod tests {
         resource2_clone.set_costs(Costs::new_with_labor("machinist", dec!(30.0)));
         resource2_clone.set_created(now4.clone());
         resource2_clone.set_updated(now4.clone());
+        resource2_clone.set_version(0);
         assert_eq!(resource5.id(), event.inner().to_resource_inventoried_as().as_ref().unwrap());
         assert_eq!(resource5, resource2_clone);
     }
@@ -2,6 +2,7 @@ use crate::{
     error::{Result, Error},
     models::{
         company::CompanyID,
+        ModelRef,
         member::MemberID,
         lib::basis_model::Model,
         user::UserID,
@@ -53,6 +54,16 @@ macro_rules! impl_agent_for_model_id {
     };
 }
 
+impl From<AgentID> for ModelRef {
+    fn from(val: AgentID) -> Self {
+        match val {
+            AgentID::CompanyID(id) => ModelRef::Company(id),
+            AgentID::MemberID(id) => ModelRef::Member(id),
+            AgentID::UserID(id) => ModelRef::User(id),
+        }
+    }
+}
+
 impl_agent_for_model_id! { CompanyID }
 impl_agent_for_model_id! { MemberID }
 impl_agent_for_model_id! { UserID }
@@ -99,6 +99,13 @@ macro_rules! basis_model {
                     /// Notes if the model has been deleted, which has the same
                     /// effect of deactivation, but is permanent.
                     deleted: Option<chrono::DateTime<chrono::Utc>>,
+                    /// Counts how many times the model has been updated (or
+                    /// deleted). Each modification notes the version it
+                    /// expects to replace, which lets stores catch two
+                    /// transactions clobbering each other's changes.
+                    #[builder(default)]
+                    #[serde(default)]
+                    version: u64,
                 }
             }
 
@@ -108,6 +115,12 @@ macro_rules! basis_model {
                 pub(crate) fn builder() -> $builder {
                     $builder::default()
                 }
+
+                /// Returns the fields that changed between a `previous` copy
+                /// of this model and this one.
+                pub fn diff(&self, previous: &Self) -> Vec<crate::models::FieldDiff> {
+                    crate::models::lib::diff::diff(previous, self)
+                }
             }
 
 
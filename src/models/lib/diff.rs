@@ -0,0 +1,261 @@
+//! Field-level diffs between two copies of a model.
+//!
+//! Models are compared via their serialized form, which lets us walk into
+//! nested structures (like a model's `inner` VF object or its `Costs`) without
+//! each of them having to know how to diff themselves. Objects are descended
+//! into and each changed key gets its own dotted path (`inner.name`,
+//! `costs.labor.machinist`), while anything else (strings, numbers, lists) is
+//! compared as a whole.
+//!
+//! Decimals normally serialize as floats (`om2` turns on `rust_decimal`'s
+//! `serde-float` for everyone), which would make a diff of `10.0000000001`
+//! credits come out as `10.0`. While diffing, the crate's own decimal fields
+//! (which opt in via the `serialize_decimal*` functions here) serialize as
+//! exact strings instead. Any new decimal field on a model needs to opt in the
+//! same way.
+
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer, Deserialize};
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+thread_local! {
+    /// Set while `diff` is serializing, so decimals come out exact.
+    static EXACT_DECIMALS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Turns on exact decimals for as long as it's held. Dropping it puts things
+/// back the way they were, including when serialization panics.
+struct ExactDecimals {
+    /// Whether exact decimals were already on when we took over
+    previous: bool,
+}
+
+impl ExactDecimals {
+    /// Turn on exact decimals until the returned guard is dropped.
+    fn enable() -> Self {
+        Self { previous: EXACT_DECIMALS.with(|exact| exact.replace(true)) }
+    }
+}
+
+impl Drop for ExactDecimals {
+    fn drop(&mut self) {
+        EXACT_DECIMALS.with(|exact| exact.set(self.previous));
+    }
+}
+
+/// Wraps a decimal so it serializes as an exact string while diffing, and as
+/// it normally would otherwise.
+struct DiffDecimal<'a>(&'a Decimal);
+
+impl<'a> Serialize for DiffDecimal<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if EXACT_DECIMALS.with(|exact| exact.get()) {
+            serializer.serialize_str(&self.0.normalize().to_string())
+        } else {
+            Serialize::serialize(self.0, serializer)
+        }
+    }
+}
+
+/// Serialize a decimal field, exactly if we're diffing.
+pub(crate) fn serialize_decimal<S: Serializer>(val: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    DiffDecimal(val).serialize(serializer)
+}
+
+/// Serialize an optional decimal field, exactly if we're diffing.
+pub(crate) fn serialize_decimal_opt<S: Serializer>(val: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    val.as_ref().map(DiffDecimal).serialize(serializer)
+}
+
+/// Serialize a map of decimals, exactly if we're diffing.
+pub(crate) fn serialize_decimal_map<K, S>(map: &HashMap<K, Decimal>, serializer: S) -> Result<S::Ok, S::Error>
+    where K: Serialize + Eq + Hash,
+          S: Serializer,
+{
+    serializer.collect_map(map.iter().map(|(key, val)| (key, DiffDecimal(val))))
+}
+
+/// Describes a single changed field within a model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldDiff {
+    /// The dotted path to the field, ie `inner.name` or `costs.labor.machinist`
+    path: String,
+    /// The field's previous value (`null` if it wasn't set)
+    old: Value,
+    /// The field's new value (`null` if it was unset)
+    new: Value,
+}
+
+impl FieldDiff {
+    /// Create a new field diff
+    pub fn new<T: Into<String>>(path: T, old: Value, new: Value) -> Self {
+        Self { path: path.into(), old, new }
+    }
+
+    /// The dotted path to the changed field
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The field's previous value
+    pub fn old(&self) -> &Value {
+        &self.old
+    }
+
+    /// The field's new value
+    pub fn new_value(&self) -> &Value {
+        &self.new
+    }
+
+    /// Swap the old and new values around
+    pub fn reverse(self) -> Self {
+        Self { path: self.path, old: self.new, new: self.old }
+    }
+}
+
+/// Diff two serializable values, returning the changes needed to go from
+/// `old` to `new`, sorted by path.
+///
+/// Our models always serialize cleanly, but if one doesn't it's treated as
+/// `null` rather than failing the whole modification.
+pub(crate) fn diff<T: Serialize>(old: &T, new: &T) -> Vec<FieldDiff> {
+    let (old, new) = {
+        let _exact = ExactDecimals::enable();
+        (serde_json::to_value(old).unwrap_or(Value::Null), serde_json::to_value(new).unwrap_or(Value::Null))
+    };
+    let mut diffs = Vec::new();
+    diff_values(None, &old, &new, &mut diffs);
+    diffs.sort_by(|a, b| a.path.cmp(&b.path));
+    diffs
+}
+
+/// Combine the diffs of two consecutive changes into the diff of the overall
+/// change, dropping any fields that ended up back where they started.
+pub(crate) fn combine(first: Vec<FieldDiff>, second: Vec<FieldDiff>) -> Vec<FieldDiff> {
+    let mut combined = first;
+    for diff in second {
+        match combined.iter_mut().find(|existing| existing.path == diff.path) {
+            Some(existing) => existing.new = diff.new,
+            None => combined.push(diff),
+        }
+    }
+    combined.retain(|diff| diff.old != diff.new);
+    combined.sort_by(|a, b| a.path.cmp(&b.path));
+    combined
+}
+
+/// Recursively compare two values, pushing any differences into `diffs`.
+fn diff_values(path: Option<&str>, old: &Value, new: &Value, diffs: &mut Vec<FieldDiff>) {
+    if old == new {
+        return;
+    }
+    let empty = serde_json::Map::new();
+    // an unset object (ie empty `Costs` buckets) is diffed as an empty one so
+    // that each of its fields still gets its own path
+    let objects = match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => Some((old_map, new_map)),
+        (Value::Object(old_map), Value::Null) => Some((old_map, &empty)),
+        (Value::Null, Value::Object(new_map)) => Some((&empty, new_map)),
+        _ => None,
+    };
+    match objects {
+        Some((old_map, new_map)) => {
+            // fields that are unset are skipped by the serializer, so a key
+            // missing on one side means "null"
+            let keys = old_map.keys().chain(new_map.keys().filter(|key| !old_map.contains_key(*key)));
+            for key in keys {
+                let subpath = match path {
+                    Some(path) => format!("{}.{}", path, key),
+                    None => key.clone(),
+                };
+                let old_val = old_map.get(key).unwrap_or(&Value::Null);
+                let new_val = new_map.get(key).unwrap_or(&Value::Null);
+                diff_values(Some(&subpath), old_val, new_val, diffs);
+            }
+        }
+        None => {
+            diffs.push(FieldDiff::new(path.unwrap_or(""), old.clone(), new.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        costs::Costs,
+        models::{
+            process::ProcessID,
+            company::CompanyID,
+        },
+        util::{self, test::*},
+    };
+    use rust_decimal_macros::*;
+    use serde_json::json;
+
+    #[test]
+    fn diffs_nested_fields() {
+        let now = util::time::now();
+        let process = make_process(&ProcessID::create(), &CompanyID::create(), "make widgets", &Costs::new_with_labor("machinist", dec!(10)), &now);
+        assert_eq!(process.diff(&process), vec![]);
+
+        let mut process2 = process.clone();
+        process2.inner_mut().set_name("make gadgets".into());
+        process2.inner_mut().set_note(Some("gadgets > widgets".into()));
+        process2.set_costs(Costs::new_with_labor("welder", dec!(4)));
+        let diffs = process2.diff(&process);
+        assert_eq!(diffs, vec![
+            FieldDiff::new("costs.labor.machinist", json!("10"), Value::Null),
+            FieldDiff::new("costs.labor.welder", Value::Null, json!("4")),
+            FieldDiff::new("inner.name", json!("make widgets"), json!("make gadgets")),
+            FieldDiff::new("inner.note", Value::Null, json!("gadgets > widgets")),
+        ]);
+        assert_eq!(diffs[2].path(), "inner.name");
+        assert_eq!(diffs[2].old(), &json!("make widgets"));
+        assert_eq!(diffs[2].new_value(), &json!("make gadgets"));
+        assert_eq!(diffs[2].clone().reverse(), FieldDiff::new("inner.name", json!("make gadgets"), json!("make widgets")));
+    }
+
+    #[test]
+    fn diffs_exact_decimals() {
+        let old = Costs::new_with_labor("machinist", dec!(10));
+        let new = Costs::new_with_labor("machinist", dec!(10.0000000000000001));
+        assert_eq!(diff(&old, &new), vec![
+            FieldDiff::new("labor.machinist", json!("10"), json!("10.0000000000000001")),
+        ]);
+        // same value, different scale
+        assert_eq!(diff(&old, &Costs::new_with_labor("machinist", dec!(10.00))), vec![]);
+        // everywhere else, decimals serialize as usual
+        assert_eq!(serde_json::to_value(&new).unwrap(), json!({"labor": {"machinist": 10.0}}));
+
+        // even if diffing blows up partway through
+        struct Explodes;
+        impl Serialize for Explodes {
+            fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+                panic!("kaboom");
+            }
+        }
+        assert!(std::panic::catch_unwind(|| diff(&Explodes, &Explodes)).is_err());
+        assert_eq!(serde_json::to_value(&new).unwrap(), json!({"labor": {"machinist": 10.0}}));
+    }
+
+    #[test]
+    fn combines() {
+        let first = vec![
+            FieldDiff::new("inner.name", json!("make widgets"), json!("make gadgets")),
+            FieldDiff::new("version", json!(0), json!(1)),
+        ];
+        let second = vec![
+            FieldDiff::new("inner.name", json!("make gadgets"), json!("make widgets")),
+            FieldDiff::new("inner.note", Value::Null, json!("gadgets > widgets")),
+            FieldDiff::new("version", json!(1), json!(2)),
+        ];
+        assert_eq!(combine(first, second), vec![
+            FieldDiff::new("inner.note", Value::Null, json!("gadgets > widgets")),
+            FieldDiff::new("version", json!(0), json!(2)),
+        ]);
+    }
+}
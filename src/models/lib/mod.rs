@@ -24,6 +24,98 @@ macro_rules! load_models {
                 $model(crate::models::$path::$model),
             )*
         }
+
+        impl $enumname {
+            /// Grab the wrapped model's version
+            pub fn version(&self) -> u64 {
+                match self {
+                    $(
+                        $enumname::$model(inner) => *inner.version(),
+                    )*
+                }
+            }
+
+            /// Set the wrapped model's version
+            pub(crate) fn set_version(&mut self, version: u64) {
+                match self {
+                    $(
+                        $enumname::$model(inner) => { inner.set_version(version); }
+                    )*
+                }
+            }
+
+            /// Whether the wrapped model has been deleted
+            pub fn is_deleted(&self) -> bool {
+                match self {
+                    $(
+                        $enumname::$model(inner) => crate::models::lib::basis_model::Model::is_deleted(inner),
+                    )*
+                }
+            }
+
+            /// Set (or clear) the wrapped model's deleted timestamp
+            pub(crate) fn set_deleted(&mut self, deleted: Option<chrono::DateTime<chrono::Utc>>) {
+                match self {
+                    $(
+                        $enumname::$model(inner) => crate::models::lib::basis_model::Model::set_deleted(inner, deleted),
+                    )*
+                }
+            }
+
+            /// Whether the wrapped model is active (and not deleted)
+            pub fn is_active(&self) -> bool {
+                match self {
+                    $(
+                        $enumname::$model(inner) => crate::models::lib::basis_model::Model::is_active(inner),
+                    )*
+                }
+            }
+
+            /// Diff the wrapped model against a `previous` copy of it. Returns
+            /// `None` if the two aren't the same type of model.
+            pub fn diff(&self, previous: &$enumname) -> Option<Vec<crate::models::FieldDiff>> {
+                match (self, previous) {
+                    $(
+                        ($enumname::$model(inner), $enumname::$model(previous)) => Some(inner.diff(previous)),
+                    )*
+                    _ => None,
+                }
+            }
+        }
+    };
+
+    // create an enum that can reference any model by its ID
+    (
+        @pub enum_ref $enumname:ident
+        $( ($path:ident, $model:ident, $id:ident $(, $_extratypes:ident)*), )*
+    ) => {
+        /// An enum that can point to *any* model via its ID, for instance
+        /// `ModelRef::User(user_id)`. Handy when we need to talk about models
+        /// without carrying their data around.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+        pub enum $enumname {
+            $(
+                $model(crate::models::$path::$id),
+            )*
+        }
+
+        impl From<&Model> for $enumname {
+            fn from(model: &Model) -> Self {
+                match model {
+                    $(
+                        Model::$model(inner) => $enumname::$model(inner.id().clone()),
+                    )*
+                }
+            }
+        }
+
+        $(
+            impl From<crate::models::$path::$id> for $enumname {
+                fn from(id: crate::models::$path::$id) -> Self {
+                    $enumname::$model(id)
+                }
+            }
+        )*
     };
 
     // entry point
@@ -32,6 +124,8 @@ macro_rules! load_models {
             @$($load_type)*
             (account, Account, AccountID),
             (agreement, Agreement, AgreementID),
+            (audit_record, AuditRecord, AuditRecordID),
+            (ballot, Ballot, BallotID),
             (commitment, Commitment, CommitmentID),
             (company, Company, CompanyID),
             (member, Member, MemberID),
@@ -39,14 +133,15 @@ macro_rules! load_models {
             (event, Event, EventID),
             (intent, Intent, IntentID),
             (occupation, Occupation, OccupationID),
+            (payroll, Payroll, PayrollID),
             (process, Process, ProcessID),
             (process_spec, ProcessSpec, ProcessSpecID),
+            (proposal, Proposal, ProposalID),
             (resource, Resource, ResourceID),
+            (resource_group, ResourceGroup, ResourceGroupID),
+            (resource_group_link, ResourceGroupLink, ResourceGroupLinkID),
             (resource_spec, ResourceSpec, ResourceSpecID, Dimensions),
             (user, User, UserID),
-
-            //(resource_group, ResourceGroup, ResourceGroupID),
-            //(resource_group_link, ResourceGroupLink, ResourceGroupLinkID),
         }
     };
 }
@@ -54,4 +149,5 @@ macro_rules! load_models {
 #[macro_use]
 pub(crate) mod basis_model;
 pub mod agent;
+pub mod diff;
 
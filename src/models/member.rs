@@ -7,10 +7,10 @@
 //! Members have classes which describe the membership:
 //!
 //! - `Company` - Describes a company that is a member of another company. This
-//! membership might come with certain privileges, such as usage of resources
-//! in the larger company. When a smaller company becomes a members of a larger
-//! company, the smaller company's members also become implicit members, and
-//! become part owners of the parent company.
+//!   membership might come with certain privileges, such as usage of resources
+//!   in the larger company. When a smaller company becomes a members of a larger
+//!   company, the smaller company's members also become implicit members, and
+//!   become part owners of the parent company (see [effective_permissions][2]).
 //! - `User` - An individual user who is a member of a company.
 //! - `Worker` - An individual who works at the parent company, making widgets
 //! or growing vegetables or any other productive role. Workers are the only
@@ -20,12 +20,15 @@
 //!
 //! [0]: ../process/struct.Process.html
 //! [1]: ../../transactions/event/work/index.html
+//! [2]: fn.effective_permissions.html
 
+use chrono::{DateTime, Utc};
 use crate::{
-    error::{Error, Result},
+    access::{AccessDenied, Acl, CanAccess, DenyReason, RoleHasPermission},
+    error::Result,
     models::{
         account::AccountID,
-        company::{CompanyID, Permission},
+        company::{Company, CompanyID, Permission},
         lib::{
             agent::{Agent, AgentID},
             basis_model::Model,
@@ -67,6 +70,7 @@ pub struct Compensation {
     /// If the `period` is not hourly, we can give an estimate for the number of
     /// hours worked per week, which gives us an ability to estimate our labor
     /// hours (and not just wage payments)
+    #[serde(serialize_with = "crate::models::lib::diff::serialize_decimal_opt")]
     est_hours_per_week: Option<Decimal>,
 }
 
@@ -166,6 +170,82 @@ impl MemberWorker {
     }
 }
 
+/// A time-limited grant of company permissions from one member to another,
+/// for instance to cover for a coordinator while they're on leave.
+///
+/// Delegations are stored on the member receiving the permissions, and are
+/// only honored between `start` (inclusive) and `expires` (exclusive), for the
+/// permissions the grantor still holds (see [Member::verify_delegations][1]).
+///
+/// [1]: struct.Member.html#method.verify_delegations
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Delegation {
+    /// The member granting the permissions
+    grantor: MemberID,
+    /// The permissions granted. The grantor must hold all of these when the
+    /// delegation is made.
+    permissions: Vec<Permission>,
+    /// When the delegation takes effect
+    start: DateTime<Utc>,
+    /// When the delegation runs out
+    expires: DateTime<Utc>,
+    /// The permissions we've confirmed the grantor still holds. This is never
+    /// stored, so a delegation loaded from storage isn't honored until it's
+    /// been verified again.
+    #[getset(skip)]
+    #[serde(skip)]
+    verified: Vec<Permission>,
+}
+
+impl Delegation {
+    /// Create a new delegation
+    pub fn new(grantor: MemberID, permissions: Vec<Permission>, start: DateTime<Utc>, expires: DateTime<Utc>) -> Self {
+        Self {
+            grantor,
+            permissions,
+            start,
+            expires,
+            verified: Vec::new(),
+        }
+    }
+
+    /// Determine if this delegation is in effect at the given time.
+    pub fn is_active_at(&self, now: &DateTime<Utc>) -> bool {
+        now >= self.start() && now < self.expires()
+    }
+}
+
+/// A member handing their vote on company proposals to another member of the
+/// same company (liquid democracy). Vote proxies are stored on the member
+/// delegating their vote.
+///
+/// A proxy with a `topic` only applies to proposals with that topic, and takes
+/// precedence over a proxy without one (which applies to everything else).
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct VoteProxy {
+    /// The member voting in our place
+    delegate: MemberID,
+    /// The proposal topic this proxy covers, or `None` for all topics
+    topic: Option<String>,
+}
+
+impl VoteProxy {
+    /// Create a new vote proxy
+    pub fn new(delegate: MemberID, topic: Option<String>) -> Self {
+        Self {
+            delegate,
+            topic,
+        }
+    }
+}
+
+/// The maximum number of company-in-company memberships that permissions will
+/// flow through when resolving [effective permissions](fn.effective_permissions.html).
+/// Grants from further up the chain than this are ignored.
+pub const MAX_INHERITANCE_DEPTH: usize = 8;
+
 /// Describes the type of membership for a particular Member record.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum MemberClass {
@@ -200,6 +280,21 @@ basis_model! {
         class: MemberClass,
         /// The permissions this member has at this company (additive)
         permissions: Vec<Permission>,
+        /// The names of the company-defined roles this member holds. The
+        /// permissions granted by these roles are added to `permissions`.
+        roles: Vec<String>,
+        /// Permissions other members have delegated to this member.
+        delegations: Vec<Delegation>,
+        /// Who this member has delegated their vote to (see [VoteProxy]).
+        ///
+        /// [VoteProxy]: struct.VoteProxy.html
+        vote_proxies: Vec<VoteProxy>,
+        /// Whether this is a [Vote](../../system/vote/struct.Vote.html) member
+        /// created for a passed proposal, which can use permissions the
+        /// company's bylaws reserve for votes
+        #[builder(default)]
+        #[serde(default)]
+        vote: bool,
         /// Agreement under which this membership takes place. This can be an
         /// employee agreement, or any general membership agreement (for
         /// instance, there might be a "you can be a member of this housing
@@ -221,25 +316,126 @@ impl Member {
         self.inner().object()
     }
 
-    /// Determines if a member can perform an action (base on their permissions
-    /// list). Note that we don't use roles here, the idea is that companies
-    /// manage their own roles and permissions are assigned to users directly.
-    pub fn can(&self, permission: &Permission) -> bool {
-        if !self.is_active() {
-            return false;
+    /// Determines if a member can perform an action within the given company,
+    /// based on their permissions list and the company roles they hold. Each
+    /// permission is treated as a role granting only itself.
+    pub fn can(&self, company: &Company, permission: &Permission) -> bool {
+        self.can_access(company, permission)
+    }
+
+    /// Determines if this member has been delegated the given permission by
+    /// another member, the delegation is in effect at the given time, and the
+    /// delegation has been [verified](#method.verify_delegations).
+    pub fn is_delegated(&self, company: &Company, permission: &Permission, now: &DateTime<Utc>) -> bool {
+        self.is_active() &&
+            self.in_scope(company) &&
+            self.delegations().iter()
+                .filter(|delegation| delegation.is_active_at(now))
+                .any(|delegation| delegation.verified.iter().any(|perm| perm.can(permission)))
+    }
+
+    /// Check the delegations made to this member against the current member
+    /// records of their grantors. Each delegation is honored only for the
+    /// permissions its grantor still holds (through their own permissions or
+    /// roles), and only while the grantor is an active member of the company.
+    /// Delegations whose grantor isn't in `grantors` aren't honored at all.
+    ///
+    /// Verification isn't stored, so this must be called whenever a member's
+    /// delegations should be honored, with freshly-loaded grantor records.
+    pub fn verify_delegations(&mut self, company: &Company, grantors: &[Member]) {
+        for delegation in self.delegations_mut() {
+            delegation.verified = match grantors.iter().find(|grantor| grantor.id() == delegation.grantor()) {
+                Some(grantor) => {
+                    delegation.permissions().iter()
+                        .filter(|perm| grantor.can(company, perm))
+                        .cloned()
+                        .collect()
+                }
+                None => Vec::new(),
+            };
+        }
+    }
+
+    /// Check if this member (acting as the given user) can perform an action
+    /// on a company, either through its own permissions/roles or via a
+    /// [verified](#method.verify_delegations) delegation in effect at `now`.
+    pub fn access_check(&self, user_id: &UserID, company: &Company, permission: Permission, now: &DateTime<Utc>) -> Result<()> {
+        if self.member_id() != &user_id.clone().into() {
+            Err(AccessDenied::new(DenyReason::WrongUser, Some(permission.clone().into()), Some(self.agent_id())))?;
+        }
+        self.bylaw_check(company, &permission)?;
+        if self.is_delegated(company, &permission, now) {
+            return Ok(());
         }
-        self.permissions().contains(&Permission::All) ||
-            self.permissions().contains(permission)
+        self.check_access(company, &permission)
     }
 
-    /// Check if this member can perform an action on a company.
-    pub fn access_check(&self, user_id: &UserID, company_id: &CompanyID, permission: Permission) -> Result<()> {
-        if self.member_id() != &user_id.clone().into() || self.group_id() != &company_id.clone().into() || !self.can(&permission) {
-            Err(Error::InsufficientPrivileges)?;
+    /// Check that the company's [bylaws][bylaw] don't reserve the given
+    /// permission for votes, unless this is a vote member.
+    ///
+    /// [bylaw]: ../company/struct.Bylaw.html
+    pub fn bylaw_check(&self, company: &Company, permission: &Permission) -> Result<()> {
+        if !self.vote() && company.bylaw(permission).is_some() {
+            Err(AccessDenied::new(DenyReason::RequiresVote, Some(permission.clone().into()), Some(self.agent_id())))?;
         }
         Ok(())
     }
 
+    /// Check if an object's [Acl][acl] (if it has one) lets this member
+    /// perform the given action on it. This is checked *in addition to*
+    /// [access_check](#method.access_check).
+    ///
+    /// [acl]: ../../access/struct.Acl.html
+    pub fn acl_check(&self, acl: &Option<Acl>, permission: &Permission) -> Result<()> {
+        match acl {
+            Some(acl) if !acl.allows(self, permission) => {
+                Err(AccessDenied::new(DenyReason::ObjectAcl, Some(permission.clone().into()), Some(self.agent_id())))?
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Add a delegation to this member, replacing any existing delegation
+    /// from the same grantor.
+    pub(crate) fn delegate(&mut self, delegation: Delegation) {
+        self.delegations_mut().retain(|existing| existing.grantor() != delegation.grantor());
+        self.delegations_mut().push(delegation);
+    }
+
+    /// Remove any delegations made by the given grantor, returning whether or
+    /// not any were removed.
+    pub(crate) fn revoke_delegation(&mut self, grantor: &MemberID) -> bool {
+        let len = self.delegations().len();
+        self.delegations_mut().retain(|existing| existing.grantor() != grantor);
+        len != self.delegations().len()
+    }
+
+    /// Find who this member has delegated their vote to for the given proposal
+    /// topic, falling back to their general (topic-less) proxy.
+    pub fn vote_delegate(&self, topic: Option<&String>) -> Option<&MemberID> {
+        let find = |topic: Option<&String>| {
+            self.vote_proxies().iter()
+                .find(|proxy| proxy.topic().as_ref() == topic)
+                .map(|proxy| proxy.delegate())
+        };
+        topic.and_then(|topic| find(Some(topic))).or_else(|| find(None))
+    }
+
+    /// Add a vote proxy to this member, replacing any existing proxy for the
+    /// same topic.
+    pub(crate) fn delegate_vote(&mut self, proxy: VoteProxy) {
+        self.vote_proxies_mut().retain(|existing| existing.topic() != proxy.topic());
+        self.vote_proxies_mut().push(proxy);
+    }
+
+    /// Remove this member's vote proxy for the given topic, returning whether
+    /// or not there was one.
+    pub(crate) fn revoke_vote_proxy(&mut self, topic: Option<&String>) -> bool {
+        let len = self.vote_proxies().len();
+        self.vote_proxies_mut().retain(|existing| existing.topic().as_ref() != topic);
+        len != self.vote_proxies().len()
+    }
+
     /// Try and get a `CompanyID` from this member's group id.
     pub fn company_id(&self) -> Result<CompanyID> {
         self.group_id().clone().try_into()
@@ -262,6 +458,121 @@ impl Member {
     }
 }
 
+/// Compute a user's effective permissions in a company, given the member
+/// records along the membership chain(s) between them.
+///
+/// This includes the permissions granted by the user's direct membership(s) in
+/// the company, as well as the permissions granted to any company that is a
+/// member (`MemberClass::Company`) of the company: these flow down to that
+/// company's members, and to the members of *its* member companies, and so on.
+///
+/// Inactive or deleted member records break the chain. Cycles in the
+/// membership graph are detected and not followed, and permissions only flow
+/// through at most [MAX_INHERITANCE_DEPTH](constant.MAX_INHERITANCE_DEPTH.html)
+/// levels of company membership.
+///
+/// Member records that aren't part of a chain between the user and the company
+/// are ignored, so it's fine to pass in extra records.
+pub fn effective_permissions(user_id: &UserID, company: &Company, members: &[Member]) -> Vec<Permission> {
+    fn walk(user: &AgentID, group: &AgentID, inherited: &[Permission], members: &[Member], path: &mut Vec<AgentID>, permissions: &mut Vec<Permission>) {
+        if path.len() > MAX_INHERITANCE_DEPTH || path.contains(group) {
+            return;
+        }
+        path.push(group.clone());
+        let group_members = members.iter()
+            .filter(|member| member.is_active() && member.group_id() == group);
+        for member in group_members {
+            if member.member_id() == user {
+                permissions.extend(inherited.iter().cloned());
+            }
+            if let MemberClass::Company(_) = member.class() {
+                walk(user, member.member_id(), inherited, members, path, permissions);
+            }
+        }
+        path.pop();
+    }
+
+    let user: AgentID = user_id.clone().into();
+    let mut permissions = vec![];
+    if !company.is_active() {
+        return permissions;
+    }
+    let company_members = members.iter()
+        .filter(|member| member.is_active() && member.in_scope(company));
+    for member in company_members {
+        let granted = member.access_roles(company);
+        if member.member_id() == &user {
+            permissions.extend(granted.iter().cloned());
+        }
+        if let MemberClass::Company(_) = member.class() {
+            let mut path = vec![company.agent_id()];
+            walk(&user, member.member_id(), &granted, members, &mut path, &mut permissions);
+        }
+    }
+    permissions
+}
+
+/// Follow a member's chain of [vote proxies][proxy] for the given proposal
+/// topic until we reach a member for whom `has_voted` is true, returning that
+/// member's ID.
+///
+/// Returns `None` if the chain ends (or hits an inactive member) before finding
+/// someone who voted, or if it loops back on itself.
+///
+/// [proxy]: struct.VoteProxy.html
+pub fn resolve_vote_delegate<F>(members: &[Member], from: &MemberID, topic: Option<&String>, has_voted: F) -> Option<MemberID>
+    where F: Fn(&MemberID) -> bool
+{
+    let mut path = vec![from];
+    loop {
+        let current = path.last()?;
+        let next = members.iter()
+            .find(|member| member.id() == *current && member.is_active())
+            .and_then(|member| member.vote_delegate(topic))?;
+        if path.contains(&next) {
+            return None;
+        }
+        if has_voted(next) {
+            return Some(next.clone());
+        }
+        path.push(next);
+    }
+}
+
+/// Check if a user can perform an action on a company, either directly or
+/// through the companies they are (transitively) members of. See
+/// [effective_permissions](fn.effective_permissions.html). Permissions the
+/// company's bylaws reserve for votes are always denied.
+pub fn transitive_access_check(user_id: &UserID, company: &Company, members: &[Member], permission: Permission) -> Result<()> {
+    if company.bylaw(&permission).is_some() {
+        Err(AccessDenied::new(DenyReason::RequiresVote, Some(permission.clone().into()), Some(user_id.clone().into())))?;
+    }
+    if !effective_permissions(user_id, company, members).iter().any(|perm| perm.can(&permission)) {
+        Err(AccessDenied::new(DenyReason::MissingPermission, Some(permission.into()), Some(user_id.clone().into())))?;
+    }
+    Ok(())
+}
+
+impl CanAccess for Member {
+    type Role = Permission;
+    /// A member's permissions only apply within the company they link to.
+    type Scope = Company;
+
+    fn access_roles(&self, company: &Self::Scope) -> Vec<Self::Role> {
+        let mut permissions = self.permissions().clone();
+        for name in self.roles() {
+            if let Some(role) = company.role(name) {
+                permissions.extend(role.permissions().iter().cloned());
+            }
+        }
+        permissions
+    }
+
+    fn in_scope(&self, company: &Self::Scope) -> bool {
+        self.group_id() == &company.agent_id()
+    }
+}
+
 impl Agent for Member {
     fn agent_id(&self) -> AgentID {
         self.id().clone().into()
@@ -271,8 +582,9 @@ impl Agent for Member {
 #[cfg(test)]
 mod test {
     use crate::{
+        access::DenyReason,
         models::{
-            company::{CompanyID, Permission as CompanyPermission},
+            company::{CompanyID, Permission as CompanyPermission, Role},
             user::UserID,
         },
         util::{self, test::*},
@@ -286,45 +598,167 @@ mod test {
         let member = make_member_worker(&MemberID::create(), &UserID::create(), &CompanyID::create(), &OccupationID::create(), vec![CompanyPermission::MemberCreate, CompanyPermission::MemberUpdate], &now);
         let user_id: UserID = member.member_id().clone().try_into().unwrap();
         let company_id: CompanyID = member.group_id().clone().try_into().unwrap();
-        assert!(member.can(&CompanyPermission::MemberCreate));
-        assert!(member.access_check(&user_id, &company_id, CompanyPermission::MemberCreate).is_ok());
-        assert!(member.access_check(&user_id, &company_id, CompanyPermission::CompanyDelete).is_err());
+        let company = make_company(&company_id, "jerry's widgets", &now);
+        assert!(member.can(&company, &CompanyPermission::MemberCreate));
+        assert!(member.access_check(&user_id, &company, CompanyPermission::MemberCreate, &now).is_ok());
+        assert!(member.access_check(&user_id, &company, CompanyPermission::CompanyDelete, &now).is_err());
 
         let mut member2 = member.clone();
         member2.set_permissions(vec![CompanyPermission::MemberCreate, CompanyPermission::MemberUpdate, CompanyPermission::CompanyDelete]);
-        assert!(member2.can(&CompanyPermission::MemberCreate));
-        assert!(member2.access_check(&user_id, &company_id, CompanyPermission::MemberCreate).is_ok());
-        assert!(member2.access_check(&user_id, &company_id, CompanyPermission::CompanyDelete).is_ok());
+        assert!(member2.can(&company, &CompanyPermission::MemberCreate));
+        assert!(member2.access_check(&user_id, &company, CompanyPermission::MemberCreate, &now).is_ok());
+        assert!(member2.access_check(&user_id, &company, CompanyPermission::CompanyDelete, &now).is_ok());
 
         let mut member3 = member2.clone();
         member3.set_permissions(vec![]);
-        assert!(!member3.can(&CompanyPermission::MemberCreate));
-        assert!(member3.access_check(&user_id, &company_id, CompanyPermission::MemberCreate).is_err());
-        assert!(member3.access_check(&user_id, &company_id, CompanyPermission::CompanyDelete).is_err());
+        assert!(!member3.can(&company, &CompanyPermission::MemberCreate));
+        assert!(member3.access_check(&user_id, &company, CompanyPermission::MemberCreate, &now).is_err());
+        assert!(member3.access_check(&user_id, &company, CompanyPermission::CompanyDelete, &now).is_err());
 
         let mut member4 = member2.clone();
         member4.set_deleted(Some(now.clone()));
-        assert!(!member4.can(&CompanyPermission::MemberCreate));
-        assert!(member4.access_check(&user_id, &company_id, CompanyPermission::MemberCreate).is_err());
-        assert!(member4.access_check(&user_id, &company_id, CompanyPermission::CompanyDelete).is_err());
+        assert!(!member4.can(&company, &CompanyPermission::MemberCreate));
+        assert!(member4.access_check(&user_id, &company, CompanyPermission::MemberCreate, &now).is_err());
+        assert!(member4.access_check(&user_id, &company, CompanyPermission::CompanyDelete, &now).is_err());
 
         let mut member5 = member2.clone();
         member5.set_active(false);
-        assert!(!member5.can(&CompanyPermission::MemberCreate));
-        assert!(member5.access_check(&user_id, &company_id, CompanyPermission::MemberCreate).is_err());
-        assert!(member5.access_check(&user_id, &company_id, CompanyPermission::CompanyDelete).is_err());
+        assert!(!member5.can(&company, &CompanyPermission::MemberCreate));
+        assert!(member5.access_check(&user_id, &company, CompanyPermission::MemberCreate, &now).is_err());
+        assert!(member5.access_check(&user_id, &company, CompanyPermission::CompanyDelete, &now).is_err());
 
         let mut member6 = member2.clone();
         member6.inner_mut().set_subject(UserID::create().into());
-        assert!(member6.can(&CompanyPermission::MemberCreate));
-        assert!(member6.access_check(&user_id, &company_id, CompanyPermission::MemberCreate).is_err());
-        assert!(member6.access_check(&user_id, &company_id, CompanyPermission::CompanyDelete).is_err());
+        assert!(member6.can(&company, &CompanyPermission::MemberCreate));
+        assert!(member6.access_check(&user_id, &company, CompanyPermission::MemberCreate, &now).is_err());
+        assert!(member6.access_check(&user_id, &company, CompanyPermission::CompanyDelete, &now).is_err());
 
         let mut member7 = member2.clone();
         member7.inner_mut().set_object(CompanyID::create().into());
-        assert!(member7.can(&CompanyPermission::MemberCreate));
-        assert!(member7.access_check(&user_id, &company_id, CompanyPermission::MemberCreate).is_err());
-        assert!(member7.access_check(&user_id, &company_id, CompanyPermission::CompanyDelete).is_err());
+        assert!(!member7.can(&company, &CompanyPermission::MemberCreate));
+        assert!(member7.access_check(&user_id, &company, CompanyPermission::MemberCreate, &now).is_err());
+        assert!(member7.access_check(&user_id, &company, CompanyPermission::CompanyDelete, &now).is_err());
+
+        // permissions can come from company roles
+        let mut company2 = company.clone();
+        company2.set_roles(vec![
+            Role::new("coordinator", vec![CompanyPermission::AgreementCreate, CompanyPermission::Transfer]),
+            Role::new("admin", vec![CompanyPermission::All]),
+        ]);
+        let mut member8 = member3.clone();
+        assert!(!member8.can(&company2, &CompanyPermission::Transfer));
+        member8.set_roles(vec!["coordinator".into()]);
+        assert!(member8.can(&company2, &CompanyPermission::Transfer));
+        assert!(member8.access_check(&user_id, &company2, CompanyPermission::AgreementCreate, &now).is_ok());
+        assert!(member8.access_check(&user_id, &company2, CompanyPermission::CompanyDelete, &now).is_err());
+        // roles the company doesn't define grant nothing
+        assert!(!member8.can(&company, &CompanyPermission::Transfer));
+        member8.set_roles(vec!["coordinator".into(), "admin".into()]);
+        assert!(member8.access_check(&user_id, &company2, CompanyPermission::CompanyDelete, &now).is_ok());
+        member8.set_active(false);
+        assert!(!member8.can(&company2, &CompanyPermission::Transfer));
     }
-}
 
+    fn make_member_company(parent: &CompanyID, child: &CompanyID, permissions: Vec<CompanyPermission>, now: &DateTime<Utc>) -> Member {
+        Member::builder()
+            .id(MemberID::create())
+            .inner(
+                vf::AgentRelationship::builder()
+                    .subject(child.clone())
+                    .object(parent.clone())
+                    .relationship(())
+                    .build().unwrap()
+            )
+            .class(MemberClass::Company(MemberCompany::new()))
+            .permissions(permissions)
+            .active(true)
+            .created(*now)
+            .updated(*now)
+            .build().unwrap()
+    }
+
+    #[test]
+    fn transitive_permissions() {
+        let now = util::time::now();
+        let user_id = UserID::create();
+        let mut coop = make_company(&CompanyID::create(), "widget co-op", &now);
+        coop.set_roles(vec![Role::new("supplier", vec![CompanyPermission::Transfer])]);
+        let factory = make_company(&CompanyID::create(), "widget factory", &now);
+        let workshop = make_company(&CompanyID::create(), "widget workshop", &now);
+
+        // the factory is a member of the co-op, the workshop is a member of the
+        // factory, and our user works at the workshop
+        let mut factory_member = make_member_company(coop.id(), factory.id(), vec![CompanyPermission::Use], &now);
+        factory_member.set_roles(vec!["supplier".into()]);
+        let workshop_member = make_member_company(factory.id(), workshop.id(), vec![CompanyPermission::MemberCreate], &now);
+        let user_member = make_member_worker(&MemberID::create(), &user_id, workshop.id(), &OccupationID::create(), vec![CompanyPermission::Work], &now);
+        let unrelated = make_member_worker(&MemberID::create(), &UserID::create(), workshop.id(), &OccupationID::create(), vec![CompanyPermission::All], &now);
+        let members = vec![factory_member.clone(), workshop_member.clone(), user_member.clone(), unrelated];
+
+        assert_eq!(effective_permissions(&user_id, &workshop, &members), vec![CompanyPermission::Work]);
+        assert_eq!(effective_permissions(&user_id, &factory, &members), vec![CompanyPermission::MemberCreate]);
+        assert_eq!(effective_permissions(&user_id, &coop, &members), vec![CompanyPermission::Use, CompanyPermission::Transfer]);
+        assert!(transitive_access_check(&user_id, &coop, &members, CompanyPermission::Transfer).is_ok());
+        let res = transitive_access_check(&user_id, &coop, &members, CompanyPermission::MemberCreate);
+        expect_denied(res, DenyReason::MissingPermission);
+        assert_eq!(effective_permissions(&UserID::create(), &coop, &members), vec![]);
+
+        // a direct membership adds to the inherited permissions
+        let mut members2 = members.clone();
+        members2.push(make_member_worker(&MemberID::create(), &user_id, coop.id(), &OccupationID::create(), vec![CompanyPermission::Consume], &now));
+        assert_eq!(effective_permissions(&user_id, &coop, &members2), vec![CompanyPermission::Use, CompanyPermission::Transfer, CompanyPermission::Consume]);
+
+        // inactive records break the chain
+        let mut workshop_member2 = workshop_member.clone();
+        workshop_member2.set_active(false);
+        let members3 = vec![factory_member.clone(), workshop_member2, user_member.clone()];
+        assert_eq!(effective_permissions(&user_id, &coop, &members3), vec![]);
+        let mut coop2 = coop.clone();
+        coop2.set_deleted(Some(now));
+        assert_eq!(effective_permissions(&user_id, &coop2, &members), vec![]);
+
+        // cycles aren't followed forever
+        let cycle_member = make_member_company(workshop.id(), coop.id(), vec![CompanyPermission::All], &now);
+        let mut members4 = members.clone();
+        members4.push(cycle_member);
+        assert_eq!(effective_permissions(&user_id, &coop, &members4), vec![CompanyPermission::Use, CompanyPermission::Transfer]);
+        // the co-op's grant at the workshop only reaches our user by going back
+        // through the workshop, so it doesn't count
+        assert_eq!(effective_permissions(&user_id, &workshop, &members4), vec![CompanyPermission::Work]);
+    }
+
+    #[test]
+    fn transitive_permissions_cap() {
+        let now = util::time::now();
+        let user_id = UserID::create();
+        let top = make_company(&CompanyID::create(), "top", &now);
+        let mut members = vec![];
+        let mut parent_id = top.id().clone();
+        let mut chain = vec![];
+        for _ in 0..(MAX_INHERITANCE_DEPTH + 1) {
+            let child_id = CompanyID::create();
+            let perms = if parent_id == *top.id() { vec![CompanyPermission::Use] } else { vec![] };
+            members.push(make_member_company(&parent_id, &child_id, perms, &now));
+            chain.push(child_id.clone());
+            parent_id = child_id;
+        }
+
+        // a member at the cap still inherits, one past it does not
+        let mut members2 = members.clone();
+        members2.push(make_member_worker(&MemberID::create(), &user_id, &chain[MAX_INHERITANCE_DEPTH - 1], &OccupationID::create(), vec![], &now));
+        assert_eq!(effective_permissions(&user_id, &top, &members2), vec![CompanyPermission::Use]);
+        let mut members3 = members.clone();
+        members3.push(make_member_worker(&MemberID::create(), &user_id, &chain[MAX_INHERITANCE_DEPTH], &OccupationID::create(), vec![], &now));
+        assert_eq!(effective_permissions(&user_id, &top, &members3), vec![]);
+    }
+    #[test]
+    fn loads_old_members() {
+        // saved before members had roles, delegations or vote proxies
+        let member: Member = serde_json::from_str(r#"{"id":"jerry","inner":{"object":{"company":"widgets"},"relationship":null,"subject":{"user":"jerry"}},"class":{"Worker":{"occupation":"machinist","compensation":null}},"permissions":["Work"],"active":true,"created":"2020-06-01T00:00:00Z","updated":"2020-06-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(member.id(), &MemberID::new("jerry"));
+        assert_eq!(member.permissions(), &vec![CompanyPermission::Work]);
+        assert_eq!(member.roles(), &Vec::<String>::new());
+        assert_eq!(member.delegations(), &vec![]);
+        assert_eq!(member.vote_proxies(), &vec![]);
+    }
+}
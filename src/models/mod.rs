@@ -27,16 +27,28 @@
 //! [Modifications]: struct.Modifications.html
 //! [vf]: https://valueflo.ws/
 
+use chrono::{DateTime, Utc};
 use crate::{
+    access::RequiredPermission,
     error::{Error, Result},
+    log,
+    models::{
+        audit_record::{AuditRecord, AuditRecordID},
+        company::CompanyID,
+        member::Member,
+        user::User,
+    },
 };
 use serde::{Serialize, Deserialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use std::convert::TryFrom;
 
 #[macro_use]
 pub(crate) mod lib;
 
 pub use lib::agent::{Agent, AgentID};
+pub use lib::diff::FieldDiff;
 
 // load all of our pub mod <model>; ... lines
 load_models!{ pub mod }
@@ -44,6 +56,9 @@ load_models!{ pub mod }
 // create an enum that contains all of our model types
 load_models!{ pub enum Model }
 
+// create an enum that references any of our models by ID
+load_models!{ pub enum_ref ModelRef }
+
 /// A type for determining if a model should be created, updated, or deleted.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Op {
@@ -56,18 +71,128 @@ pub enum Op {
 }
 
 /// Documents a modification to a model.
+///
+/// Updates and deletes bump the model's version, and note the version they
+/// expect to be replacing (`expected_version`). If the stored model's version
+/// doesn't match, someone else modified it after our copy was read, and
+/// applying this modification would silently undo their changes.
+///
+/// Updates can also carry a field-level `diff` against the copy of the model
+/// they were made from, so consumers (change feeds, audit UIs) can see what
+/// changed without having to dig up the old model.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Modification {
     /// The type of modification
     op: Op,
     /// The model we're modifying
     model: Model,
+    /// The version of the model we expect to be replacing (`None` for creates)
+    #[serde(default)]
+    expected_version: Option<u64>,
+    /// The fields this modification changes, if known (updates only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    diff: Option<Vec<FieldDiff>>,
 }
 
 impl Modification {
-    /// Create a new modification
-    pub(crate) fn new(op: Op, model: Model) -> Self {
-        Self { op, model }
+    /// Create a new modification. Created models start at version 0, and
+    /// updates/deletes bump the model's version.
+    pub(crate) fn new(op: Op, mut model: Model) -> Self {
+        let expected_version = match op {
+            Op::Create => {
+                model.set_version(0);
+                None
+            }
+            Op::Update | Op::Delete => {
+                let version = model.version();
+                model.set_version(version + 1);
+                Some(version)
+            }
+        };
+        Self { op, model, expected_version, diff: None }
+    }
+
+    /// Create a new update modification, noting which fields changed from
+    /// the `original` copy of the model.
+    pub(crate) fn new_update(original: Model, model: Model) -> Self {
+        let mut modification = Self::new(Op::Update, model);
+        modification.diff = modification.model.diff(&original);
+        modification
+    }
+
+    /// Create a modification that creates a model as-is, keeping its version
+    /// (as opposed to `Modification::new`, which resets created models to
+    /// version 0).
+    fn create_as_is(model: Model) -> Self {
+        Self { op: Op::Create, model, expected_version: None, diff: None }
+    }
+
+    /// Collapse two consecutive modifications to the same model into the one
+    /// with the same net effect, or `None` if they cancel each other out.
+    ///
+    /// Errors if `next` couldn't actually follow `self` (creating a model that
+    /// exists, updating one that doesn't, or expecting a version `self` didn't
+    /// leave behind).
+    fn squash(self, next: Modification) -> Result<Option<Self>> {
+        let model_ref = ModelRef::from(&next.model);
+        if self.op != Op::Delete && next.op != Op::Create {
+            next.check_version(Some(self.model.version()))?;
+        }
+        let squashed = match (self.op, next.op) {
+            (Op::Create, Op::Create) | (Op::Update, Op::Create) => Err(Error::ModelExists(model_ref))?,
+            (Op::Delete, Op::Update) | (Op::Delete, Op::Delete) => Err(Error::ModelNotFound(model_ref))?,
+            (Op::Create, Op::Update) => Some(Self::create_as_is(next.model)),
+            (Op::Create, Op::Delete) => None,
+            (Op::Update, Op::Update) => {
+                let diff = match (self.diff, next.diff) {
+                    (Some(first), Some(second)) => Some(lib::diff::combine(first, second)),
+                    _ => None,
+                };
+                Some(Self { op: Op::Update, model: next.model, expected_version: self.expected_version, diff })
+            }
+            (Op::Update, Op::Delete) => {
+                Some(Self { op: Op::Delete, model: next.model, expected_version: self.expected_version, diff: None })
+            }
+            // deleting and then recreating a model replaces it
+            (Op::Delete, Op::Create) => {
+                Some(Self { op: Op::Update, model: next.model, expected_version: self.expected_version, diff: None })
+            }
+        };
+        Ok(squashed)
+    }
+
+    /// The model being modified
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// The version of the model this modification expects to replace, if any.
+    pub fn expected_version(&self) -> Option<u64> {
+        self.expected_version
+    }
+
+    /// The fields this modification changes, if it was created with a diff.
+    pub fn diff(&self) -> Option<&Vec<FieldDiff>> {
+        self.diff.as_ref()
+    }
+
+    /// Check this modification against the version of the model currently
+    /// stored (if any), returning `Error::VersionConflict` if the stored model
+    /// has changed since this modification's copy of it was read.
+    ///
+    /// Updates and deletes must have an expected version (otherwise we return
+    /// `Error::VersionMissing`), so modifications replayed from storage can't
+    /// skip the check by leaving it out.
+    pub fn check_version(&self, stored_version: Option<u64>) -> Result<()> {
+        match (&self.op, self.expected_version, stored_version) {
+            (Op::Update, None, _) | (Op::Delete, None, _) => {
+                Err(Error::VersionMissing(ModelRef::from(&self.model)))
+            }
+            (_, Some(expected), Some(found)) if expected != found => {
+                Err(Error::VersionConflict(ModelRef::from(&self.model), expected, found))
+            }
+            _ => Ok(()),
+        }
     }
 
     /// Turn this modification into a pair. Good for implementing saving logic:
@@ -139,6 +264,10 @@ impl Modification {
 /// This is passed back by successfully run transactions. You can use a set of
 /// modifications either by converting into a vec (`into_vec()`), or using an
 /// iterator.
+///
+/// The last modification returned by a transaction is always the creation of
+/// an [AuditRecord](audit_record/struct.AuditRecord.html) describing who ran
+/// it.
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Modifications {
     /// The model modifications we're making
@@ -158,6 +287,81 @@ impl Modifications {
         mods
     }
 
+    /// Create a new modification set with a single update, including a diff
+    /// against the `original` model
+    pub(crate) fn new_update<T: Into<Model>>(original: T, model: T) -> Self {
+        let mut mods = Self::new();
+        mods.push_update(original, model);
+        mods
+    }
+
+    /// Merge a number of modification sets into one, in order.
+    pub fn merge<I: IntoIterator<Item = Modifications>>(sets: I) -> Self {
+        let mut merged = Self::new();
+        for mods in sets {
+            merged.append(mods);
+        }
+        merged
+    }
+
+    /// Append another set of modifications onto the end of this one.
+    pub fn append(&mut self, other: Modifications) {
+        self.modifications.extend(other.modifications);
+    }
+
+    /// Collapse the set so each model is modified at most once, by its net
+    /// operation. A create followed by updates becomes a single create, a
+    /// create followed by a delete cancels out entirely, and updates followed
+    /// by a delete become a delete. Updates keep the version they originally
+    /// expected, and their diffs are combined.
+    ///
+    /// Models stay in the order they were first modified in. Applying the
+    /// squashed set has the same result as applying the original one, and
+    /// like applying it, fails if the modifications don't follow each other
+    /// (ie, updating a model after it's been deleted, or from a version other
+    /// than the one the previous modification left behind).
+    ///
+    /// ```rust
+    /// use basis_core::{
+    ///     models::{Modifications, Op, user::{User, UserID}},
+    ///     transactions,
+    /// };
+    /// use chrono::Utc;
+    ///
+    /// let now = Utc::now();
+    /// let mods = transactions::user::create(UserID::create(), "andrew@lyonbros.com", "andrew", true, &now).unwrap();
+    /// let user = mods.clone().into_vec()[0].clone().expect_op::<User>(Op::Create).unwrap();
+    /// let mods2 = transactions::user::update(&user, user.clone(), None, Some("andrew lyon".into()), None, &now).unwrap();
+    /// let squashed = Modifications::merge(vec![mods, mods2]).squash().unwrap().into_vec();
+    /// // one user create, two audit records
+    /// assert_eq!(squashed.len(), 3);
+    /// let user2 = squashed[0].clone().expect_op::<User>(Op::Create).unwrap();
+    /// assert_eq!(user2.name(), "andrew lyon");
+    /// ```
+    pub fn squash(self) -> Result<Self> {
+        // the net modification for each model (`None` meaning the model was
+        // created and deleted within the set)
+        let mut squashed: Vec<(ModelRef, Option<Modification>)> = Vec::new();
+        for modification in self.modifications {
+            let model_ref = ModelRef::from(&modification.model);
+            let entry = match squashed.iter_mut().find(|(squashed_ref, _)| squashed_ref == &model_ref) {
+                Some((_, entry)) => entry,
+                None => {
+                    squashed.push((model_ref, Some(modification)));
+                    continue;
+                }
+            };
+            *entry = match entry.take() {
+                Some(previous) => previous.squash(modification)?,
+                None if modification.op == Op::Create => Some(modification),
+                None => Err(Error::ModelNotFound(model_ref))?,
+            };
+        }
+        Ok(Self {
+            modifications: squashed.into_iter().filter_map(|(_, modification)| modification).collect(),
+        })
+    }
+
     /// Consume the modification set and return the list of modifications
     pub fn into_vec(self) -> Vec<Modification> {
         self.modifications
@@ -173,6 +377,113 @@ impl Modifications {
     pub(crate) fn push<T: Into<Model>>(&mut self, op: Op, model: T) {
         self.push_raw(Modification::new(op, model.into()));
     }
+
+    /// Push an update into the list, including a diff against the `original`
+    /// model
+    pub(crate) fn push_update<T: Into<Model>>(&mut self, original: T, model: T) {
+        self.push_raw(Modification::new_update(original.into(), model.into()));
+    }
+
+    /// Compute the modifications that undo this set, given the copies of the
+    /// models as they were *before* it was applied (`originals`). Creates
+    /// become deletes (marking the model deleted as of `now`), while updates
+    /// and deletes are undone by updating the model back to its original.
+    ///
+    /// A model modified more than once in the set is reverted straight to its
+    /// original. The undo modifications expect the versions this set left
+    /// behind, so if anything has modified the models since, applying them
+    /// fails with `Error::VersionConflict` rather than clobbering the newer
+    /// changes. Restored models also keep counting up from those versions, so
+    /// stale copies of them can't sneak in afterwards.
+    ///
+    /// Audit records are left alone: reverting a transaction doesn't change
+    /// the fact that it ran.
+    pub fn invert(&self, originals: &[Model], now: &DateTime<Utc>) -> Result<Self> {
+        // the first and last modification made to each model, in the order
+        // the models were first touched
+        let mut touched: Vec<(ModelRef, &Modification, &Modification)> = Vec::new();
+        for modification in &self.modifications {
+            if let Model::AuditRecord(_) = modification.model {
+                continue;
+            }
+            let model_ref = ModelRef::from(&modification.model);
+            match touched.iter_mut().find(|(touched_ref, ..)| touched_ref == &model_ref) {
+                Some(entry) => entry.2 = modification,
+                None => touched.push((model_ref, modification, modification)),
+            }
+        }
+
+        let mut inverse = Self::new();
+        for (model_ref, first, last) in touched.into_iter().rev() {
+            let original = match first.op {
+                Op::Create => None,
+                Op::Update | Op::Delete => {
+                    let original = originals.iter()
+                        .find(|model| ModelRef::from(*model) == model_ref)
+                        .ok_or_else(|| Error::RevertMissingOriginal(model_ref.clone()))?;
+                    // make sure this is the copy the set actually replaced
+                    first.check_version(Some(original.version()))?;
+                    Some(original.clone())
+                }
+            };
+            let version = last.model.version();
+            match (original, &last.op) {
+                // created and then deleted, nothing to undo
+                (None, Op::Delete) => {}
+                (None, _) => {
+                    let mut model = last.model.clone();
+                    model.set_deleted(Some(*now));
+                    inverse.push(Op::Delete, model);
+                }
+                (Some(mut original), _) => {
+                    original.set_version(version);
+                    inverse.push_update(last.model.clone(), original);
+                }
+            }
+        }
+        Ok(inverse)
+    }
+
+    /// Append an [AuditRecord][audit] noting who ran the transaction that
+    /// produced this modification set, and which models it touched. This is
+    /// the last thing a transaction does before returning.
+    ///
+    /// The record's id is derived from the transaction's name, caller, scope,
+    /// permissions, time, and the modifications it made, so running the same
+    /// transaction on the same inputs always produces the same output.
+    ///
+    /// [audit]: audit_record/struct.AuditRecord.html
+    pub(crate) fn audit<T: Into<String>>(mut self, transaction: T, caller: &User, member: Option<&Member>, company_id: Option<&CompanyID>, permissions: Vec<RequiredPermission>, now: &DateTime<Utc>) -> Result<Self> {
+        let transaction = transaction.into();
+        let contents = json!({
+            "transaction": transaction,
+            "user_id": caller.id(),
+            "member_id": member.map(|member| member.id()),
+            "company_id": company_id,
+            "permissions": permissions,
+            "now": now,
+            "modifications": self.modifications,
+        });
+        let id = Sha256::digest(log::canonical_json(&contents).as_bytes()).iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let record = AuditRecord::builder()
+            .id(AuditRecordID::new(id))
+            .user_id(caller.id().clone())
+            .member_id(member.map(|member| member.id().clone()))
+            .company_id(company_id.cloned())
+            .transaction(transaction)
+            .permissions(permissions)
+            .affected(self.modifications.iter().map(|modification| ModelRef::from(&modification.model)).collect::<Vec<_>>())
+            .vote(*caller.vote())
+            .active(true)
+            .created(*now)
+            .updated(*now)
+            .build()
+            .map_err(Error::BuilderFailed)?;
+        self.push(Op::Create, record);
+        Ok(self)
+    }
 }
 
 impl IntoIterator for Modifications {
@@ -225,5 +536,200 @@ mod tests {
         let res = mods[0].clone().expect_op::<Process>(Op::Update);
         assert_eq!(res, Err(Error::OpMismatch));
     }
-}
 
+    #[test]
+    fn versions() {
+        let now = util::time::now();
+        let mut user = make_user(&UserID::new("slappy"), None, &now);
+        user.set_version(3);
+        let mut modifications = Modifications::new_single(Op::Create, user.clone());
+        modifications.push(Op::Update, user.clone());
+        modifications.push(Op::Delete, user.clone());
+        let mods = modifications.into_vec();
+
+        assert_eq!(mods[0].expected_version(), None);
+        assert_eq!(mods[0].model().version(), 0);
+        assert_eq!(mods[1].expected_version(), Some(3));
+        assert_eq!(mods[1].model().version(), 4);
+        assert_eq!(mods[2].expected_version(), Some(3));
+        assert_eq!(mods[2].model().version(), 4);
+
+        assert_eq!(mods[0].check_version(Some(12)), Ok(()));
+        assert_eq!(mods[1].check_version(Some(3)), Ok(()));
+        assert_eq!(mods[1].check_version(None), Ok(()));
+        assert_eq!(mods[1].check_version(Some(4)), Err(Error::VersionConflict(ModelRef::User(UserID::new("slappy")), 3, 4)));
+
+        // updates and deletes that lost their expected version (ie, in storage)
+        // don't get a free pass
+        for modification in &mods[1..] {
+            let mut json = serde_json::to_value(modification).unwrap();
+            json.as_object_mut().unwrap().remove("expected_version");
+            let stripped: Modification = serde_json::from_value(json).unwrap();
+            assert_eq!(stripped.expected_version(), None);
+            assert_eq!(stripped.check_version(Some(3)), Err(Error::VersionMissing(ModelRef::User(UserID::new("slappy")))));
+        }
+        let mut json = serde_json::to_value(&mods[0]).unwrap();
+        json.as_object_mut().unwrap().remove("expected_version");
+        let stripped: Modification = serde_json::from_value(json).unwrap();
+        assert_eq!(stripped.check_version(None), Ok(()));
+    }
+
+    #[test]
+    fn diffs() {
+        let now = util::time::now();
+        let user = make_user(&UserID::new("slappy"), None, &now);
+        let mut user2 = user.clone();
+        user2.set_name("slappy jr".into());
+        let mut modifications = Modifications::new_update(user.clone(), user2.clone());
+        modifications.push(Op::Update, user2.clone());
+        modifications.push(Op::Delete, user2);
+        let mods = modifications.into_vec();
+
+        assert_eq!(mods[0].diff(), Some(&vec![
+            FieldDiff::new("name", "buzzin' frog".into(), "slappy jr".into()),
+            FieldDiff::new("version", 0.into(), 1.into()),
+        ]));
+        assert_eq!(mods[0].expected_version(), Some(0));
+        assert_eq!(mods[1].diff(), None);
+        assert_eq!(mods[2].diff(), None);
+
+        let ser = serde_json::to_string(&mods[1]).unwrap();
+        assert!(!ser.contains("diff"));
+        let ser = serde_json::to_string(&mods[0]).unwrap();
+        let modification: Modification = serde_json::from_str(&ser).unwrap();
+        assert_eq!(modification, mods[0]);
+    }
+
+    #[test]
+    fn audit() {
+        let now = util::time::now();
+        let user = make_user(&UserID::new("slappy"), None, &now);
+        let audited = |user: &User, transaction: &str| {
+            Modifications::new_single(Op::Create, user.clone())
+                .audit(transaction, user, None, None, vec![], &now).unwrap()
+                .into_vec()[1].clone()
+                .expect_op::<AuditRecord>(Op::Create).unwrap()
+        };
+
+        let record = audited(&user, "user.create");
+        assert_eq!(record.transaction(), "user.create");
+        assert_eq!(record.affected(), &vec![ModelRef::User(user.id().clone())]);
+        assert_eq!(record, audited(&user, "user.create"));
+        assert!(record.id() != audited(&user, "user.update").id());
+        let mut user2 = user.clone();
+        user2.set_name("slappy jr".into());
+        assert!(record.id() != audited(&user2, "user.create").id());
+    }
+
+    #[test]
+    fn invert() {
+        let now = util::time::now();
+        let user = make_user(&UserID::new("slappy"), None, &now);
+        let mut user2 = user.clone();
+        user2.set_name("slappy jr".into());
+        let created = make_user(&UserID::new("jinkies"), None, &now);
+        let temp = make_user(&UserID::new("zoinks"), None, &now);
+        let mut modifications = Modifications::new_update(user.clone(), user2.clone());
+        modifications.push(Op::Create, created.clone());
+        modifications.push(Op::Create, temp.clone());
+        modifications.push(Op::Delete, temp.clone());
+        let mods = modifications.clone().into_vec();
+        let user2 = mods[0].clone().expect_op::<User>(Op::Update).unwrap();
+
+        let now2 = util::time::now();
+        let inverse = modifications.invert(&[user.clone().into()], &now2).unwrap().into_vec();
+        assert_eq!(inverse.len(), 2);
+        let created2 = inverse[0].clone().expect_op::<User>(Op::Delete).unwrap();
+        assert_eq!(created2.id(), created.id());
+        assert_eq!(created2.deleted(), &Some(now2));
+        assert_eq!(inverse[0].expected_version(), Some(0));
+        assert_eq!(inverse[1].expected_version(), Some(1));
+        assert_eq!(inverse[1].diff(), Some(&vec![
+            FieldDiff::new("name", "slappy jr".into(), "buzzin' frog".into()),
+            FieldDiff::new("version", 1.into(), 2.into()),
+        ]));
+        let user3 = inverse[1].clone().expect_op::<User>(Op::Update).unwrap();
+        assert_eq!(user3.name(), user.name());
+        assert_eq!(user3.version(), &2);
+
+        let res = modifications.invert(&[], &now2);
+        assert_eq!(res, Err(Error::RevertMissingOriginal(ModelRef::User(UserID::new("slappy")))));
+        let res = modifications.invert(&[user2.clone().into()], &now2);
+        assert_eq!(res, Err(Error::VersionConflict(ModelRef::User(UserID::new("slappy")), 0, 1)));
+
+        // deleted models are updated back to their original, without
+        // resetting their version
+        let mut deleted = user2.clone();
+        deleted.set_deleted(Some(now2));
+        let modifications = Modifications::new_single(Op::Delete, deleted);
+        let inverse = modifications.invert(&[user2.clone().into()], &now2).unwrap().into_vec();
+        assert_eq!(inverse[0].expected_version(), Some(2));
+        let user4 = inverse[0].clone().expect_op::<User>(Op::Update).unwrap();
+        assert_eq!(user4.name(), user2.name());
+        assert_eq!(user4.deleted(), &None);
+        assert_eq!(user4.version(), &3);
+    }
+
+    #[test]
+    fn squash() {
+        let now = util::time::now();
+        let user = make_user(&UserID::new("slappy"), None, &now);
+        let mut user2 = user.clone();
+        user2.set_name("slappy jr".into());
+        let mods = Modifications::new_single(Op::Create, user.clone());
+        let user = mods.clone().into_vec()[0].clone().expect_op::<User>(Op::Create).unwrap();
+        let mods2 = Modifications::new_update(user.clone(), user2.clone());
+        let user2 = mods2.clone().into_vec()[0].clone().expect_op::<User>(Op::Update).unwrap();
+        let mut user3 = user2.clone();
+        user3.set_email("slappy@jr.com".into());
+        let mods3 = Modifications::new_update(user2.clone(), user3.clone());
+        let user3 = mods3.clone().into_vec()[0].clone().expect_op::<User>(Op::Update).unwrap();
+        let mods4 = Modifications::new_single(Op::Delete, user3.clone());
+        let other = make_user(&UserID::new("jinkies"), None, &now);
+        let mods5 = Modifications::new_single(Op::Create, other.clone());
+
+        // create + updates = create
+        let squashed = Modifications::merge(vec![mods.clone(), mods5.clone(), mods2.clone(), mods3.clone()]).squash().unwrap().into_vec();
+        assert_eq!(squashed.len(), 2);
+        let user4 = squashed[0].clone().expect_op::<User>(Op::Create).unwrap();
+        assert_eq!(user4, user3);
+        assert_eq!(squashed[1].clone().expect_op::<User>(Op::Create).unwrap().id(), other.id());
+
+        // updates + updates = update, with the first expected version
+        let squashed = Modifications::merge(vec![mods2.clone(), mods3.clone()]).squash().unwrap().into_vec();
+        assert_eq!(squashed.len(), 1);
+        assert_eq!(squashed[0].expected_version(), Some(0));
+        assert_eq!(squashed[0].model().version(), 2);
+        assert_eq!(squashed[0].diff(), Some(&vec![
+            FieldDiff::new("email", "surely@hotmail.com".into(), "slappy@jr.com".into()),
+            FieldDiff::new("name", "buzzin' frog".into(), "slappy jr".into()),
+            FieldDiff::new("version", 0.into(), 2.into()),
+        ]));
+
+        // update + delete = delete
+        let squashed = Modifications::merge(vec![mods2.clone(), mods3.clone(), mods4.clone()]).squash().unwrap().into_vec();
+        assert_eq!(squashed.len(), 1);
+        assert_eq!(squashed[0].clone().expect_op::<User>(Op::Delete).unwrap().version(), &3);
+        assert_eq!(squashed[0].expected_version(), Some(0));
+
+        // create + delete = nothing
+        let squashed = Modifications::merge(vec![mods.clone(), mods2.clone(), mods5.clone(), mods3.clone(), mods4.clone()]).squash().unwrap().into_vec();
+        assert_eq!(squashed.len(), 1);
+        assert_eq!(squashed[0].clone().expect_op::<User>(Op::Create).unwrap().id(), other.id());
+
+        // delete + create = update
+        let squashed = Modifications::merge(vec![mods4.clone(), mods.clone()]).squash().unwrap().into_vec();
+        assert_eq!(squashed.len(), 1);
+        assert_eq!(squashed[0].clone().expect_op::<User>(Op::Update).unwrap(), user);
+        assert_eq!(squashed[0].expected_version(), Some(2));
+
+        let res = Modifications::merge(vec![mods.clone(), mods.clone()]).squash();
+        assert_eq!(res, Err(Error::ModelExists(ModelRef::User(UserID::new("slappy")))));
+        let res = Modifications::merge(vec![mods4.clone(), mods3.clone()]).squash();
+        assert_eq!(res, Err(Error::ModelNotFound(ModelRef::User(UserID::new("slappy")))));
+        let res = Modifications::merge(vec![mods2.clone(), mods3.clone(), mods4.clone(), mods4.clone()]).squash();
+        assert_eq!(res, Err(Error::ModelNotFound(ModelRef::User(UserID::new("slappy")))));
+        let res = Modifications::merge(vec![mods.clone(), mods3.clone()]).squash();
+        assert_eq!(res, Err(Error::VersionConflict(ModelRef::User(UserID::new("slappy")), 1, 0)));
+    }
+}
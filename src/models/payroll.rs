@@ -0,0 +1,227 @@
+//! Payroll is the process of turning recorded labor (work events) into credits
+//! paid into a worker's account.
+//!
+//! Each run of payroll covers a single pay period, which is determined by the
+//! worker's [PayrollSchedule] and creates a `Payroll` record so we can always
+//! trace where a worker's credits came from.
+//!
+//! [PayrollSchedule]: ../member/enum.PayrollSchedule.html
+
+use chrono::{Datelike, DateTime, Duration, NaiveDate, TimeZone, Utc};
+use crate::{
+    costs::Costs,
+    error::{Error, Result},
+    models::{
+        account::AccountID,
+        company::CompanyID,
+        event::EventID,
+        member::{MemberID, PayrollSchedule},
+    },
+    util,
+};
+use getset::Getters;
+use rust_decimal::prelude::*;
+use serde::{Serialize, Deserialize};
+
+/// Biweekly pay periods are counted in two-week blocks starting from this date
+/// (a Monday).
+const BIWEEKLY_ANCHOR: (i32, u32, u32) = (1970, 1, 5);
+
+/// Describes the span of time a run of payroll covers. The start of the
+/// period is inclusive and the end is exclusive.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct PayPeriod {
+    /// When the period starts (inclusive)
+    start: DateTime<Utc>,
+    /// When the period ends (exclusive)
+    end: DateTime<Utc>,
+}
+
+impl PayPeriod {
+    /// Create a new pay period
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    /// Find the pay period for the given schedule that contains `date`.
+    ///
+    /// - `BiWeekly` periods are two weeks long and always start on a Monday.
+    /// - `SemiMonthly` periods run from the 1st through the 15th, and the 16th
+    ///   through the last day of the month.
+    pub fn from_schedule(schedule: &PayrollSchedule, date: &DateTime<Utc>) -> Result<Self> {
+        let day = date.naive_utc().date();
+        let (start, end) = match schedule {
+            PayrollSchedule::BiWeekly => {
+                let anchor = make_date(BIWEEKLY_ANCHOR.0, BIWEEKLY_ANCHOR.1, BIWEEKLY_ANCHOR.2)?;
+                let days_since = day.signed_duration_since(anchor).num_days();
+                let start = anchor + Duration::days(days_since.div_euclid(14) * 14);
+                (start, start + Duration::days(14))
+            }
+            PayrollSchedule::SemiMonthly => {
+                if day.day() <= 15 {
+                    (make_date(day.year(), day.month(), 1)?, make_date(day.year(), day.month(), 16)?)
+                } else {
+                    let next_month = if day.month() == 12 {
+                        make_date(day.year() + 1, 1, 1)?
+                    } else {
+                        make_date(day.year(), day.month() + 1, 1)?
+                    };
+                    (make_date(day.year(), day.month(), 16)?, next_month)
+                }
+            }
+        };
+        Ok(Self::new(start_of_day(start)?, start_of_day(end)?))
+    }
+
+    /// Determine if a point in time falls within this period.
+    pub fn contains(&self, date: &DateTime<Utc>) -> bool {
+        date >= self.start() && date < self.end()
+    }
+
+    /// Return the portion of this period that overlaps with the given range, if
+    /// any. This is how we handle partial periods (for instance, a worker who
+    /// was hired halfway through a period).
+    pub fn clamp(&self, start: &DateTime<Utc>, end: &DateTime<Utc>) -> Option<Self> {
+        let clamped_start = *std::cmp::max(self.start(), start);
+        let clamped_end = *std::cmp::min(self.end(), end);
+        if clamped_end <= clamped_start {
+            return None;
+        }
+        Some(Self::new(clamped_start, clamped_end))
+    }
+
+    /// Prorate a yearly amount (ie, a salary) over this period.
+    ///
+    /// The period is split on year boundaries so that each piece is prorated
+    /// against the actual length of its year (leap years have 366 days).
+    pub fn prorate_yearly(&self, yearly: Decimal) -> Result<Decimal> {
+        let mut total = Decimal::zero();
+        let mut cursor = *self.start();
+        while &cursor < self.end() {
+            let year = cursor.year();
+            let next_year = start_of_day(make_date(year + 1, 1, 1)?)?;
+            let segment_end = std::cmp::min(next_year, *self.end());
+            let days_in_year = if util::time::is_leap_year(year as i64) { 366 } else { 365 };
+            let year_seconds = Decimal::from(days_in_year * 24 * 60 * 60);
+            let segment_seconds = Decimal::from((segment_end - cursor).num_seconds());
+            total += (yearly * segment_seconds) / year_seconds;
+            cursor = segment_end;
+        }
+        Ok(total)
+    }
+}
+
+/// Create a date, erroring if the given date is not valid.
+fn make_date(year: i32, month: u32, day: u32) -> Result<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .ok_or_else(|| Error::DateInvalid(format!("{}-{}-{}", year, month, day)))
+}
+
+/// Get a UTC time at the very beginning of the given day.
+fn start_of_day(date: NaiveDate) -> Result<DateTime<Utc>> {
+    let naive = date.and_hms_opt(0, 0, 0)
+        .ok_or_else(|| Error::DateInvalid(format!("{}", date)))?;
+    Ok(Utc.from_utc_datetime(&naive))
+}
+
+basis_model! {
+    /// A record of a single payroll run for one worker over one pay period.
+    pub struct Payroll {
+        id: <<PayrollID>>,
+        /// The worker that was paid
+        member_id: MemberID,
+        /// The company the worker was paid by
+        company_id: CompanyID,
+        /// The account the credits were paid into
+        account_id: AccountID,
+        /// The period this payroll run covers
+        period: PayPeriod,
+        /// The work events that were counted in this run
+        events: Vec<EventID>,
+        /// The total number of hours worked (or estimated) in this period
+        #[serde(serialize_with = "crate::models::lib::diff::serialize_decimal")]
+        hours: Decimal,
+        /// The wage costs recorded by the work events in this period
+        costs: Costs,
+        /// The number of credits paid to the worker
+        #[serde(serialize_with = "crate::models::lib::diff::serialize_decimal")]
+        amount: Decimal,
+    }
+    PayrollBuilder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::*;
+
+    fn date(val: &str) -> DateTime<Utc> {
+        val.parse().unwrap()
+    }
+
+    #[test]
+    fn biweekly() {
+        let period = PayPeriod::from_schedule(&PayrollSchedule::BiWeekly, &date("2020-06-17T12:44:00Z")).unwrap();
+        assert_eq!(period.start(), &date("2020-06-15T00:00:00Z"));
+        assert_eq!(period.end(), &date("2020-06-29T00:00:00Z"));
+        assert!(period.contains(&date("2020-06-15T00:00:00Z")));
+        assert!(period.contains(&date("2020-06-28T23:59:59Z")));
+        assert!(!period.contains(&date("2020-06-29T00:00:00Z")));
+
+        let period2 = PayPeriod::from_schedule(&PayrollSchedule::BiWeekly, &date("2020-06-29T00:00:00Z")).unwrap();
+        assert_eq!(period2.start(), &date("2020-06-29T00:00:00Z"));
+        assert_eq!(period2.end(), &date("2020-07-13T00:00:00Z"));
+
+        // spans a leap day
+        let period3 = PayPeriod::from_schedule(&PayrollSchedule::BiWeekly, &date("2020-02-29T08:00:00Z")).unwrap();
+        assert_eq!(period3.start(), &date("2020-02-24T00:00:00Z"));
+        assert_eq!(period3.end(), &date("2020-03-09T00:00:00Z"));
+
+        // before our anchor
+        let period4 = PayPeriod::from_schedule(&PayrollSchedule::BiWeekly, &date("1969-12-31T08:00:00Z")).unwrap();
+        assert_eq!(period4.start(), &date("1969-12-22T00:00:00Z"));
+        assert_eq!(period4.end(), &date("1970-01-05T00:00:00Z"));
+    }
+
+    #[test]
+    fn semimonthly() {
+        let period = PayPeriod::from_schedule(&PayrollSchedule::SemiMonthly, &date("2020-06-15T23:59:59Z")).unwrap();
+        assert_eq!(period.start(), &date("2020-06-01T00:00:00Z"));
+        assert_eq!(period.end(), &date("2020-06-16T00:00:00Z"));
+
+        let period = PayPeriod::from_schedule(&PayrollSchedule::SemiMonthly, &date("2020-02-20T00:00:00Z")).unwrap();
+        assert_eq!(period.start(), &date("2020-02-16T00:00:00Z"));
+        assert_eq!(period.end(), &date("2020-03-01T00:00:00Z"));
+        assert!(period.contains(&date("2020-02-29T12:00:00Z")));
+
+        let period = PayPeriod::from_schedule(&PayrollSchedule::SemiMonthly, &date("2019-12-31T00:00:00Z")).unwrap();
+        assert_eq!(period.start(), &date("2019-12-16T00:00:00Z"));
+        assert_eq!(period.end(), &date("2020-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn clamp() {
+        let period = PayPeriod::from_schedule(&PayrollSchedule::SemiMonthly, &date("2020-06-02T00:00:00Z")).unwrap();
+        let clamped = period.clamp(&date("2020-06-10T00:00:00Z"), &date("2021-01-01T00:00:00Z")).unwrap();
+        assert_eq!(clamped.start(), &date("2020-06-10T00:00:00Z"));
+        assert_eq!(clamped.end(), &date("2020-06-16T00:00:00Z"));
+        assert_eq!(period.clamp(&date("2020-07-01T00:00:00Z"), &date("2021-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn prorate_yearly() {
+        let period = PayPeriod::new(date("2019-01-01T00:00:00Z"), date("2020-01-01T00:00:00Z"));
+        assert_eq!(period.prorate_yearly(dec!(36500)).unwrap(), dec!(36500));
+
+        // leap years are 366 days long
+        let period = PayPeriod::new(date("2020-02-01T00:00:00Z"), date("2020-02-02T00:00:00Z"));
+        assert_eq!(period.prorate_yearly(dec!(36600)).unwrap(), dec!(100));
+        let period = PayPeriod::new(date("2019-02-01T00:00:00Z"), date("2019-02-02T00:00:00Z"));
+        assert_eq!(period.prorate_yearly(dec!(36500)).unwrap(), dec!(100));
+
+        // spanning years prorates each side against its own year
+        let period = PayPeriod::new(date("2019-12-31T00:00:00Z"), date("2020-01-02T00:00:00Z"));
+        assert_eq!(period.prorate_yearly(dec!(36500)).unwrap().round_dp(6), (dec!(100) + (dec!(36500) / dec!(366))).round_dp(6));
+    }
+}
@@ -2,6 +2,7 @@
 //! and dividers/subtractors of costs via their outputs, resources and services.
 
 use crate::{
+    access::Acl,
     costs::{Costs, CostMover},
     models::{
         company::CompanyID,
@@ -32,6 +33,9 @@ basis_model! {
         company_id: CompanyID,
         /// Our costs tally for this process
         costs: Costs,
+        /// Restricts which members can perform which actions on this process
+        /// (on top of their company permissions)
+        acl: Option<Acl>,
     }
     ProcessBuilder
 }
@@ -0,0 +1,478 @@
+//! Proposals are the decision-making process behind the [vote system][vote].
+//!
+//! A proposal describes a transaction that a group wants to run, either
+//! systemically (voted on by users) or within a company (voted on by the
+//! company's members). Eligible voters cast [Ballots] until the proposal
+//! closes, at which point the ballots are tallied against the proposal's
+//! [VoteRules]. If the proposal passes, a [Vote] is created and used to run the
+//! transaction, and the voting user's ID is recorded on the proposal so the
+//! resulting changes (and their [audit records][audit]) can be traced back to
+//! the ballots that justified them.
+//!
+//! The core doesn't know how to serialize or deserialize transactions, so the
+//! proposal stores the name of the transaction along with an opaque payload
+//! (ie, JSON-encoded arguments) that the implementation is responsible for
+//! interpreting when the proposal passes.
+//!
+//! [vote]: ../../system/vote/index.html
+//! [Ballots]: ../ballot/struct.Ballot.html
+//! [VoteRules]: struct.VoteRules.html
+//! [Vote]: ../../system/vote/struct.Vote.html
+//! [audit]: ../audit_record/struct.AuditRecord.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    models::{
+        ballot::{Ballot, BallotChoice},
+        company::CompanyID,
+        lib::{
+            agent::{Agent, AgentID},
+            basis_model::Model,
+        },
+        member::{Member, MemberID, resolve_vote_delegate},
+        user::UserID,
+    },
+};
+use getset::Getters;
+use rust_decimal::prelude::*;
+use serde::{Serialize, Deserialize};
+
+/// Who gets to decide on a proposal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ProposalScope {
+    /// A system-wide decision, voted on by users
+    Systemic,
+    /// A decision within a company, voted on by its members
+    Company(CompanyID),
+}
+
+/// The share of yes votes (out of all yes/no votes) needed to pass a proposal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Threshold {
+    /// More than half
+    Majority,
+    /// At least the given share, ie `2/3`
+    Supermajority(#[serde(serialize_with = "crate::models::lib::diff::serialize_decimal")] Decimal),
+    /// Nobody votes no
+    Unanimous,
+}
+
+/// The rules a proposal's ballots are tallied against.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct VoteRules {
+    /// The share of eligible voters (0 to 1) that must cast a ballot
+    /// (including abstentions) for the result to count.
+    #[serde(serialize_with = "crate::models::lib::diff::serialize_decimal")]
+    quorum: Decimal,
+    /// The share of yes votes needed to pass
+    threshold: Threshold,
+}
+
+impl VoteRules {
+    /// Create a new set of voting rules
+    pub fn new(quorum: Decimal, threshold: Threshold) -> Self {
+        Self {
+            quorum,
+            threshold,
+        }
+    }
+
+    /// Determine if these rules make sense.
+    pub fn is_valid(&self) -> bool {
+        let in_range = |val: &Decimal| val >= &Decimal::zero() && val <= &Decimal::one();
+        let threshold_valid = match self.threshold() {
+            Threshold::Supermajority(share) => in_range(share) && !share.is_zero(),
+            _ => true,
+        };
+        in_range(self.quorum()) && threshold_valid
+    }
+}
+
+/// Where a proposal is at in its life.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ProposalStatus {
+    /// Accepting ballots
+    Open,
+    /// Closed and passed (and its transaction has been run)
+    Passed,
+    /// Closed without passing
+    Failed,
+}
+
+/// Who gets a say when a proposal's ballots are counted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Electorate {
+    /// Only the listed voters' own ballots count (see [Proposal::tally][tally])
+    ///
+    /// [tally]: struct.Proposal.html#method.tally
+    Voters(Vec<AgentID>),
+    /// The active members of a company vote, and their vote delegations are
+    /// honored (see [Proposal::tally_delegated][tally])
+    ///
+    /// [tally]: struct.Proposal.html#method.tally_delegated
+    Members(Vec<Member>),
+}
+
+/// The result of counting a proposal's ballots.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Tally {
+    /// How many voters were eligible
+    eligible: u64,
+    /// Yes votes
+    yes: u64,
+    /// No votes
+    no: u64,
+    /// Abstentions
+    abstain: u64,
+    /// Whether enough eligible voters cast ballots
+    quorum_met: bool,
+    /// Whether the proposal passed (quorum was met and the yes votes cleared
+    /// the threshold)
+    passed: bool,
+}
+
+impl Tally {
+    /// Determine if enough eligible voters cast ballots under the given rules.
+    pub fn meets_quorum(&self, rules: &VoteRules) -> bool {
+        let cast = self.yes + self.no + self.abstain;
+        self.eligible > 0 && Decimal::from(cast) >= rules.quorum() * Decimal::from(self.eligible)
+    }
+
+    /// Determine if these votes pass under the given rules (quorum is met and
+    /// the yes votes clear the threshold).
+    pub fn passes(&self, rules: &VoteRules) -> bool {
+        let (yes, no) = (self.yes, self.no);
+        let cleared = match rules.threshold() {
+            Threshold::Majority => yes > no,
+            Threshold::Supermajority(share) => yes > 0 && Decimal::from(yes) >= share * Decimal::from(yes + no),
+            Threshold::Unanimous => yes > 0 && no == 0,
+        };
+        self.meets_quorum(rules) && cleared
+    }
+}
+
+basis_model! {
+    /// A proposal to run a transaction, decided by vote.
+    pub struct Proposal {
+        id: <<ProposalID>>,
+        /// Who votes on this proposal
+        scope: ProposalScope,
+        /// A short description of the proposal
+        title: String,
+        /// A longer description of the proposal
+        description: Option<String>,
+        /// What the proposal is about (ie, `budget`), used to decide which of
+        /// a member's [vote proxies][proxy] applies
+        ///
+        /// [proxy]: ../member/struct.VoteProxy.html
+        topic: Option<String>,
+        /// The transaction to run if the proposal passes, ie `company::update`
+        transaction: String,
+        /// The arguments for the transaction, serialized in whatever format the
+        /// implementation chooses
+        payload: String,
+        /// The quorum/threshold this proposal is decided by
+        rules: VoteRules,
+        /// When voting ends
+        closes: DateTime<Utc>,
+        /// Whether the proposal is open, passed, or failed
+        status: ProposalStatus,
+        /// The final count of the ballots, set when the proposal closes
+        result: Option<Tally>,
+        /// The ID of the voting user that ran the transaction (if the proposal
+        /// passed). This is the `user_id` of the transaction's audit record.
+        vote_user_id: Option<UserID>,
+    }
+    ProposalBuilder
+}
+
+impl Proposal {
+    /// Determine if this proposal is accepting ballots at the given time.
+    pub fn is_open_at(&self, now: &DateTime<Utc>) -> bool {
+        self.is_active() && self.status() == &ProposalStatus::Open && now < self.closes()
+    }
+
+    /// Count the ballots for this proposal against the given electorate.
+    pub fn count_votes(&self, ballots: &[Ballot], electorate: &Electorate) -> Tally {
+        match electorate {
+            Electorate::Voters(eligible) => self.tally(ballots, eligible),
+            Electorate::Members(members) => self.tally_delegated(ballots, members),
+        }
+    }
+
+    /// Count the ballots for this proposal.
+    ///
+    /// `eligible` is the list of voters allowed to vote on this proposal (users
+    /// for systemic proposals, members of the company for company proposals).
+    /// Ballots from anyone else, ballots for other proposals, and inactive
+    /// ballots are ignored. If a voter has more than one ballot, the most
+    /// recently updated one counts.
+    pub fn tally(&self, ballots: &[Ballot], eligible: &[AgentID]) -> Tally {
+        let weighted = self.latest_ballots(ballots, eligible).into_iter()
+            .map(|ballot| (ballot, 1))
+            .collect::<Vec<_>>();
+        self.count(&weighted, eligible.len() as u64)
+    }
+
+    /// Count the ballots for a company proposal, giving each ballot an extra
+    /// vote for every member whose [vote proxies][proxy] resolve to the member
+    /// that cast it (see [resolve_vote_delegate][resolve]).
+    ///
+    /// `members` is the list of the company's members: the active ones are
+    /// eligible to vote. Member records for other companies are ignored (as is
+    /// everyone, for systemic proposals), as are proxies pointing to them.
+    /// Members who cast their own ballot are never counted through a proxy,
+    /// and members whose proxies go nowhere (or loop) don't count at all.
+    ///
+    /// [proxy]: ../member/struct.VoteProxy.html
+    /// [resolve]: ../member/fn.resolve_vote_delegate.html
+    pub fn tally_delegated(&self, ballots: &[Ballot], members: &[Member]) -> Tally {
+        let members = members.iter()
+            .filter(|member| {
+                member.is_active() && match self.scope() {
+                    ProposalScope::Company(company_id) => member.group_id() == &company_id.clone().into(),
+                    ProposalScope::Systemic => false,
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let eligible = members.iter()
+            .map(|member| member.agent_id())
+            .collect::<Vec<_>>();
+        let mut weighted = self.latest_ballots(ballots, &eligible).into_iter()
+            .map(|ballot| (ballot, 1))
+            .collect::<Vec<_>>();
+        let has_voted = |member_id: &MemberID| {
+            let voter: AgentID = member_id.clone().into();
+            weighted.iter().any(|(ballot, _)| ballot.voter() == &voter)
+        };
+        let delegates = members.iter()
+            .filter(|member| !has_voted(member.id()))
+            .filter_map(|member| resolve_vote_delegate(&members, member.id(), self.topic().as_ref(), has_voted))
+            .map(AgentID::from)
+            .collect::<Vec<_>>();
+        for delegate in delegates {
+            if let Some((_, weight)) = weighted.iter_mut().find(|(ballot, _)| ballot.voter() == &delegate) {
+                *weight += 1;
+            }
+        }
+        self.count(&weighted, eligible.len() as u64)
+    }
+
+    /// Grab the ballots that count for this proposal: the most recent active
+    /// ballot of each eligible voter.
+    fn latest_ballots<'a>(&self, ballots: &'a [Ballot], eligible: &[AgentID]) -> Vec<&'a Ballot> {
+        let mut latest: Vec<&Ballot> = Vec::new();
+        for ballot in ballots {
+            if ballot.proposal_id() != self.id() || !ballot.is_active() || !eligible.contains(ballot.voter()) {
+                continue;
+            }
+            match latest.iter_mut().find(|existing| existing.voter() == ballot.voter()) {
+                Some(existing) => {
+                    if ballot.updated() > existing.updated() {
+                        *existing = ballot;
+                    }
+                }
+                None => latest.push(ballot),
+            }
+        }
+        latest
+    }
+
+    /// Count a set of (ballot, weight) pairs against our voting rules.
+    fn count(&self, weighted: &[(&Ballot, u64)], eligible: u64) -> Tally {
+        let count = |choice: BallotChoice| {
+            weighted.iter()
+                .filter(|(ballot, _)| ballot.choice() == &choice)
+                .map(|(_, weight)| weight)
+                .sum::<u64>()
+        };
+        let mut tally = Tally {
+            eligible,
+            yes: count(BallotChoice::Yes),
+            no: count(BallotChoice::No),
+            abstain: count(BallotChoice::Abstain),
+            quorum_met: false,
+            passed: false,
+        };
+        tally.quorum_met = tally.meets_quorum(self.rules());
+        tally.passed = tally.passes(self.rules());
+        tally
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{
+            ballot::BallotID,
+            company::CompanyID,
+            member::VoteProxy,
+            occupation::OccupationID,
+            user::UserID,
+        },
+        util::{self, test::*},
+    };
+    use rust_decimal_macros::*;
+
+    fn make_ballot<T: Into<AgentID> + Clone>(proposal_id: &ProposalID, voter: &T, choice: BallotChoice, now: &DateTime<Utc>) -> Ballot {
+        Ballot::builder()
+            .id(BallotID::create())
+            .proposal_id(proposal_id.clone())
+            .voter(voter.clone())
+            .choice(choice)
+            .active(true)
+            .created(*now)
+            .updated(*now)
+            .build().unwrap()
+    }
+
+    fn make_proposal(now: &DateTime<Utc>) -> Proposal {
+        Proposal::builder()
+            .id(ProposalID::create())
+            .scope(ProposalScope::Systemic)
+            .title("more parks")
+            .transaction("company::create")
+            .payload("{}")
+            .rules(VoteRules::new(dec!(0.5), Threshold::Majority))
+            .closes(*now)
+            .status(ProposalStatus::Open)
+            .active(true)
+            .created(*now)
+            .updated(*now)
+            .build().unwrap()
+    }
+
+    #[test]
+    fn tally() {
+        let now = util::time::now();
+        let mut proposal = make_proposal(&now);
+        let voters = (0..6).map(|_| UserID::create()).collect::<Vec<_>>();
+        let eligible = voters.iter().map(|id| id.clone().into()).collect::<Vec<AgentID>>();
+        let ballots = vec![
+            make_ballot(proposal.id(), &voters[0], BallotChoice::Yes, &now),
+            make_ballot(proposal.id(), &voters[1], BallotChoice::Yes, &now),
+            make_ballot(proposal.id(), &voters[2], BallotChoice::No, &now),
+            // not eligible
+            make_ballot(proposal.id(), &UserID::create(), BallotChoice::No, &now),
+            // wrong proposal
+            make_ballot(&ProposalID::create(), &voters[3], BallotChoice::No, &now),
+        ];
+        let tally = proposal.tally(&ballots, &eligible);
+        assert_eq!(tally.eligible(), &6);
+        assert_eq!(tally.yes(), &2);
+        assert_eq!(tally.no(), &1);
+        assert_eq!(tally.abstain(), &0);
+        assert_eq!(tally.quorum_met(), &true);
+        assert_eq!(tally.passed(), &true);
+
+        // a voter changing their mind only counts once
+        let mut ballots2 = ballots.clone();
+        let later = now + chrono::Duration::seconds(10);
+        let mut changed = make_ballot(proposal.id(), &voters[1], BallotChoice::No, &now);
+        changed.set_updated(later);
+        ballots2.push(changed);
+        let tally = proposal.tally(&ballots2, &eligible);
+        assert_eq!(tally.yes(), &1);
+        assert_eq!(tally.no(), &2);
+        assert_eq!(tally.passed(), &false);
+
+        // abstentions count toward quorum, but quorum is still required
+        proposal.set_rules(VoteRules::new(dec!(0.75), Threshold::Majority));
+        let tally = proposal.tally(&ballots, &eligible);
+        assert_eq!(tally.quorum_met(), &false);
+        assert_eq!(tally.passed(), &false);
+        let mut ballots3 = ballots.clone();
+        ballots3.push(make_ballot(proposal.id(), &voters[3], BallotChoice::Abstain, &now));
+        ballots3.push(make_ballot(proposal.id(), &voters[4], BallotChoice::Abstain, &now));
+        let tally = proposal.tally(&ballots3, &eligible);
+        assert_eq!(tally.abstain(), &2);
+        assert_eq!(tally.quorum_met(), &true);
+        assert_eq!(tally.passed(), &true);
+
+        proposal.set_rules(VoteRules::new(dec!(0.5), Threshold::Supermajority(dec!(0.67))));
+        assert_eq!(proposal.tally(&ballots, &eligible).passed(), &false);
+        proposal.set_rules(VoteRules::new(dec!(0.5), Threshold::Supermajority(dec!(0.66))));
+        assert_eq!(proposal.tally(&ballots, &eligible).passed(), &true);
+        proposal.set_rules(VoteRules::new(dec!(0.5), Threshold::Unanimous));
+        assert_eq!(proposal.tally(&ballots, &eligible).passed(), &false);
+
+        assert_eq!(proposal.tally(&ballots, &[]).passed(), &false);
+    }
+
+    #[test]
+    fn tally_delegated() {
+        let now = util::time::now();
+        let mut proposal = make_proposal(&now);
+        proposal.set_topic(Some("budget".into()));
+        let company_id = CompanyID::create();
+        proposal.set_scope(ProposalScope::Company(company_id.clone()));
+        let mut members = (0..8)
+            .map(|_| make_member_worker(&MemberID::create(), &UserID::create(), &company_id, &OccupationID::create(), vec![], &now))
+            .collect::<Vec<_>>();
+        let ids = members.iter().map(|member| member.id().clone()).collect::<Vec<_>>();
+        let proxy = |idx: usize, topic: Option<&str>| VoteProxy::new(ids[idx].clone(), topic.map(|topic| topic.into()));
+        // b votes directly, so their delegation is ignored
+        members[1].set_vote_proxies(vec![proxy(0, None)]);
+        // c delegates to a, and d delegates to c (and therefore to a)
+        members[2].set_vote_proxies(vec![proxy(0, None)]);
+        members[3].set_vote_proxies(vec![proxy(2, None)]);
+        // e delegates budget votes to b, and everything else to a
+        members[4].set_vote_proxies(vec![proxy(0, None), proxy(1, Some("budget"))]);
+        // f and g delegate to each other, so nobody gets their votes
+        members[5].set_vote_proxies(vec![proxy(6, None)]);
+        members[6].set_vote_proxies(vec![proxy(5, None)]);
+        // h is inactive, so isn't eligible
+        members[7].set_vote_proxies(vec![proxy(0, None)]);
+        members[7].set_active(false);
+        let ballots = vec![
+            make_ballot(proposal.id(), &ids[0], BallotChoice::Yes, &now),
+            make_ballot(proposal.id(), &ids[1], BallotChoice::No, &now),
+            make_ballot(proposal.id(), &ids[7], BallotChoice::No, &now),
+        ];
+
+        let tally = proposal.tally_delegated(&ballots, &members);
+        assert_eq!(tally.eligible(), &7);
+        assert_eq!(tally.yes(), &3);
+        assert_eq!(tally.no(), &2);
+
+        // members of other companies don't count, and padding the list with
+        // them doesn't change the quorum
+        let mut padded = members.clone();
+        let mut outsiders = (0..4)
+            .map(|_| make_member_worker(&MemberID::create(), &UserID::create(), &CompanyID::create(), &OccupationID::create(), vec![], &now))
+            .collect::<Vec<_>>();
+        let outsider_id = outsiders[0].id().clone();
+        outsiders[1].set_vote_proxies(vec![VoteProxy::new(outsider_id, None)]);
+        let mut ballots2 = ballots.clone();
+        ballots2.push(make_ballot(proposal.id(), outsiders[0].id(), BallotChoice::Yes, &now));
+        padded.append(&mut outsiders);
+        let tally = proposal.tally_delegated(&ballots2, &padded);
+        assert_eq!(tally.eligible(), &7);
+        assert_eq!(tally.yes(), &3);
+        assert_eq!(tally.no(), &2);
+        assert_eq!(tally.abstain(), &0);
+        assert_eq!(tally.quorum_met(), &true);
+        assert_eq!(tally.passed(), &true);
+
+        proposal.set_topic(None);
+        let tally = proposal.tally_delegated(&ballots, &members);
+        assert_eq!(tally.yes(), &4);
+        assert_eq!(tally.no(), &1);
+
+        // if a doesn't vote, c and d's votes go nowhere
+        let tally = proposal.tally_delegated(&ballots[1..], &members);
+        assert_eq!(tally.yes(), &0);
+        assert_eq!(tally.no(), &1);
+        assert_eq!(tally.quorum_met(), &false);
+
+        // systemic proposals have no members
+        proposal.set_scope(ProposalScope::Systemic);
+        let tally = proposal.tally_delegated(&ballots, &members);
+        assert_eq!(tally.eligible(), &0);
+        assert_eq!(tally.yes(), &0);
+    }
+}
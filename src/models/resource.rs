@@ -6,6 +6,7 @@
 //! you, what you get is a resource (a manifestation of the chair specification).
 
 use crate::{
+    access::Acl,
     costs::{Costs, CostMover},
     models::{
         lib::agent::AgentID,
@@ -36,6 +37,10 @@ basis_model! {
         /// and we have a measure of 16g, the `5 hours` cost encompasses all
         /// 16g.
         costs: Costs,
+        /// Restricts which members can perform which actions on this resource
+        /// (on top of their company permissions). Resources created from a
+        /// spec inherit the spec's ACL.
+        acl: Option<Acl>,
     }
     ResourceBuilder
 }
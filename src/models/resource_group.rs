@@ -1,16 +1,35 @@
+//! Resource groups gather resource specifications that are, for the purposes
+//! of tracking, the same resource. The iron coming out of one mine and the iron
+//! coming out of another are two different resource specs (specs are
+//! namespaced per-company) but both belong in the "iron" group.
+//!
+//! Grouping lets us look at the `resource` bucket of [Costs] per-resource
+//! instead of per-producer, and gives each resource a globally-decided value.
+//!
+//! Note that resource groups require global systemic management.
+//!
+//! [Costs]: ../../costs/struct.Costs.html
+
+use rust_decimal::prelude::*;
+
 basis_model! {
     /// Acts as a group for various products classified as resources.
     ///
     /// For instance, a group might be "iron", and all the iron produced by iron
     /// mines might link to the group.
     pub struct ResourceGroup {
+        id: <<ResourceGroupID>>,
         /// The name of the group, generally will be some easily-identifiable
         /// resource name like "iron" or "silicon" or "fresh water"
         name: String,
-        /// The globally-decided cost (in credits) for products under this group.
-        credit_cost_per_unit: f64,
+        /// Any notes about the group, ie which unit the resources in it are
+        /// measured in
+        note: Option<String>,
+        /// The globally-decided cost (in credits) for one unit of the products
+        /// under this group.
+        #[serde(serialize_with = "crate::models::lib::diff::serialize_decimal")]
+        credit_cost_per_unit: Decimal,
     }
-    ResourceGroupID
     ResourceGroupBuilder
 }
 
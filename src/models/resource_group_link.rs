@@ -1,19 +1,27 @@
+//! A resource group link puts a resource specification into a
+//! [resource group][1].
+//!
+//! [1]: ../resource_group/index.html
+
 use crate::{
     models::{
         resource_spec::ResourceSpecID,
         resource_group::ResourceGroupID,
     },
 };
+
 basis_model! {
+    /// Links a resource spec to the resource group it belongs to. A resource
+    /// spec can only be in one group at a time.
     pub struct ResourceGroupLink {
+        id: <<ResourceGroupLinkID>>,
         /// The ID of the resource group.
         group_id: ResourceGroupID,
-        /// The ID of the product we're linking to the group.
-        product_id: ResourceSpecID,
+        /// The ID of the resource spec we're linking to the group.
+        resource_spec_id: ResourceSpecID,
         // TODO: at some point, store meta information about the resource
         // quantity/renewal/depletion/etc
     }
-    ResourceGroupLinkID
     ResourceGroupLinkBuilder
 }
 
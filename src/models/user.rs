@@ -6,14 +6,11 @@
 //! by a `User` object.
 
 use crate::{
-    access::{Permission, Role},
+    access::{CanAccess, Permission, Role},
     models::{
-        lib::{
-            agent::{Agent, AgentID},
-            basis_model::Model,
-        },
+        lib::agent::{Agent, AgentID},
     },
-    error::{Error, Result},
+    error::Result,
 };
 
 basis_model! {
@@ -27,6 +24,13 @@ basis_model! {
         email: String,
         /// The user's full name.
         name: String,
+        /// Set if this user was created by the [vote system][vote] to act on
+        /// behalf of a vote, as opposed to being a real person.
+        ///
+        /// [vote]: ../../system/vote/index.html
+        #[builder(default)]
+        #[serde(default)]
+        vote: bool,
     }
     UserBuilder
 }
@@ -34,23 +38,26 @@ basis_model! {
 impl User {
     /// Determines if a user can perform an action (base on their roles).
     pub fn can(&self, permission: &Permission) -> bool {
-        if !self.is_active() {
-            return false;
-        }
-        for role in self.roles() {
-            if role.can(permission) {
-                return true;
-            }
-        }
-        false
+        self.can_access(&(), permission)
     }
 
     /// Check if this user can perform an action.
     pub fn access_check(&self, permission: Permission) -> Result<()> {
-        if !self.can(&permission) {
-            Err(Error::InsufficientPrivileges)?;
-        }
-        Ok(())
+        self.check_access(&(), &permission)
+    }
+}
+
+impl CanAccess for User {
+    type Role = Role;
+    /// A user's roles apply system-wide.
+    type Scope = ();
+
+    fn access_roles(&self, _scope: &Self::Scope) -> Vec<Self::Role> {
+        self.roles().clone()
+    }
+
+    fn in_scope(&self, _scope: &Self::Scope) -> bool {
+        true
     }
 }
 
@@ -0,0 +1,271 @@
+//! The core doesn't store anything itself: transactions hand back
+//! [Modifications] and it's up to the caller to save them. The `Store` trait
+//! describes the bare minimum a storage layer needs to do (look models up by
+//! ID and apply a set of modifications) so that code built on the core can be
+//! written against any backend.
+//!
+//! [MemoryStore] is a reference implementation that keeps everything in memory.
+//! It's mainly useful for tests, but also documents the semantics a store is
+//! expected to have:
+//!
+//! - `Op::Create` fails if a model with the same ID already exists (deleted
+//!   or not)
+//! - `Op::Update` fails if the model doesn't exist
+//! - `Op::Delete` fails if the model doesn't exist, and otherwise stores the
+//!   deleted copy of it. Models are never removed, just marked deleted, so
+//!   they can still be looked up (and referenced) afterwards
+//! - `Op::Update` and `Op::Delete` fail with `Error::VersionConflict` if the
+//!   stored model's version isn't the one the [Modification] expects (ie,
+//!   another transaction updated it in the meantime)
+//! - if any modification in a set fails, none of them are applied
+//!
+//! ```rust
+//! use basis_core::{
+//!     models::user::{User, UserID},
+//!     store::{MemoryStore, Store},
+//!     transactions,
+//! };
+//! use chrono::Utc;
+//!
+//! let mut store = MemoryStore::new();
+//! let user_id = UserID::create();
+//! let mods = transactions::user::create(user_id.clone(), "andrew@lyonbros.com", "andrew", true, &Utc::now()).unwrap();
+//! store.apply(mods).unwrap();
+//! let user: User = store.get(user_id).unwrap().unwrap();
+//! assert_eq!(user.name(), "andrew");
+//! ```
+//!
+//! [Modifications]: ../models/struct.Modifications.html
+//! [MemoryStore]: struct.MemoryStore.html
+//! [Modification]: ../models/struct.Modification.html
+
+use crate::{
+    error::{Error, Result},
+    models::{Model, ModelRef, Modifications, Op},
+};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// A place to keep models.
+pub trait Store {
+    /// Grab any model by its reference.
+    fn get_ref(&self, model_ref: &ModelRef) -> Result<Option<Model>>;
+
+    /// Apply a set of modifications. This must be atomic: either all of the
+    /// modifications are applied, or (on error) none of them are.
+    fn apply(&mut self, mods: Modifications) -> Result<()>;
+
+    /// Grab a model by its ID, ie `let user: User = store.get(user_id)?`.
+    ///
+    /// Returns `Error::WrongModelType` if the model found isn't a `T` (which
+    /// would mean the store is handing back the wrong models).
+    fn get<T, I>(&self, id: I) -> Result<Option<T>>
+        where T: TryFrom<Model>,
+              I: Into<ModelRef>,
+              Self: Sized,
+    {
+        match self.get_ref(&id.into())? {
+            Some(model) => Ok(Some(T::try_from(model).map_err(|_| Error::WrongModelType)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A `Store` that keeps its models in memory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryStore {
+    /// Our models, indexed by reference
+    models: HashMap<ModelRef, Model>,
+}
+
+impl MemoryStore {
+    /// Create a new, empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many models are in the store
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    /// Whether the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+}
+
+impl Store for MemoryStore {
+    fn get_ref(&self, model_ref: &ModelRef) -> Result<Option<Model>> {
+        Ok(self.models.get(model_ref).cloned())
+    }
+
+    fn apply(&mut self, mods: Modifications) -> Result<()> {
+        // stage the changes so we can bail without touching our models if any
+        // of them fail
+        let mut staged: HashMap<ModelRef, Model> = HashMap::new();
+        for modification in mods {
+            let model_ref = ModelRef::from(modification.model());
+            let existing = staged.get(&model_ref).or_else(|| self.models.get(&model_ref));
+            let exists = existing.is_some();
+            modification.check_version(existing.map(|model| model.version()))?;
+            let (op, model) = modification.into_pair();
+            match op {
+                Op::Create if exists => Err(Error::ModelExists(model_ref))?,
+                Op::Update | Op::Delete if !exists => Err(Error::ModelNotFound(model_ref))?,
+                // deletes store the deleted copy of the model
+                Op::Create | Op::Update | Op::Delete => { staged.insert(model_ref, model); }
+            }
+        }
+        self.models.extend(staged);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::Role,
+        models::{
+            audit_record::AuditRecord,
+            company::{Company, CompanyID},
+            member::{Member, MemberClass, MemberID, MemberWorker},
+            occupation::OccupationID,
+            user::{User, UserID},
+        },
+        transactions,
+        util::{self, test::*},
+    };
+
+    #[test]
+    fn applies_modifications() {
+        let now = util::time::now();
+        let mut store = MemoryStore::new();
+        let user = make_user(&UserID::create(), None, &now);
+        let company_id = CompanyID::create();
+        let member_id = MemberID::create();
+        let founder = transactions::company::Founder::new(member_id.clone(), MemberClass::Worker(MemberWorker::new(OccupationID::create(), None)), true);
+        let mods = transactions::company::create(&user, company_id.clone(), "jerry's widgets", "jerry@widgets.expert", true, founder, &now).unwrap();
+        store.apply(mods.clone()).unwrap();
+        assert_eq!(store.len(), 3);
+        let company: Company = store.get(company_id.clone()).unwrap().unwrap();
+        assert_eq!(company.inner().name(), "jerry's widgets");
+        let member: Member = store.get(member_id.clone()).unwrap().unwrap();
+        assert_eq!(member.company_id().unwrap(), company_id);
+        let record_id = mods.clone().into_vec().last().unwrap().clone().expect_op::<AuditRecord>(Op::Create).unwrap().id().clone();
+        assert!(store.get::<AuditRecord, _>(record_id).unwrap().is_some());
+        assert_eq!(store.get::<User, _>(user.id().clone()).unwrap(), None);
+        assert_eq!(store.get::<User, _>(ModelRef::Company(company_id.clone())), Err(Error::WrongModelType));
+
+        // no creating things twice
+        let res = store.apply(mods);
+        assert_eq!(res, Err(Error::ModelExists(ModelRef::Company(company_id.clone()))));
+
+        let admin = make_user(user.id(), Some(vec![Role::SuperAdmin]), &now);
+        let mods = transactions::company::update(&admin, None, company.clone(), Some("jerry's WIDGETS".into()), None, None, &now).unwrap();
+        store.apply(mods).unwrap();
+        let company2: Company = store.get(company_id.clone()).unwrap().unwrap();
+        assert_eq!(company2.inner().name(), "jerry's WIDGETS");
+        assert_eq!(store.len(), 4);
+
+        // a second update made from the same (now stale) copy of the company
+        // would clobber the first one
+        let mods = transactions::company::update(&admin, None, company.clone(), None, Some("jerry@widgets.biz".into()), None, &now).unwrap();
+        let res = store.apply(mods);
+        assert_eq!(res, Err(Error::VersionConflict(ModelRef::Company(company_id.clone()), 0, 1)));
+        assert_eq!(store.get::<Company, _>(company_id.clone()).unwrap().unwrap(), company2);
+
+        // deleted models stick around, marked deleted
+        let mods = transactions::company::delete(&admin, None, company2.clone(), &now).unwrap();
+        store.apply(mods.clone()).unwrap();
+        let company3: Company = store.get(company_id.clone()).unwrap().unwrap();
+        assert_eq!(company3.deleted(), &Some(now));
+        assert_eq!(company3.version(), &2);
+        assert_eq!(store.len(), 5);
+        let res = store.apply(mods);
+        assert_eq!(res, Err(Error::VersionConflict(ModelRef::Company(company_id.clone()), 1, 2)));
+
+        // updates (and deletes) need something to update, and a failed set of
+        // modifications leaves the store untouched
+        let store2 = store.clone();
+        let company4 = make_company(&CompanyID::create(), "larry's chairs", &now);
+        let res = store.apply(transactions::company::delete(&admin, None, company4.clone(), &now).unwrap());
+        assert_eq!(res, Err(Error::ModelNotFound(ModelRef::Company(company4.id().clone()))));
+        assert_eq!(store, store2);
+        let mut member2 = member.clone();
+        member2.set_roles(vec!["coordinator".into()]);
+        let mut mods = Modifications::new_single(Op::Update, member2);
+        mods.push(Op::Update, make_company(&CompanyID::create(), "larry's chairs", &now));
+        assert!(store.apply(mods).is_err());
+        assert_eq!(store, store2);
+    }
+
+    #[test]
+    fn reverts_modifications() {
+        let now = util::time::now();
+        let mut store = MemoryStore::new();
+        let admin = make_user(&UserID::create(), Some(vec![Role::SuperAdmin]), &now);
+        let company = make_company(&CompanyID::create(), "jerry's widgets", &now);
+        let mods = Modifications::new_single(Op::Create, company.clone());
+        store.apply(mods.clone()).unwrap();
+        let company: Company = store.get(company.id().clone()).unwrap().unwrap();
+
+        let update_mods = transactions::company::update(&admin, None, company.clone(), Some("jerry's WIDGETS".into()), None, None, &now).unwrap();
+        store.apply(update_mods.clone()).unwrap();
+        let undo = update_mods.invert(&[company.clone().into()], &now).unwrap();
+        store.apply(undo).unwrap();
+        let company2: Company = store.get(company.id().clone()).unwrap().unwrap();
+        assert_eq!(company2.inner().name(), "jerry's widgets");
+        assert_eq!(company2.version(), &2);
+        // the audit record of the update sticks around
+        assert_eq!(store.len(), 2);
+
+        // once something else modifies the company, the update can no longer
+        // be reverted
+        let update_mods = transactions::company::update(&admin, None, company2.clone(), Some("jerry's WIDGETS".into()), None, None, &now).unwrap();
+        store.apply(update_mods.clone()).unwrap();
+        let company3: Company = store.get(company.id().clone()).unwrap().unwrap();
+        let mods2 = transactions::company::update(&admin, None, company3.clone(), None, Some("jerry@widgets.biz".into()), None, &now).unwrap();
+        store.apply(mods2).unwrap();
+        let undo = update_mods.invert(&[company2.clone().into()], &now).unwrap();
+        let res = store.apply(undo);
+        assert_eq!(res, Err(Error::VersionConflict(ModelRef::Company(company.id().clone()), 3, 4)));
+
+        // creates are reverted by deleting, deletes by restoring
+        let company4: Company = store.get(company.id().clone()).unwrap().unwrap();
+        let delete_mods = transactions::company::delete(&admin, None, company4.clone(), &now).unwrap();
+        store.apply(delete_mods.clone()).unwrap();
+        store.apply(delete_mods.invert(&[company4.clone().into()], &now).unwrap()).unwrap();
+        let company5: Company = store.get(company.id().clone()).unwrap().unwrap();
+        assert_eq!(company5.email(), "jerry@widgets.biz");
+        assert_eq!(company5.version(), &6);
+        let res = store.apply(mods.invert(&[], &now).unwrap());
+        assert_eq!(res, Err(Error::VersionConflict(ModelRef::Company(company.id().clone()), 0, 6)));
+    }
+
+    #[test]
+    fn applies_squashed_modifications() {
+        let now = util::time::now();
+        let user = make_user(&UserID::create(), None, &now);
+        let company_id = CompanyID::create();
+        let member_id = MemberID::create();
+        let founder = transactions::company::Founder::new(member_id.clone(), MemberClass::Worker(MemberWorker::new(OccupationID::create(), None)), true);
+        let mods = transactions::company::create(&user, company_id.clone(), "jerry's widgets", "jerry@widgets.expert", true, founder, &now).unwrap();
+        let company = mods.clone().into_vec()[0].clone().expect_op::<Company>(Op::Create).unwrap();
+        let member = mods.clone().into_vec()[1].clone().expect_op::<Member>(Op::Create).unwrap();
+        let mods2 = transactions::company::update(&user, Some(&member), company.clone(), Some("jerry's WIDGETS".into()), None, None, &now).unwrap();
+        let company2 = mods2.clone().into_vec()[0].clone().expect_op::<Company>(Op::Update).unwrap();
+        let mods3 = transactions::company::update(&user, Some(&member), company2.clone(), None, Some("jerry@widgets.biz".into()), None, &now).unwrap();
+
+        let mut store = MemoryStore::new();
+        for set in [mods.clone(), mods2.clone(), mods3.clone()] {
+            store.apply(set).unwrap();
+        }
+        let squashed = Modifications::merge(vec![mods, mods2, mods3]).squash().unwrap();
+        assert_eq!(squashed.clone().into_vec().len(), 5);
+        let mut store2 = MemoryStore::new();
+        store2.apply(squashed).unwrap();
+        assert_eq!(store, store2);
+    }
+}
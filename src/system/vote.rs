@@ -4,7 +4,9 @@
 //! specific companies.
 //!
 //! The idea here is to provide an interface for democracy without the core
-//! needing to know the implementation details.
+//! needing to know the implementation details. That said, if you want the core
+//! to handle the decision-making as well, see [proposals][proposal], which
+//! track ballots and create `Vote` objects when they pass.
 //!
 //! ```rust
 //! use basis_core::{
@@ -36,6 +38,8 @@
 //! assert_eq!(company_voter.member().as_ref().unwrap().inner().object(), &company_id.clone().into());
 //! assert_eq!(company_voter.member().as_ref().unwrap().permissions(), &vec![CompanyPermission::All]);
 //! ```
+//!
+//! [proposal]: ../../models/proposal/index.html
 
 use chrono::{DateTime, Utc};
 use crate::{
@@ -45,6 +49,7 @@ use crate::{
         company::{CompanyID, Permission as CompanyPermission},
         lib::agent::{Agent, AgentID},
         member::*,
+        proposal::{Proposal, ProposalScope, ProposalStatus},
         user::{User, UserID},
     },
 };
@@ -69,6 +74,7 @@ impl Vote {
     fn make_voter(id: UserID, role: Role, now: &DateTime<Utc>) -> Result<User> {
         User::builder()
             .roles(vec![role])
+            .vote(true)
             .email(format!("vote-{}@basisproject.net", id.as_str()))
             .name(format!("Vote {}", id.as_str()))
             .id(id)
@@ -99,8 +105,37 @@ impl Vote {
     /// Create a new voting company member.
     ///
     /// This is specifically for voting to run a transaction internal to a
-    /// company. This member is given company-wide admin abilities.
+    /// company. This member is given company-wide admin abilities, but *can't*
+    /// use the permissions the company's bylaws reserve for votes: only a
+    /// passed [proposal][proposal] can do that, since the core can check that
+    /// its tally satisfies the bylaws (see [proposal::close][close]).
+    ///
+    /// [proposal]: ../../models/proposal/index.html
+    /// [close]: ../../transactions/proposal/fn.close.html
     pub fn company(user_id: UserID, member_id: MemberID, company_id: &CompanyID, now: &DateTime<Utc>) -> Result<Self> {
+        Self::company_voter(user_id, member_id, company_id, false, now)
+    }
+
+    /// Create the voting user (and member, for company proposals) that runs a
+    /// passed [Proposal](../../models/proposal/struct.Proposal.html)'s
+    /// transaction. `member_id` is ignored for systemic proposals.
+    ///
+    /// Company vote members created here can use the permissions the company's
+    /// bylaws reserve for votes, so this is only for `proposal::close`, which
+    /// checks the proposal's tally against those bylaws.
+    pub(crate) fn from_proposal(proposal: &Proposal, user_id: UserID, member_id: MemberID, now: &DateTime<Utc>) -> Result<Self> {
+        if proposal.status() != &ProposalStatus::Passed {
+            Err(Error::ProposalInvalid)?;
+        }
+        match proposal.scope() {
+            ProposalScope::Systemic => Self::systemic(user_id, now),
+            ProposalScope::Company(company_id) => Self::company_voter(user_id, member_id, company_id, true, now),
+        }
+    }
+
+    /// Make a company voting user/member. `vote` decides whether the member can
+    /// use permissions reserved by the company's bylaws.
+    fn company_voter(user_id: UserID, member_id: MemberID, company_id: &CompanyID, vote: bool, now: &DateTime<Utc>) -> Result<Self> {
         let user = Self::make_voter(user_id, Role::User, now)?;
         let company_agent_id: AgentID = company_id.clone().into();
         let member = Member::builder()
@@ -116,6 +151,7 @@ impl Vote {
             .class(MemberClass::User(MemberUser::new()))
             .permissions(vec![CompanyPermission::All])
             .agreement(None)
+            .vote(vote)
             .active(true)
             .created(now.clone())
             .updated(now.clone())
@@ -132,8 +168,10 @@ impl Vote {
 mod tests {
     use super::*;
     use crate::{
+        models::proposal::{ProposalID, Threshold, VoteRules},
         util,
     };
+    use rust_decimal_macros::*;
 
     #[test]
     fn systemic() {
@@ -142,6 +180,7 @@ mod tests {
         let voter = Vote::systemic(user_id.clone(), &now).unwrap();
         assert_eq!(voter.user().id(), &user_id);
         assert_eq!(voter.user().roles(), &vec![Role::SuperAdmin]);
+        assert_eq!(voter.user().vote(), &true);
         assert_eq!(voter.user().active(), &true);
         assert_eq!(voter.user().created(), &now);
         assert_eq!(voter.user().updated(), &now);
@@ -171,9 +210,45 @@ mod tests {
             _ => panic!("voter::tests::company() -- bad class"),
         }
         assert_eq!(member.permissions(), &vec![CompanyPermission::All]);
+        assert_eq!(member.vote(), &false);
         assert_eq!(member.active(), &true);
         assert_eq!(member.created(), &now);
         assert_eq!(member.updated(), &now);
     }
-}
 
+    #[test]
+    fn from_proposal() {
+        let now = util::time::now();
+        let company_id = CompanyID::new("hairy larry's scrumptious dairies");
+        let mut proposal = Proposal::builder()
+            .id(ProposalID::create())
+            .scope(ProposalScope::Company(company_id.clone()))
+            .title("sell the dairy")
+            .transaction("company::delete")
+            .payload("{}")
+            .rules(VoteRules::new(dec!(0.5), Threshold::Majority))
+            .closes(now)
+            .status(ProposalStatus::Open)
+            .active(true)
+            .created(now)
+            .updated(now)
+            .build().unwrap();
+        let res = Vote::from_proposal(&proposal, UserID::create(), MemberID::create(), &now);
+        assert_eq!(res, Err(Error::ProposalInvalid));
+        proposal.set_status(ProposalStatus::Failed);
+        let res = Vote::from_proposal(&proposal, UserID::create(), MemberID::create(), &now);
+        assert_eq!(res, Err(Error::ProposalInvalid));
+
+        // only passed proposals make vote members
+        proposal.set_status(ProposalStatus::Passed);
+        let voter = Vote::from_proposal(&proposal, UserID::create(), MemberID::create(), &now).unwrap();
+        let member = voter.member().clone().unwrap();
+        assert_eq!(member.inner().object(), &company_id.clone().into());
+        assert_eq!(member.vote(), &true);
+
+        proposal.set_scope(ProposalScope::Systemic);
+        let voter = Vote::from_proposal(&proposal, UserID::create(), MemberID::create(), &now).unwrap();
+        assert_eq!(voter.user().roles(), &vec![Role::SuperAdmin]);
+        assert_eq!(voter.member(), &None);
+    }
+}
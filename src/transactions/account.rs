@@ -0,0 +1,206 @@
+//! Accounts hold the credits a user has earned through their labor.
+//!
+//! Users can only create, update, and delete their own accounts. Note that
+//! account balances cannot be set here: credits only ever enter accounts as a
+//! result of labor.
+//!
+//! See the [account model.][1]
+//!
+//! [1]: ../../models/account/index.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    access::{AccessDenied, DenyReason, Permission},
+    error::{Error, Result},
+    models::{
+        Op,
+        Modifications,
+        account::{Account, AccountID},
+        lib::basis_model::Model,
+        user::User,
+    },
+};
+use rust_decimal::prelude::*;
+
+/// Create a new account for the calling user, with a zero balance.
+pub fn create<T: Into<String>>(caller: &User, id: AccountID, name: T, description: T, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::AccountCreate)?;
+    let model = Account::builder()
+        .id(id)
+        .user_id(caller.id().clone())
+        .name(name)
+        .description(description)
+        .balance(Decimal::zero())
+        .active(active)
+        .created(*now)
+        .updated(*now)
+        .build()
+        .map_err(Error::BuilderFailed)?;
+    Modifications::new_single(Op::Create, model)
+        .audit("account::create", caller, None, None, vec![Permission::AccountCreate.into()], now)
+}
+
+/// Update an account's basic info.
+pub fn update(caller: &User, mut subject: Account, name: Option<String>, description: Option<String>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::AccountUpdate)?;
+    if !subject.is_owned_by(caller.id()) {
+        Err(AccessDenied::for_actor(DenyReason::NotOwner, caller.id().clone()))?;
+    }
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("account".into()))?;
+    }
+    let original = subject.clone();
+    if let Some(name) = name {
+        subject.set_name(name);
+    }
+    if let Some(description) = description {
+        subject.set_description(description);
+    }
+    if let Some(active) = active {
+        subject.set_active(active);
+    }
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("account::update", caller, None, None, vec![Permission::AccountUpdate.into()], now)
+}
+
+/// Delete an account. The account must have a zero balance, otherwise we'd be
+/// erasing credits.
+pub fn delete(caller: &User, mut subject: Account, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::AccountDelete)?;
+    if !subject.is_owned_by(caller.id()) {
+        Err(AccessDenied::for_actor(DenyReason::NotOwner, caller.id().clone()))?;
+    }
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("account".into()))?;
+    }
+    if !subject.balance().is_zero() {
+        Err(Error::CannotEraseCredits)?;
+    }
+    subject.set_deleted(Some(*now));
+    Modifications::new_single(Op::Delete, subject)
+        .audit("account::delete", caller, None, None, vec![Permission::AccountDelete.into()], now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{
+            user::UserID,
+        },
+        util::{self, test::{self, *}},
+    };
+    use rust_decimal_macros::*;
+
+    #[test]
+    fn can_create() {
+        let id = AccountID::create();
+        let now = util::time::now();
+        let state = TestState::standard(vec![], &now);
+
+        let testfn = |state: &TestState<Account, Account>| {
+            create(state.user(), id.clone(), "savings", "my savings account", true, &now)
+        };
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+
+        let account = mods[0].clone().expect_op::<Account>(Op::Create).unwrap();
+        assert_eq!(account.id(), &id);
+        assert_eq!(account.user_id(), state.user().id());
+        assert_eq!(account.name(), "savings");
+        assert_eq!(account.description(), "my savings account");
+        assert_eq!(account.balance(), &Decimal::zero());
+        assert_eq!(account.active(), &true);
+        assert_eq!(account.created(), &now);
+        assert_eq!(account.updated(), &now);
+        assert_eq!(account.deleted(), &None);
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_update() {
+        let id = AccountID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        let mods = create(state.user(), id.clone(), "savings", "my savings account", true, &now).unwrap().into_vec();
+        let account = mods[0].clone().expect_op::<Account>(Op::Create).unwrap();
+        state.model = Some(account);
+
+        let now2 = util::time::now();
+        let testfn = |state: &TestState<Account, Account>| {
+            update(state.user(), state.model().clone(), Some("checking".into()), None, Some(false), &now2)
+        };
+        test::double_deleted_tester(&state, "account", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+
+        let account2 = mods[0].clone().expect_op::<Account>(Op::Update).unwrap();
+        assert_eq!(account2.id(), &id);
+        assert_eq!(account2.user_id(), state.user().id());
+        assert_eq!(account2.name(), "checking");
+        assert_eq!(account2.description(), "my savings account");
+        assert_eq!(account2.balance(), &Decimal::zero());
+        assert_eq!(account2.active(), &false);
+        assert_eq!(account2.created(), &now);
+        assert_eq!(account2.updated(), &now2);
+
+        // can't update someone else's account
+        let mut state2 = state.clone();
+        state2.model_mut().set_user_id(UserID::create());
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::NotOwner);
+
+        let mut state3 = state.clone();
+        state3.user_mut().set_roles(vec![]);
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_delete() {
+        let id = AccountID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        let mods = create(state.user(), id.clone(), "savings", "my savings account", true, &now).unwrap().into_vec();
+        let account = mods[0].clone().expect_op::<Account>(Op::Create).unwrap();
+        state.model = Some(account);
+
+        let now2 = util::time::now();
+        let testfn = |state: &TestState<Account, Account>| {
+            delete(state.user(), state.model().clone(), &now2)
+        };
+        test::double_deleted_tester(&state, "account", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+
+        let account2 = mods[0].clone().expect_op::<Account>(Op::Delete).unwrap();
+        assert_eq!(account2.id(), &id);
+        assert_eq!(account2.created(), &now);
+        assert_eq!(account2.deleted(), &Some(now2));
+
+        // can't delete an account with credits in it
+        let mut state2 = state.clone();
+        state2.model_mut().set_balance(dec!(13.5));
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::CannotEraseCredits));
+
+        // can't delete someone else's account
+        let mut state3 = state.clone();
+        state3.model_mut().set_user_id(UserID::create());
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::NotOwner);
+
+        let mut state4 = state.clone();
+        state4.user_mut().set_roles(vec![]);
+        let res = testfn(&state4);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+}
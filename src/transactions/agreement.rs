@@ -3,22 +3,27 @@
 //!
 //! In other words, an agreement is basically an order.
 //!
+//! Agreements must be accepted by each of their participants before they are
+//! binding (see [accept]). Changing an agreement's content voids any previous
+//! acceptances.
+//!
 //! See the [agreement model.][1]
 //!
 //! [1]: ../../models/agreement/index.html
+//! [accept]: fn.accept.html
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, DenyReason, Permission},
     error::{Error, Result},
     models::{
         Op,
         Modifications,
         lib::{
-            agent::AgentID,
+            agent::{Agent, AgentID},
             basis_model::Model,
         },
-        agreement::{Agreement, AgreementID},
+        agreement::{Agreement, AgreementID, Signature},
         company::{Company, Permission as CompanyPermission},
         member::Member,
         user::User,
@@ -34,7 +39,7 @@ use vf_rs::vf;
 /// data in any way.
 pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company, id: AgreementID, participants: Vec<AgentID>, name: T, note: T, created: Option<DateTime<Utc>>, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateAgreements)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::AgreementCreate)?;
+    member.access_check(caller.id(), company, CompanyPermission::AgreementCreate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -54,16 +59,28 @@ pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("agreement::create", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateAgreements.into(), CompanyPermission::AgreementCreate.into()], now)
 }
 
 /// Update an agreement, including the participant list.
+///
+/// Changing the participants, name, note, or created date of the agreement
+/// bumps its revision and clears all signatures, meaning each participant must
+/// [accept] the agreement again before it is binding.
+///
+/// [accept]: fn.accept.html
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Agreement, participants: Option<Vec<AgentID>>, name: Option<String>, note: Option<String>, created: Option<Option<DateTime<Utc>>>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateAgreements)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::AgreementUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::AgreementUpdate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
+    if !subject.has_participant(&company.agent_id()) {
+        // can't update an agreement you are not party to
+        Err(AccessDenied::for_actor(DenyReason::AgreementParticipantMismatch, company.id().clone()))?;
+    }
+    let previous = subject.clone();
     if let Some(participants) = participants {
         subject.set_participants(participants);
     }
@@ -76,11 +93,52 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Ag
     if let Some(note) = note {
         subject.inner_mut().set_note(Some(note));
     }
+    if subject.participants() != previous.participants() || subject.inner() != previous.inner() {
+        subject.reset_signatures();
+    }
     if let Some(active) = active {
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(previous, subject)
+        .audit("agreement::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateAgreements.into(), CompanyPermission::AgreementUpdate.into()], now)
+}
+
+/// Accept (sign) the current revision of an agreement on behalf of a
+/// participating company.
+///
+/// `revision` is the revision the signer reviewed. If the agreement has been
+/// edited since (its revision no longer matches) we return
+/// `Error::AgreementRevisionMismatch` rather than sign changes nobody looked
+/// at.
+///
+/// Once every participant has accepted the current revision, the agreement is
+/// binding and events can be recorded against it.
+pub fn accept(caller: &User, member: &Member, company: &Company, mut subject: Agreement, revision: u64, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateAgreements)?;
+    member.access_check(caller.id(), company, CompanyPermission::AgreementFinalize, now)?;
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("agreement".into()))?;
+    }
+    if !subject.is_active() {
+        Err(Error::ObjectIsInactive("agreement".into()))?;
+    }
+    if !subject.has_participant(&company.agent_id()) {
+        // can't accept an agreement you are not party to
+        Err(AccessDenied::for_actor(DenyReason::AgreementParticipantMismatch, company.id().clone()))?;
+    }
+    if subject.revision() != &revision {
+        Err(Error::AgreementRevisionMismatch)?;
+    }
+    let signature = Signature::new(company.agent_id(), caller.id().clone(), member.id().clone(), revision, *now);
+    let original = subject.clone();
+    subject.sign(signature);
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("agreement::accept", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateAgreements.into(), CompanyPermission::AgreementFinalize.into()], now)
 }
 
 #[cfg(test)]
@@ -88,7 +146,6 @@ mod tests {
     use super::*;
     use crate::{
         models::{
-            lib::agent::Agent,
             company::CompanyID,
         },
         util::{self, test::{self, *}},
@@ -108,7 +165,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let agreement = mods[0].clone().expect_op::<Agreement>(Op::Create).unwrap();
         assert_eq!(agreement.id(), &id);
@@ -147,10 +204,103 @@ mod tests {
         assert_eq!(agreement2.inner().name(), &Some("order 1111222".into()));
         assert_eq!(agreement2.inner().note(), &Some("jerry's long-winded order".into()));
         assert_eq!(agreement2.participants(), &vec![company_from.agent_id()]);
+        assert_eq!(agreement2.signatures(), &vec![]);
+        assert_eq!(agreement2.revision(), &(agreement1.revision() + 1));
         assert_eq!(agreement2.active(), agreement1.active());
         assert_eq!(agreement2.created(), agreement1.created());
         assert_eq!(agreement2.updated(), &now2);
         assert_eq!(agreement2.deleted(), &None);
+
+        // changing only the active status doesn't void signatures
+        let agreement3 = make_agreement(agreement1.id(), &participants, "order 1234141", "widgets", &now);
+        let mods = update(state.user(), state.member(), state.company(), agreement3.clone(), None, None, None, None, Some(false), &now2).unwrap().into_vec();
+        let agreement4 = mods[0].clone().expect_op::<Agreement>(Op::Update).unwrap();
+        assert_eq!(agreement4.signatures(), agreement3.signatures());
+        assert_eq!(agreement4.revision(), agreement3.revision());
+        assert_eq!(agreement4.active(), &false);
+
+        // but changing the content does
+        let mods = update(state.user(), state.member(), state.company(), agreement3.clone(), None, None, Some("add another widget".into()), None, None, &now2).unwrap().into_vec();
+        let agreement5 = mods[0].clone().expect_op::<Agreement>(Op::Update).unwrap();
+        assert_eq!(agreement5.signatures(), &vec![]);
+        assert_eq!(agreement5.revision(), &1);
+        assert!(!agreement5.is_binding());
+
+        // can't update an agreement we aren't party to
+        let mut agreement6 = agreement1.clone();
+        agreement6.set_participants(vec![company_from.agent_id()]);
+        let res = update(state.user(), state.member(), state.company(), agreement6, None, Some("order 1111222".into()), None, None, None, &now2);
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
+    }
+
+    #[test]
+    fn can_accept() {
+        let now = util::time::now();
+        let id = AgreementID::create();
+        let mut state = TestState::standard(vec![CompanyPermission::AgreementCreate, CompanyPermission::AgreementFinalize], &now);
+        let company_from = make_company(&CompanyID::create(), "jerry's widgets", &now);
+        let participants = vec![state.company().agent_id(), company_from.agent_id()];
+
+        let mods = create(state.user(), state.member(), state.company(), id.clone(), participants.clone(), "order 1234141", "widgets plz", Some(now), true, &now).unwrap().into_vec();
+        let agreement1 = mods[0].clone().expect_op::<Agreement>(Op::Create).unwrap();
+        assert!(!agreement1.is_binding());
+        state.model = Some(agreement1.clone());
+        let now2 = util::time::now();
+
+        let testfn = |state: &TestState<Agreement, Agreement>| {
+            accept(state.user(), state.member(), state.company(), state.model().clone(), 0, &now2)
+        };
+        test::standard_transaction_tests(&state, &testfn);
+        test::double_deleted_tester(&state, "agreement", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let agreement2 = mods[0].clone().expect_op::<Agreement>(Op::Update).unwrap();
+        assert_eq!(agreement2.signatures().len(), 1);
+        let signature = &agreement2.signatures()[0];
+        assert_eq!(signature.participant(), &state.company().agent_id());
+        assert_eq!(signature.user_id(), state.user().id());
+        assert_eq!(signature.member_id(), state.member().id());
+        assert_eq!(signature.revision(), &0);
+        assert_eq!(signature.signed(), &now2);
+        assert!(agreement2.has_signed(&state.company().agent_id()));
+        assert!(!agreement2.has_signed(&company_from.agent_id()));
+        assert!(!agreement2.is_binding());
+        assert_eq!(agreement2.updated(), &now2);
+
+        // signing twice just replaces our signature
+        let mods = accept(state.user(), state.member(), state.company(), agreement2.clone(), 0, &now2).unwrap().into_vec();
+        let agreement3 = mods[0].clone().expect_op::<Agreement>(Op::Update).unwrap();
+        assert_eq!(agreement3.signatures().len(), 1);
+
+        // once the other participant signs, the agreement is binding
+        let mut state2 = state.clone();
+        state2.company = Some(company_from.clone());
+        state2.member_mut().inner_mut().set_object(company_from.agent_id());
+        let mods = accept(state2.user(), state2.member(), state2.company(), agreement3.clone(), 0, &now2).unwrap().into_vec();
+        let agreement4 = mods[0].clone().expect_op::<Agreement>(Op::Update).unwrap();
+        assert_eq!(agreement4.signatures().len(), 2);
+        assert!(agreement4.is_binding());
+
+        // can't accept an agreement we aren't party to
+        let mut state3 = state.clone();
+        state3.model_mut().set_participants(vec![company_from.agent_id()]);
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
+
+        let mut state4 = state.clone();
+        state4.model_mut().set_active(false);
+        let res = testfn(&state4);
+        assert_eq!(res, Err(Error::ObjectIsInactive("agreement".into())));
+
+        // can't sign an edit we haven't reviewed
+        let mut state5 = state.clone();
+        state5.model_mut().set_revision(1);
+        let res = testfn(&state5);
+        assert_eq!(res, Err(Error::AgreementRevisionMismatch));
+        let mods = accept(state5.user(), state5.member(), state5.company(), state5.model().clone(), 1, &now2).unwrap().into_vec();
+        let agreement5 = mods[0].clone().expect_op::<Agreement>(Op::Update).unwrap();
+        assert_eq!(agreement5.signatures()[0].revision(), &1);
     }
 }
 
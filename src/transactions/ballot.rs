@@ -0,0 +1,189 @@
+//! Ballots are how voters weigh in on a [Proposal]. Users vote on systemic
+//! proposals, and members vote on their company's proposals.
+//!
+//! See the [ballot model.][1]
+//!
+//! [Proposal]: ../../models/proposal/struct.Proposal.html
+//! [1]: ../../models/ballot/index.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    access::{AccessDenied, DenyReason, Permission, RequiredPermission},
+    error::{Error, Result},
+    models::{
+        Op,
+        Modifications,
+        ballot::{Ballot, BallotChoice, BallotID},
+        company::CompanyID,
+        lib::{
+            agent::{Agent, AgentID},
+            basis_model::Model,
+        },
+        member::Member,
+        proposal::{Proposal, ProposalScope},
+        user::User,
+    },
+};
+
+/// Figure out who is voting on a proposal. Systemic proposals are voted on by
+/// the calling user, and company proposals are voted on by the caller's
+/// (active) member record in that company.
+fn voter(caller: &User, member: Option<&Member>, proposal: &Proposal) -> Result<(AgentID, Option<CompanyID>)> {
+    caller.access_check(Permission::ProposalVote)?;
+    match proposal.scope() {
+        ProposalScope::Systemic => Ok((caller.agent_id(), None)),
+        ProposalScope::Company(company_id) => {
+            let member = member.ok_or(AccessDenied::new(DenyReason::WrongCompany, Some(Permission::ProposalVote.into()), Some(caller.agent_id())))?;
+            if member.member_id() != &caller.agent_id() {
+                Err(AccessDenied::new(DenyReason::WrongUser, Some(Permission::ProposalVote.into()), Some(member.agent_id())))?;
+            }
+            if member.group_id() != &company_id.clone().into() {
+                Err(AccessDenied::new(DenyReason::WrongCompany, Some(Permission::ProposalVote.into()), Some(member.agent_id())))?;
+            }
+            if !member.is_active() {
+                Err(AccessDenied::new(DenyReason::Inactive, Some(Permission::ProposalVote.into()), Some(member.agent_id())))?;
+            }
+            Ok((member.agent_id(), Some(company_id.clone())))
+        }
+    }
+}
+
+/// Cast a ballot on an open proposal.
+pub fn cast(caller: &User, member: Option<&Member>, proposal: &Proposal, id: BallotID, choice: BallotChoice, now: &DateTime<Utc>) -> Result<Modifications> {
+    let (voter, company_id) = voter(caller, member, proposal)?;
+    if !proposal.is_open_at(now) {
+        Err(Error::ProposalClosed)?;
+    }
+    let model = Ballot::builder()
+        .id(id)
+        .proposal_id(proposal.id().clone())
+        .voter(voter)
+        .choice(choice)
+        .active(true)
+        .created(*now)
+        .updated(*now)
+        .build()
+        .map_err(Error::BuilderFailed)?;
+    let permissions: Vec<RequiredPermission> = vec![Permission::ProposalVote.into()];
+    Modifications::new_single(Op::Create, model)
+        .audit("ballot::cast", caller, member.filter(|_| company_id.is_some()), company_id.as_ref(), permissions, now)
+}
+
+/// Change a ballot's choice while its proposal is still open.
+pub fn update(caller: &User, member: Option<&Member>, proposal: &Proposal, mut subject: Ballot, choice: BallotChoice, now: &DateTime<Utc>) -> Result<Modifications> {
+    let (voter, company_id) = voter(caller, member, proposal)?;
+    if subject.voter() != &voter {
+        Err(AccessDenied::new(DenyReason::NotOwner, Some(Permission::ProposalVote.into()), Some(voter)))?;
+    }
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("ballot".into()))?;
+    }
+    if subject.proposal_id() != proposal.id() || !proposal.is_open_at(now) {
+        Err(Error::ProposalClosed)?;
+    }
+    let original = subject.clone();
+    subject.set_choice(choice);
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("ballot::update", caller, member.filter(|_| company_id.is_some()), company_id.as_ref(), vec![Permission::ProposalVote.into()], now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{
+            company::Permission as CompanyPermission,
+            proposal::{ProposalID, ProposalStatus, Threshold, VoteRules},
+        },
+        transactions,
+        util::{self, test::*},
+    };
+    use rust_decimal_macros::*;
+
+    fn make_proposal(state: &TestState<Proposal, Ballot>, company: bool, now: &DateTime<Utc>) -> Proposal {
+        let (member, company) = if company {
+            (Some(state.member()), Some(state.company()))
+        } else {
+            (None, None)
+        };
+        let mods = transactions::proposal::create(state.user(), member, company, ProposalID::create(), "rename the company", None, None, "company::update", "{}", VoteRules::new(dec!(0.5), Threshold::Majority), *now + chrono::Duration::days(7), now).unwrap().into_vec();
+        mods[0].clone().expect_op::<Proposal>(Op::Create).unwrap()
+    }
+
+    #[test]
+    fn can_cast() {
+        let now = util::time::now();
+        let state = TestState::standard(vec![CompanyPermission::ProposalCreate], &now);
+        let proposal = make_proposal(&state, true, &now);
+        let id = BallotID::create();
+
+        let mods = cast(state.user(), Some(state.member()), &proposal, id.clone(), BallotChoice::Yes, &now).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let ballot = mods[0].clone().expect_op::<Ballot>(Op::Create).unwrap();
+        assert_eq!(ballot.id(), &id);
+        assert_eq!(ballot.proposal_id(), proposal.id());
+        assert_eq!(ballot.voter(), &state.member().agent_id());
+        assert_eq!(ballot.choice(), &BallotChoice::Yes);
+
+        // members vote on company proposals, even without any company
+        // permissions
+        let mut state2 = state.clone();
+        state2.member_mut().set_permissions(vec![]);
+        assert!(cast(state2.user(), Some(state2.member()), &proposal, id.clone(), BallotChoice::Yes, &now).is_ok());
+
+        let res = cast(state.user(), None, &proposal, id.clone(), BallotChoice::Yes, &now);
+        expect_denied(res, DenyReason::WrongCompany);
+        let mut state3 = state.clone();
+        state3.member_mut().inner_mut().set_object(CompanyID::create().into());
+        let res = cast(state3.user(), Some(state3.member()), &proposal, id.clone(), BallotChoice::Yes, &now);
+        expect_denied(res, DenyReason::WrongCompany);
+        let mut state4 = state.clone();
+        state4.member_mut().set_active(false);
+        let res = cast(state4.user(), Some(state4.member()), &proposal, id.clone(), BallotChoice::Yes, &now);
+        expect_denied(res, DenyReason::Inactive);
+        let mut state5 = state.clone();
+        state5.user_mut().set_roles(vec![]);
+        let res = cast(state5.user(), Some(state5.member()), &proposal, id.clone(), BallotChoice::Yes, &now);
+        expect_denied(res, DenyReason::MissingPermission);
+
+        let res = cast(state.user(), Some(state.member()), &proposal, id.clone(), BallotChoice::Yes, proposal.closes());
+        assert_eq!(res, Err(Error::ProposalClosed));
+        let mut proposal2 = proposal.clone();
+        proposal2.set_status(ProposalStatus::Failed);
+        let res = cast(state.user(), Some(state.member()), &proposal2, id.clone(), BallotChoice::Yes, &now);
+        assert_eq!(res, Err(Error::ProposalClosed));
+
+        // users vote on systemic proposals
+        let proposal3 = make_proposal(&state, false, &now);
+        let mods = cast(state.user(), None, &proposal3, id.clone(), BallotChoice::No, &now).unwrap().into_vec();
+        let ballot = mods[0].clone().expect_op::<Ballot>(Op::Create).unwrap();
+        assert_eq!(ballot.voter(), &state.user().agent_id());
+    }
+
+    #[test]
+    fn can_update() {
+        let now = util::time::now();
+        let state = TestState::standard(vec![CompanyPermission::ProposalCreate], &now);
+        let proposal = make_proposal(&state, true, &now);
+        let mods = cast(state.user(), Some(state.member()), &proposal, BallotID::create(), BallotChoice::Yes, &now).unwrap().into_vec();
+        let ballot = mods[0].clone().expect_op::<Ballot>(Op::Create).unwrap();
+        let now2 = util::time::now();
+
+        let mods = update(state.user(), Some(state.member()), &proposal, ballot.clone(), BallotChoice::Abstain, &now2).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let ballot2 = mods[0].clone().expect_op::<Ballot>(Op::Update).unwrap();
+        assert_eq!(ballot2.choice(), &BallotChoice::Abstain);
+        assert_eq!(ballot2.updated(), &now2);
+        let paths = mods[0].diff().unwrap().iter().map(|diff| diff.path()).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["choice", "updated", "version"]);
+
+        let mut ballot3 = ballot.clone();
+        ballot3.set_voter(crate::models::member::MemberID::create().into());
+        let res = update(state.user(), Some(state.member()), &proposal, ballot3, BallotChoice::No, &now2);
+        expect_denied(res, DenyReason::NotOwner);
+
+        let res = update(state.user(), Some(state.member()), &proposal, ballot.clone(), BallotChoice::No, proposal.closes());
+        assert_eq!(res, Err(Error::ProposalClosed));
+    }
+}
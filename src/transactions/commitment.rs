@@ -11,7 +11,7 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, DenyReason, Permission},
     costs::Costs,
     error::{Error, Result},
     models::{
@@ -39,18 +39,18 @@ use vf_rs::{vf, geo::SpatialThing};
 /// Create a new commitment
 pub fn create(caller: &User, member: &Member, company: &Company, agreement: &Agreement, id: CommitmentID, move_costs: Costs, action: OrderAction, agreed_in: Option<Url>, at_location: Option<SpatialThing>, created: Option<DateTime<Utc>>, due: Option<DateTime<Utc>>, effort_quantity: Option<Measure>, finished: Option<bool>, has_beginning: Option<DateTime<Utc>>, has_end: Option<DateTime<Utc>>, has_point_in_time: Option<DateTime<Utc>>, in_scope_of: Vec<AgentID>, input_of: Option<ProcessID>, name: Option<String>, note: Option<String>, output_of: Option<ProcessID>, provider: AgentID, receiver: AgentID, resource_conforms_to: Option<ResourceSpecID>, resource_inventoried_as: Option<ResourceID>, resource_quantity: Option<Measure>, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateCommitments)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::CommitmentCreate)?;
+    member.access_check(caller.id(), company, CompanyPermission::CommitmentCreate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
     let company_agent_id: AgentID = company.agent_id();
     if company_agent_id != provider && company_agent_id != receiver {
         // can't create a commitment for a company you aren't a member of DUUUHHH
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
     }
     if !agreement.has_participant(&provider) || !agreement.has_participant(&receiver) {
         // can't create a commitment for an agreement you are not party to
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::AgreementParticipantMismatch, company.id().clone()))?;
     }
     let event_action = match action {
         OrderAction::DeliverService => vf::Action::DeliverService,
@@ -91,13 +91,14 @@ pub fn create(caller: &User, member: &Member, company: &Company, agreement: &Agr
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("commitment::create", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateCommitments.into(), CompanyPermission::CommitmentCreate.into()], now)
 }
 
 /// Update a commitment
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Commitment, move_costs: Option<Costs>, action: Option<OrderAction>, agreed_in: Option<Option<Url>>, at_location: Option<Option<SpatialThing>>, created: Option<Option<DateTime<Utc>>>, due: Option<Option<DateTime<Utc>>>, effort_quantity: Option<Option<Measure>>, finished: Option<Option<bool>>, has_beginning: Option<Option<DateTime<Utc>>>, has_end: Option<Option<DateTime<Utc>>>, has_point_in_time: Option<Option<DateTime<Utc>>>, in_scope_of: Option<Vec<AgentID>>, input_of: Option<Option<ProcessID>>, name: Option<Option<String>>, note: Option<Option<String>>, output_of: Option<Option<ProcessID>>, resource_conforms_to: Option<Option<ResourceSpecID>>, resource_inventoried_as: Option<Option<ResourceID>>, resource_quantity: Option<Option<Measure>>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateCommitments)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::CommitmentUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::CommitmentUpdate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -109,6 +110,7 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Co
         }
     });
 
+    let original = subject.clone();
     if let Some(move_costs) = move_costs {
         subject.set_move_costs(move_costs);
     }
@@ -170,13 +172,14 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Co
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("commitment::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateCommitments.into(), CompanyPermission::CommitmentUpdate.into()], now)
 }
 
 /// Delete a commitment
 pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Commitment, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateCommitments)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::CommitmentDelete)?;
+    member.access_check(caller.id(), company, CompanyPermission::CommitmentDelete, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -184,7 +187,8 @@ pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Co
         Err(Error::ObjectIsDeleted("commitment".into()))?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("commitment::delete", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateCommitments.into(), CompanyPermission::CommitmentDelete.into()], now)
 }
 
 #[cfg(test)]
@@ -220,7 +224,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let commitment = mods[0].clone().expect_op::<Commitment>(Op::Create).unwrap();
         assert_eq!(commitment.id(), &id);
@@ -255,12 +259,12 @@ mod tests {
         company3.set_id(CompanyID::new("bill's zingers, get your premium zings here. got a friend who constantly pranks you? turn the tables and zing that doofus in front of everyone!!"));
         company4.set_id(CompanyID::new("jill's zingers, get the best zings here. turn that lame party into a laugh fest with some classic zingers. don't buy at bill's, he sucks."));
         let res = testfn_inner(&state, &agreement, &company3, &company4);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongCompany);
 
         let mut agreement2 = agreement.clone();
         agreement2.set_participants(vec![]);
         let res = testfn_inner(&state, &agreement2, &company_from, &company_to);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
     }
 
     #[test]
@@ -341,7 +345,7 @@ mod tests {
         test::double_deleted_tester(&state, "commitment", &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let commitment2 = mods[0].clone().expect_op::<Commitment>(Op::Delete).unwrap();
         assert_eq!(commitment2.id(), commitment1.id());
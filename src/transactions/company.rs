@@ -12,13 +12,16 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, DenyReason, Permission, RequiredPermission},
     error::{Error, Result},
     models::{
         Op,
         Modifications,
-        company::{Company, CompanyID, Permission as CompanyPermission},
-        lib::basis_model::Model,
+        company::{Bylaw, BylawRequirement, Company, CompanyID, Permission as CompanyPermission, Role},
+        lib::{
+            agent::Agent,
+            basis_model::Model,
+        },
         member::{Member, MemberID, MemberClass},
         user::User,
     },
@@ -86,16 +89,25 @@ pub fn create<T: Into<String>>(caller: &User, id: CompanyID, company_name: T, co
     let mut mods = Modifications::new();
     mods.push(Op::Create, company);
     mods.push(Op::Create, founder);
-    Ok(mods)
+    mods.audit("company::create", caller, None, Some(&id), vec![Permission::CompanyCreate.into()], now)
 }
 
 /// Update a private company
 pub fn update(caller: &User, member: Option<&Member>, mut subject: Company, name: Option<String>, email: Option<String>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
-    caller.access_check(Permission::CompanyAdminUpdate)
-        .or_else(|_| member.ok_or(Error::InsufficientPrivileges)?.access_check(caller.id(), subject.id(), CompanyPermission::CompanyUpdate))?;
+    // company admins can act without a member record, so note which way we
+    // got in for the audit record
+    let (member, permission): (Option<&Member>, RequiredPermission) = match caller.access_check(Permission::CompanyAdminUpdate) {
+        Ok(_) => (None, Permission::CompanyAdminUpdate.into()),
+        Err(e) => {
+            let member = member.ok_or(e)?;
+            member.access_check(caller.id(), &subject, CompanyPermission::CompanyUpdate, now)?;
+            (Some(member), CompanyPermission::CompanyUpdate.into())
+        }
+    };
     if subject.is_deleted() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
+    let original = subject.clone();
     if let Some(name) = name {
         subject.inner_mut().set_name(name);
     }
@@ -106,33 +118,162 @@ pub fn update(caller: &User, member: Option<&Member>, mut subject: Company, name
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    let company_id = subject.id().clone();
+    Modifications::new_update(original, subject)
+        .audit("company::update", caller, member, Some(&company_id), vec![permission], now)
 }
 
 /// Delete a private company
 pub fn delete(caller: &User, member: Option<&Member>, mut subject: Company, now: &DateTime<Utc>) -> Result<Modifications> {
-    caller.access_check(Permission::CompanyAdminDelete)
-        .or_else(|_| member.ok_or(Error::InsufficientPrivileges)?.access_check(caller.id(), subject.id(), CompanyPermission::CompanyDelete))?;
+    // company admins can act without a member record, so note which way we
+    // got in for the audit record
+    let (member, permission): (Option<&Member>, RequiredPermission) = match caller.access_check(Permission::CompanyAdminDelete) {
+        Ok(_) => (None, Permission::CompanyAdminDelete.into()),
+        Err(e) => {
+            let member = member.ok_or(e)?;
+            member.access_check(caller.id(), &subject, CompanyPermission::CompanyDelete, now)?;
+            (Some(member), CompanyPermission::CompanyDelete.into())
+        }
+    };
     if subject.is_deleted() {
         Err(Error::ObjectIsDeleted("company".into()))?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    let company_id = subject.id().clone();
+    Modifications::new_single(Op::Delete, subject)
+        .audit("company::delete", caller, member, Some(&company_id), vec![permission], now)
+}
+
+/// Make sure the calling member holds every permission they're putting into a
+/// role, so nobody can write themselves (or anyone else) a role that grants
+/// more than they already have.
+fn check_role_permissions(member: &Member, company: &Company, permissions: &[CompanyPermission]) -> Result<()> {
+    if let Some(perm) = permissions.iter().find(|perm| !member.can(company, perm)) {
+        Err(AccessDenied::new(DenyReason::MissingPermission, Some(perm.clone().into()), Some(member.agent_id())))?;
+    }
+    Ok(())
+}
+
+/// Create a new named role for a company. Members can then be assigned the
+/// role (see [member::set_roles][1]).
+///
+/// The caller must hold every permission the role grants. Role names can't be
+/// reused, even after the role is deleted.
+///
+/// [1]: ../member/fn.set_roles.html
+pub fn create_role<T: Into<String>>(caller: &User, member: &Member, mut subject: Company, name: T, permissions: Vec<CompanyPermission>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateRoles)?;
+    member.access_check(caller.id(), &subject, CompanyPermission::RoleCreate, now)?;
+    if !subject.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    check_role_permissions(member, &subject, &permissions)?;
+    let name = name.into();
+    if subject.role(&name).is_some() || subject.retired_roles().contains(&name) {
+        Err(Error::CompanyRoleExists(name.clone()))?;
+    }
+    let original = subject.clone();
+    subject.roles_mut().push(Role::new(name, permissions));
+    subject.set_updated(*now);
+    let company_id = subject.id().clone();
+    Modifications::new_update(original, subject)
+        .audit("company::create_role", caller, Some(member), Some(&company_id), vec![Permission::CompanyUpdateRoles.into(), CompanyPermission::RoleCreate.into()], now)
+}
+
+/// Set the permissions for one of a company's roles. This applies to every
+/// member that holds the role.
+///
+/// The caller must hold every permission the role grants.
+pub fn update_role(caller: &User, member: &Member, mut subject: Company, name: &str, permissions: Vec<CompanyPermission>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateRoles)?;
+    member.access_check(caller.id(), &subject, CompanyPermission::RoleUpdate, now)?;
+    if !subject.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    check_role_permissions(member, &subject, &permissions)?;
+    let original = subject.clone();
+    let role = subject.roles_mut().iter_mut()
+        .find(|role| role.name() == name)
+        .ok_or(Error::CompanyRoleNotFound(name.into()))?;
+    role.set_permissions(permissions);
+    subject.set_updated(*now);
+    let company_id = subject.id().clone();
+    Modifications::new_update(original, subject)
+        .audit("company::update_role", caller, Some(member), Some(&company_id), vec![Permission::CompanyUpdateRoles.into(), CompanyPermission::RoleUpdate.into()], now)
+}
+
+/// Delete one of a company's roles. Members holding the role immediately lose
+/// the permissions it granted.
+///
+/// The role's name is retired, so a new role can't be created with the same
+/// name and hand its permissions to the members still listing the old one.
+pub fn delete_role(caller: &User, member: &Member, mut subject: Company, name: &str, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateRoles)?;
+    member.access_check(caller.id(), &subject, CompanyPermission::RoleDelete, now)?;
+    if !subject.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if subject.role(name).is_none() {
+        Err(Error::CompanyRoleNotFound(name.into()))?;
+    }
+    let original = subject.clone();
+    subject.roles_mut().retain(|role| role.name() != name);
+    subject.retired_roles_mut().push(name.into());
+    subject.set_updated(*now);
+    let company_id = subject.id().clone();
+    Modifications::new_update(original, subject)
+        .audit("company::delete_role", caller, Some(member), Some(&company_id), vec![Permission::CompanyUpdateRoles.into(), CompanyPermission::RoleDelete.into()], now)
+}
+
+/// Set the company's bylaws, which reserve some of its permissions for votes
+/// (see [Bylaw][bylaw]).
+///
+/// Once a company has bylaws, they can only be changed by a vote.
+///
+/// [bylaw]: ../../models/company/struct.Bylaw.html
+pub fn set_bylaws(caller: &User, member: &Member, mut subject: Company, bylaws: Vec<Bylaw>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateBylaws)?;
+    member.access_check(caller.id(), &subject, CompanyPermission::BylawsUpdate, now)?;
+    if !subject.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if !subject.bylaws().is_empty() && !member.vote() {
+        Err(AccessDenied::new(DenyReason::RequiresVote, Some(CompanyPermission::BylawsUpdate.into()), Some(member.agent_id())))?;
+    }
+    let valid = bylaws.iter().all(|bylaw| {
+        match bylaw.requirement() {
+            BylawRequirement::Vote(rules) => rules.is_valid(),
+            BylawRequirement::Approvals(required) => required > &0,
+        }
+    });
+    if !valid {
+        Err(Error::BylawInvalid)?;
+    }
+    let original = subject.clone();
+    subject.set_bylaws(bylaws);
+    subject.set_updated(*now);
+    let company_id = subject.id().clone();
+    Modifications::new_update(original, subject)
+        .audit("company::set_bylaws", caller, Some(member), Some(&company_id), vec![Permission::CompanyUpdateBylaws.into(), CompanyPermission::BylawsUpdate.into()], now)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        access::DenyReason,
         models::{
             Op,
             lib::agent::Agent,
             member::{MemberClass, MemberWorker},
             occupation::OccupationID,
+            proposal::{Proposal, ProposalID, ProposalScope, ProposalStatus, Threshold, VoteRules},
             user::UserID,
         },
+        system::vote::Vote,
         util::{self, test::{self, *}},
     };
+    use rust_decimal_macros::*;
 
     #[test]
     fn can_create() {
@@ -151,7 +292,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 2);
+        assert_eq!(mods.len(), 3);
 
         let company = mods[0].clone().expect_op::<Company>(Op::Create).unwrap();
         let member = mods[1].clone().expect_op::<Member>(Op::Create).unwrap();
@@ -195,7 +336,7 @@ mod tests {
         test::permissions_checks(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let company2 = mods[0].clone().expect_op::<Company>(Op::Update).unwrap();
         assert_eq!(company2.id(), state.company().id());
@@ -206,12 +347,12 @@ mod tests {
         assert_eq!(company2.updated(), &now2);
 
         let res = testfn_inner(&state, None);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
 
         let mut state2 = state.clone();
         state2.user_mut().set_id(UserID::create());
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongUser);
     }
 
     #[test]
@@ -241,19 +382,19 @@ mod tests {
         test::permissions_checks(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let company2 = mods[0].clone().expect_op::<Company>(Op::Delete).unwrap();
         assert_eq!(company2.created(), &now);
         assert_eq!(company2.updated(), &now);
         assert_eq!(company2.deleted(), &Some(now2));
 
         let res = testfn_inner(&state, None);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
 
         let mut state2 = state.clone();
         state2.user_mut().set_id(UserID::create());
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongUser);
 
         // set the model inot the state, which makes testfn use the model
         // instead of the company for the `subject` param, making our test
@@ -262,5 +403,178 @@ mod tests {
         state2.model = Some(state.company().clone());
         test::double_deleted_tester(&state2, "company", &testfn);
     }
-}
 
+    #[test]
+    fn can_set_bylaws() {
+        let now = util::time::now();
+        let state = TestState::standard(vec![CompanyPermission::BylawsUpdate, CompanyPermission::CompanyDelete], &now);
+        let bylaws = vec![
+            Bylaw::new(CompanyPermission::CompanyDelete, BylawRequirement::Vote(VoteRules::new(dec!(0.5), Threshold::Supermajority(dec!(0.75))))),
+            Bylaw::new(CompanyPermission::MemberSetCompensation, BylawRequirement::Approvals(2)),
+        ];
+
+        let now2 = util::time::now();
+        let testfn_inner = |state: &TestState<Company, Company>, member: &Member, bylaws: Vec<Bylaw>| {
+            set_bylaws(state.user(), member, state.company().clone(), bylaws, &now2)
+        };
+        let testfn = |state: &TestState<Company, Company>| {
+            testfn_inner(state, state.member(), bylaws.clone())
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let company = mods[0].clone().expect_op::<Company>(Op::Update).unwrap();
+        assert_eq!(company.bylaws(), &bylaws);
+        assert_eq!(company.updated(), &now2);
+        assert!(company.bylaw(&CompanyPermission::CompanyDelete).is_some());
+        assert!(company.bylaw(&CompanyPermission::CompanyUpdate).is_none());
+
+        // members can no longer delete the company (or change the bylaws) on
+        // their own, but a vote can
+        let res = delete(state.user(), Some(state.member()), company.clone(), &now2);
+        expect_denied(res, DenyReason::RequiresVote);
+        let mut state2 = state.clone();
+        state2.company = Some(company.clone());
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::RequiresVote);
+        // a company vote member that isn't backed by a proposal is still bound
+        // by the bylaws
+        let vote = Vote::company(UserID::create(), MemberID::create(), company.id(), &now2).unwrap();
+        let res = delete(vote.user(), vote.member().as_ref(), company.clone(), &now2);
+        expect_denied(res, DenyReason::RequiresVote);
+        let proposal = Proposal::builder()
+            .id(ProposalID::create())
+            .scope(ProposalScope::Company(company.id().clone()))
+            .title("close up shop")
+            .transaction("company::delete")
+            .payload("{}")
+            .rules(VoteRules::new(dec!(0.5), Threshold::Majority))
+            .closes(now2)
+            .status(ProposalStatus::Passed)
+            .active(true)
+            .created(now2)
+            .updated(now2)
+            .build().unwrap();
+        let vote = Vote::from_proposal(&proposal, UserID::create(), MemberID::create(), &now2).unwrap();
+        assert!(delete(vote.user(), vote.member().as_ref(), company.clone(), &now2).is_ok());
+        let mut state3 = state2.clone();
+        state3.user = Some(vote.user().clone());
+        let mods = testfn_inner(&state3, vote.member().as_ref().unwrap(), vec![]).unwrap().into_vec();
+        let company2 = mods[0].clone().expect_op::<Company>(Op::Update).unwrap();
+        assert_eq!(company2.bylaws(), &vec![]);
+
+        let res = testfn_inner(&state, state.member(), vec![Bylaw::new(CompanyPermission::CompanyDelete, BylawRequirement::Approvals(0))]);
+        assert_eq!(res, Err(Error::BylawInvalid));
+        let res = testfn_inner(&state, state.member(), vec![Bylaw::new(CompanyPermission::CompanyDelete, BylawRequirement::Vote(VoteRules::new(dec!(2), Threshold::Majority)))]);
+        assert_eq!(res, Err(Error::BylawInvalid));
+    }
+
+    #[test]
+    fn can_create_role() {
+        let now = util::time::now();
+        let state = TestState::standard(vec![CompanyPermission::RoleCreate, CompanyPermission::AgreementCreate, CompanyPermission::Transfer], &now);
+
+        let now2 = util::time::now();
+        let testfn = |state: &TestState<Company, Company>| {
+            create_role(state.user(), state.member(), state.company().clone(), "coordinator", vec![CompanyPermission::AgreementCreate, CompanyPermission::Transfer], &now2)
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let company = mods[0].clone().expect_op::<Company>(Op::Update).unwrap();
+        assert_eq!(company.id(), state.company().id());
+        assert_eq!(company.roles(), &vec![Role::new("coordinator", vec![CompanyPermission::AgreementCreate, CompanyPermission::Transfer])]);
+        assert_eq!(company.updated(), &now2);
+
+        let mut state2 = state.clone();
+        state2.company = Some(company);
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::CompanyRoleExists("coordinator".into())));
+
+        // deleted role names stay taken
+        let mut state3 = state.clone();
+        state3.company_mut().set_retired_roles(vec!["coordinator".into()]);
+        let res = testfn(&state3);
+        assert_eq!(res, Err(Error::CompanyRoleExists("coordinator".into())));
+
+        // can't create a role granting permissions we don't have
+        let res = create_role(state.user(), state.member(), state.company().clone(), "boss", vec![CompanyPermission::All], &now2);
+        expect_denied(res, DenyReason::MissingPermission);
+        let res = create_role(state.user(), state.member(), state.company().clone(), "boss", vec![CompanyPermission::Transfer, CompanyPermission::MemberSetPermissions], &now2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_update_role() {
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![CompanyPermission::RoleUpdate, CompanyPermission::AgreementCreate, CompanyPermission::AgreementUpdate], &now);
+        state.company_mut().set_roles(vec![Role::new("coordinator", vec![CompanyPermission::AgreementCreate])]);
+
+        let now2 = util::time::now();
+        let testfn_inner = |state: &TestState<Company, Company>, name: &str| {
+            update_role(state.user(), state.member(), state.company().clone(), name, vec![CompanyPermission::AgreementCreate, CompanyPermission::AgreementUpdate], &now2)
+        };
+        let testfn = |state: &TestState<Company, Company>| {
+            testfn_inner(state, "coordinator")
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let company = mods[0].clone().expect_op::<Company>(Op::Update).unwrap();
+        assert_eq!(company.role("coordinator").unwrap().permissions(), &vec![CompanyPermission::AgreementCreate, CompanyPermission::AgreementUpdate]);
+        assert_eq!(company.updated(), &now2);
+
+        let res = testfn_inner(&state, "janitor");
+        assert_eq!(res, Err(Error::CompanyRoleNotFound("janitor".into())));
+
+        // a member holding the role can't update it unless the role grants
+        // RoleUpdate
+        let mut state2 = state.clone();
+        state2.member_mut().set_permissions(vec![]);
+        state2.member_mut().set_roles(vec!["coordinator".into()]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+        state2.company_mut().set_roles(vec![Role::new("coordinator", vec![CompanyPermission::RoleUpdate])]);
+        expect_denied(testfn(&state2), DenyReason::MissingPermission);
+        state2.company_mut().set_roles(vec![Role::new("coordinator", vec![CompanyPermission::RoleUpdate, CompanyPermission::AgreementCreate, CompanyPermission::AgreementUpdate])]);
+        assert!(testfn(&state2).is_ok());
+
+        // ...and can't use RoleUpdate to grant themselves everything
+        let res = update_role(state2.user(), state2.member(), state2.company().clone(), "coordinator", vec![CompanyPermission::All], &now2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_delete_role() {
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![CompanyPermission::RoleDelete], &now);
+        state.company_mut().set_roles(vec![
+            Role::new("coordinator", vec![CompanyPermission::AgreementCreate]),
+            Role::new("janitor", vec![CompanyPermission::Use]),
+        ]);
+
+        let now2 = util::time::now();
+        let testfn_inner = |state: &TestState<Company, Company>, name: &str| {
+            delete_role(state.user(), state.member(), state.company().clone(), name, &now2)
+        };
+        let testfn = |state: &TestState<Company, Company>| {
+            testfn_inner(state, "coordinator")
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let company = mods[0].clone().expect_op::<Company>(Op::Update).unwrap();
+        assert_eq!(company.roles(), &vec![Role::new("janitor", vec![CompanyPermission::Use])]);
+        assert_eq!(company.retired_roles(), &vec!["coordinator".to_string()]);
+        assert_eq!(company.updated(), &now2);
+
+        let mut state2 = state.clone();
+        state2.company = Some(company);
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::CompanyRoleNotFound("coordinator".into())));
+    }
+}
@@ -0,0 +1,224 @@
+//! Currencies are tracked so that the system can account for money spent on
+//! inputs purchased from markets outside of the network. Currency management is
+//! generally the domain of the bank.
+//!
+//! See the [currency model.][1]
+//!
+//! [1]: ../../models/currency/index.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    access::Permission,
+    costs::Costs,
+    error::{Error, Result},
+    models::{
+        Op,
+        Modifications,
+        currency::{Currency, CurrencyID},
+        lib::basis_model::Model,
+        user::User,
+    },
+};
+
+/// The largest number of decimal places we can track (this is the limit of our
+/// `Decimal` type).
+const MAX_DECIMAL_SCALE: u32 = 28;
+
+/// Make sure a currency's decimal scale is something we can represent.
+fn check_decimal_scale(decimal_scale: u32) -> Result<()> {
+    if decimal_scale > MAX_DECIMAL_SCALE {
+        Err(Error::CurrencyDecimalScaleInvalid(decimal_scale))?;
+    }
+    Ok(())
+}
+
+/// Create a new `Currency`.
+pub fn create<T: Into<String>>(caller: &User, id: CurrencyID, name: T, decimal_scale: u32, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CurrencyCreate)?;
+    check_decimal_scale(decimal_scale)?;
+    let model = Currency::builder()
+        .id(id)
+        .name(name)
+        .decimal_scale(decimal_scale)
+        .active(active)
+        .created(*now)
+        .updated(*now)
+        .build()
+        .map_err(Error::BuilderFailed)?;
+    Modifications::new_single(Op::Create, model)
+        .audit("currency::create", caller, None, None, vec![Permission::CurrencyCreate.into()], now)
+}
+
+/// Update an existing `Currency`.
+pub fn update(caller: &User, mut subject: Currency, name: Option<String>, decimal_scale: Option<u32>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CurrencyUpdate)?;
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("currency".into()))?;
+    }
+    let original = subject.clone();
+    if let Some(name) = name {
+        subject.set_name(name);
+    }
+    if let Some(decimal_scale) = decimal_scale {
+        check_decimal_scale(decimal_scale)?;
+        subject.set_decimal_scale(decimal_scale);
+    }
+    if let Some(active) = active {
+        subject.set_active(active);
+    }
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("currency::update", caller, None, None, vec![Permission::CurrencyUpdate.into()], now)
+}
+
+/// Delete a `Currency`.
+///
+/// Because we can't go looking for costs ourselves, the caller must pass in
+/// the costs that might reference this currency (for instance, the costs of
+/// every process and resource holding currency). If any of them have a
+/// non-zero value for this currency, deleting it would erase those costs and
+/// the deletion fails.
+pub fn delete(caller: &User, mut subject: Currency, costs: &[Costs], now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CurrencyDelete)?;
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("currency".into()))?;
+    }
+    // any entry for the currency is a reference to it, even a zero one
+    if costs.iter().any(|x| x.currency().contains_key(subject.id())) {
+        Err(Error::CannotEraseCosts)?;
+    }
+    subject.set_deleted(Some(*now));
+    Modifications::new_single(Op::Delete, subject)
+        .audit("currency::delete", caller, None, None, vec![Permission::CurrencyDelete.into()], now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::{DenyReason, Role},
+        util::{self, test::{self, *}},
+    };
+    use rust_decimal_macros::*;
+
+    #[test]
+    fn can_create() {
+        let id = CurrencyID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        state.user_mut().set_roles(vec![Role::Bank]);
+
+        let testfn_inner = |state: &TestState<Currency, Currency>, decimal_scale: u32| {
+            create(state.user(), id.clone(), "usd", decimal_scale, true, &now)
+        };
+        let testfn = |state: &TestState<Currency, Currency>| {
+            testfn_inner(state, 2)
+        };
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+
+        let currency = mods[0].clone().expect_op::<Currency>(Op::Create).unwrap();
+        assert_eq!(currency.id(), &id);
+        assert_eq!(currency.name(), "usd");
+        assert_eq!(currency.decimal_scale(), &2);
+        assert_eq!(currency.active(), &true);
+        assert_eq!(currency.created(), &now);
+        assert_eq!(currency.updated(), &now);
+        assert_eq!(currency.deleted(), &None);
+
+        assert!(testfn_inner(&state, 28).is_ok());
+        let res = testfn_inner(&state, 29);
+        assert_eq!(res, Err(Error::CurrencyDecimalScaleInvalid(29)));
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_update() {
+        let id = CurrencyID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        state.user_mut().set_roles(vec![Role::Bank]);
+        let mods = create(state.user(), id.clone(), "usd", 2, true, &now).unwrap().into_vec();
+        let currency = mods[0].clone().expect_op::<Currency>(Op::Create).unwrap();
+        state.model = Some(currency);
+
+        let now2 = util::time::now();
+        let testfn_inner = |state: &TestState<Currency, Currency>, decimal_scale: u32| {
+            update(state.user(), state.model().clone(), Some("USD".into()), Some(decimal_scale), None, &now2)
+        };
+        let testfn = |state: &TestState<Currency, Currency>| {
+            testfn_inner(state, 4)
+        };
+        test::double_deleted_tester(&state, "currency", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+
+        let currency2 = mods[0].clone().expect_op::<Currency>(Op::Update).unwrap();
+        assert_eq!(currency2.id(), &id);
+        assert_eq!(currency2.name(), "USD");
+        assert_eq!(currency2.decimal_scale(), &4);
+        assert_eq!(currency2.active(), &true);
+        assert_eq!(currency2.created(), &now);
+        assert_eq!(currency2.updated(), &now2);
+
+        let res = testfn_inner(&state, 420);
+        assert_eq!(res, Err(Error::CurrencyDecimalScaleInvalid(420)));
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_delete() {
+        let id = CurrencyID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        state.user_mut().set_roles(vec![Role::Bank]);
+        let mods = create(state.user(), id.clone(), "usd", 2, true, &now).unwrap().into_vec();
+        let currency = mods[0].clone().expect_op::<Currency>(Op::Create).unwrap();
+        state.model = Some(currency);
+
+        let mut costs1 = Costs::new_with_labor("widgetmaker", dec!(42.0));
+        costs1.track_currency("cad", dec!(13.1));
+        let mut costs2 = Costs::new_with_labor("widgetmaker", dec!(42.0));
+        costs2.track_currency(id.clone(), dec!(0.01));
+
+        let now2 = util::time::now();
+        let testfn_inner = |state: &TestState<Currency, Currency>, costs: &Vec<Costs>| {
+            delete(state.user(), state.model().clone(), costs, &now2)
+        };
+        let testfn = |state: &TestState<Currency, Currency>| {
+            testfn_inner(state, &vec![costs1.clone()])
+        };
+        test::double_deleted_tester(&state, "currency", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+
+        let currency2 = mods[0].clone().expect_op::<Currency>(Op::Delete).unwrap();
+        assert_eq!(currency2.id(), &id);
+        assert_eq!(currency2.created(), &now);
+        assert_eq!(currency2.deleted(), &Some(now2));
+
+        // can't delete a currency that's still in use
+        let res = testfn_inner(&state, &vec![costs1.clone(), costs2.clone()]);
+        assert_eq!(res, Err(Error::CannotEraseCosts));
+        let costs3: Costs = serde_json::from_value(serde_json::json!({"currency": {id.as_str(): 0}})).unwrap();
+        assert_eq!(costs3.get_currency(id.clone()), dec!(0));
+        let res = testfn_inner(&state, &vec![costs1.clone(), costs3.clone()]);
+        assert_eq!(res, Err(Error::CannotEraseCosts));
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+}
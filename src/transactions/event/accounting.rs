@@ -29,7 +29,8 @@ use vf_rs::{vf, geo::SpatialThing};
 /// amount.
 pub fn lower<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Company, id: EventID, resource: Resource, resource_measure: T, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Lower)?;
+    member.access_check(caller.id(), company, CompanyPermission::Lower, now)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Lower)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -70,7 +71,7 @@ pub fn lower<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Co
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::accounting::lower", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Lower.into()], now)
 }
 
 /// Move costs between internal processes.
@@ -79,7 +80,9 @@ pub fn lower<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Co
 /// if a process has an excess of costs that should be moved somewhere else.
 pub fn move_costs(caller: &User, member: &Member, company: &Company, id: EventID, process_from: Process, process_to: Process, move_costs: Costs, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::MoveCosts)?;
+    member.access_check(caller.id(), company, CompanyPermission::MoveCosts, now)?;
+    member.acl_check(process_from.acl(), &CompanyPermission::MoveCosts)?;
+    member.acl_check(process_to.acl(), &CompanyPermission::MoveCosts)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -120,14 +123,18 @@ pub fn move_costs(caller: &User, member: &Member, company: &Company, id: EventID
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::accounting::move_costs", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::MoveCosts.into()], now)
 }
 
 /// Move a resource internally. This can split a resource into two, or move one
 /// resource entirely into another one.
 pub fn move_resource<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Company, id: EventID, resource_from: Resource, resource_to: ResourceMover, move_costs: Costs, resource_measure: T, new_location: Option<SpatialThing>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::MoveResource)?;
+    member.access_check(caller.id(), company, CompanyPermission::MoveResource, now)?;
+    member.acl_check(resource_from.acl(), &CompanyPermission::MoveResource)?;
+    if let ResourceMover::Update(resource) = &resource_to {
+        member.acl_check(resource.acl(), &CompanyPermission::MoveResource)?;
+    }
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -182,14 +189,15 @@ pub fn move_resource<T: Into<NumericUnion>>(caller: &User, member: &Member, comp
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::accounting::move_resource", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::MoveResource.into()], now)
 }
 
 /// Raise the quantity (both accounting and onhand) or a resource by a fixed
 /// amount.
 pub fn raise<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Company, id: EventID, resource: Resource, resource_measure: T, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Raise)?;
+    member.access_check(caller.id(), company, CompanyPermission::Raise, now)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Raise)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -230,7 +238,7 @@ pub fn raise<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Co
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::accounting::raise", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Raise.into()], now)
 }
 
 #[cfg(test)]
@@ -264,7 +272,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 2);
+        assert_eq!(mods.len(), 3);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource2 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
 
@@ -317,7 +325,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process_from2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let process_to2 = mods[2].clone().expect_op::<Process>(Op::Update).unwrap();
@@ -385,7 +393,7 @@ mod tests {
 
         // test ResourceMover::Update()
         let mods = testfn_update(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource_from2 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_to2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -425,7 +433,7 @@ mod tests {
 
         // test ResourceMover::Create()
         let mods = testfn_create(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource_from3 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_created = mods[2].clone().expect_op::<Resource>(Op::Create).unwrap();
@@ -499,7 +507,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 2);
+        assert_eq!(mods.len(), 3);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource2 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
 
@@ -30,7 +30,9 @@ use vf_rs::{vf, geo::SpatialThing};
 /// This operates on a whole resource.
 pub fn dropoff(caller: &User, member: &Member, company: &Company, id: EventID, process: Process, resource: Resource, move_costs: Costs, new_location: Option<SpatialThing>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Dropoff)?;
+    member.access_check(caller.id(), company, CompanyPermission::Dropoff, now)?;
+    member.acl_check(process.acl(), &CompanyPermission::Dropoff)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Dropoff)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -71,7 +73,7 @@ pub fn dropoff(caller: &User, member: &Member, company: &Company, id: EventID, p
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::delivery::dropoff", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Dropoff.into()], now)
 }
 
 /// Signifies that a delivery has been picked up from its origin. Note that
@@ -81,7 +83,9 @@ pub fn dropoff(caller: &User, member: &Member, company: &Company, id: EventID, p
 /// This operates on a whole resource.
 pub fn pickup(caller: &User, member: &Member, company: &Company, id: EventID, resource: Resource, process: Process, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Pickup)?;
+    member.access_check(caller.id(), company, CompanyPermission::Pickup, now)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Pickup)?;
+    member.acl_check(process.acl(), &CompanyPermission::Pickup)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -121,7 +125,7 @@ pub fn pickup(caller: &User, member: &Member, company: &Company, id: EventID, re
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::delivery::pickup", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Pickup.into()], now)
 }
 
 #[cfg(test)]
@@ -159,7 +163,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let resource2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -227,7 +231,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
 
         assert_eq!(event.id(), &id);
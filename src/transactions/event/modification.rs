@@ -30,7 +30,9 @@ use vf_rs::vf;
 /// of that process would be `modify`.
 pub fn accept<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Company, id: EventID, resource: Resource, process: Process, resource_measure: T, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Accept)?;
+    member.access_check(caller.id(), company, CompanyPermission::Accept, now)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Accept)?;
+    member.acl_check(process.acl(), &CompanyPermission::Accept)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -75,7 +77,7 @@ pub fn accept<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &C
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::modification::accept", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Accept.into()], now)
 }
 
 /// Modify (repair) a resource.
@@ -84,7 +86,9 @@ pub fn accept<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &C
 /// of that process would be `modify`.
 pub fn modify<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Company, id: EventID, process: Process, resource: Resource, move_costs: Costs, resource_measure: T, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Modify)?;
+    member.access_check(caller.id(), company, CompanyPermission::Modify, now)?;
+    member.acl_check(process.acl(), &CompanyPermission::Modify)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Modify)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -129,7 +133,7 @@ pub fn modify<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &C
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::modification::modify", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Modify.into()], now)
 }
 
 #[cfg(test)]
@@ -165,7 +169,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 2);
+        assert_eq!(mods.len(), 3);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource2 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
 
@@ -226,7 +230,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let resource2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
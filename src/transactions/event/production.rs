@@ -33,7 +33,9 @@ use vf_rs::vf;
 /// the costs of that research would be imbued in the formula.
 pub fn cite(caller: &User, member: &Member, company: &Company, id: EventID, resource: Resource, process: Process, move_costs: Costs, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Cite)?;
+    member.access_check(caller.id(), company, CompanyPermission::Cite, now)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Cite)?;
+    member.acl_check(process.acl(), &CompanyPermission::Cite)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -73,7 +75,7 @@ pub fn cite(caller: &User, member: &Member, company: &Company, id: EventID, reso
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::production::cite", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Cite.into()], now)
 }
 
 /// Consume some or all of a resource, transferring some or all of its costs
@@ -84,7 +86,9 @@ pub fn cite(caller: &User, member: &Member, company: &Company, id: EventID, reso
 /// ie `produce`, of a widget).
 pub fn consume<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Company, id: EventID, resource: Resource, process: Process, move_costs: Costs, move_measure: T, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Consume)?;
+    member.access_check(caller.id(), company, CompanyPermission::Consume, now)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Consume)?;
+    member.acl_check(process.acl(), &CompanyPermission::Consume)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -130,7 +134,7 @@ pub fn consume<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::production::consume", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Consume.into()], now)
 }
 
 
@@ -141,7 +145,9 @@ pub fn consume<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &
 /// then `produce` a widget.
 pub fn produce<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &Company, id: EventID, process: Process, resource: Resource, move_costs: Costs, produce_measure: T, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Produce)?;
+    member.access_check(caller.id(), company, CompanyPermission::Produce, now)?;
+    member.acl_check(process.acl(), &CompanyPermission::Produce)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Produce)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -187,7 +193,7 @@ pub fn produce<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::production::produce", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Produce.into()], now)
 }
 
 /// Use a resource, transferring some or all of its costs into the process it's
@@ -211,7 +217,9 @@ pub fn produce<T: Into<NumericUnion>>(caller: &User, member: &Member, company: &
 /// instead of `use`.
 pub fn useeee(caller: &User, member: &Member, company: &Company, id: EventID, resource: Resource, process: Process, move_costs: Costs, effort_quantity: Option<Measure>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::Use)?;
+    member.access_check(caller.id(), company, CompanyPermission::Use, now)?;
+    member.acl_check(resource.acl(), &CompanyPermission::Use)?;
+    member.acl_check(process.acl(), &CompanyPermission::Use)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -252,13 +260,14 @@ pub fn useeee(caller: &User, member: &Member, company: &Company, id: EventID, re
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::production::useeee", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), CompanyPermission::Use.into()], now)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        access::{Acl, AclEntry, AclSubject, DenyReason},
         models::{
             company::CompanyID,
             event::{EventError, EventID},
@@ -292,7 +301,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let resource2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -363,7 +372,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let resource2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -412,6 +421,23 @@ mod tests {
         state4.model_mut().set_in_custody_of(CompanyID::new("ziggy").into());
         let res = testfn(&state4);
         assert_eq!(res, Err(Error::Event(EventError::ResourceCustodyMismatch)));
+
+        // an ACL on the resource or process can limit who consumes
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Role("tool library".into()), vec![CompanyPermission::Consume])]);
+        let mut state5 = state.clone();
+        state5.model_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn(&state5), DenyReason::ObjectAcl);
+        state5.member_mut().set_roles(vec!["tool library".into()]);
+        assert!(testfn(&state5).is_ok());
+
+        let mut state6 = state.clone();
+        state6.model2_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn(&state6), DenyReason::ObjectAcl);
+
+        // ACLs don't affect actions they don't mention
+        let mut state7 = state.clone();
+        state7.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Role("tool library".into()), vec![CompanyPermission::Use])])));
+        assert!(testfn(&state7).is_ok());
     }
 
     #[test]
@@ -434,7 +460,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let resource2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -506,7 +532,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let resource2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
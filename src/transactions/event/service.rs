@@ -5,7 +5,7 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, DenyReason, Permission},
     costs::Costs,
     error::{Error, Result},
     models::{
@@ -29,16 +29,23 @@ use vf_rs::vf;
 /// Provide a service to another agent, moving costs along the way.
 pub fn deliver_service(caller: &User, member: &Member, company_from: &Company, company_to: &Company, agreement: &Agreement, id: EventID, process_from: Process, process_to: Process, move_costs: Costs, agreed_in: Option<Url>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company_from.id(), CompanyPermission::DeliverService)?;
+    member.access_check(caller.id(), company_from, CompanyPermission::DeliverService, now)?;
+    member.acl_check(process_from.acl(), &CompanyPermission::DeliverService)?;
+    member.acl_check(process_to.acl(), &CompanyPermission::DeliverService)?;
     if !company_from.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
     if !company_to.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
-    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_from.agent_id()) {
+    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_to.agent_id()) {
         // can't create an event for an agreement you are not party to
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::AgreementParticipantMismatch, company_from.id().clone()))?;
+    }
+    if !agreement.is_binding() {
+        // can't create an event for an agreement that hasn't been accepted by
+        // all its participants
+        Err(Error::AgreementNotBinding)?;
     }
 
     let process_from_id = process_from.id().clone();
@@ -78,7 +85,7 @@ pub fn deliver_service(caller: &User, member: &Member, company_from: &Company, c
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::service::deliver_service", caller, Some(member), Some(company_from.id()), vec![Permission::EventCreate.into(), CompanyPermission::DeliverService.into()], now)
 }
 
 
@@ -86,11 +93,13 @@ pub fn deliver_service(caller: &User, member: &Member, company_from: &Company, c
 mod tests {
     use super::*;
     use crate::{
+        access::{Acl, AclEntry, AclSubject},
         models::{
             agreement::AgreementID,
             company::CompanyID,
             event::{EventID, EventError},
             lib::agent::Agent,
+            member::MemberID,
             occupation::OccupationID,
             process::{Process, ProcessID},
         },
@@ -124,8 +133,17 @@ mod tests {
         };
         test::standard_transaction_tests(&state, &testfn_from);
 
+        // an ACL on either process can stop the service
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::DeliverService])]);
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_from(&state2), DenyReason::ObjectAcl);
+        let mut state2 = state.clone();
+        state2.model2_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_from(&state2), DenyReason::ObjectAcl);
+
         let mods = testfn_from(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let process_from2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         let process_to2 = mods[2].clone().expect_op::<Process>(Op::Update).unwrap();
@@ -173,7 +191,14 @@ mod tests {
         let mut agreement2 = agreement.clone();
         agreement2.set_participants(vec![company_to.agent_id()]);
         let res = testfn_inner(&state, &company_from, &company_to, &agreement2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
+
+        // can't add an event unless the agreement has been accepted by all
+        // participants
+        let mut agreement3 = agreement.clone();
+        agreement3.reset_signatures();
+        let res = testfn_inner(&state, &company_from, &company_to, &agreement3);
+        assert_eq!(res, Err(Error::AgreementNotBinding));
 
         let mut state5 = state.clone();
         state5.company = Some(company_to.clone());
@@ -7,7 +7,7 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, DenyReason, Permission},
     costs::Costs,
     error::{Error, Result},
     models::{
@@ -34,16 +34,25 @@ use vf_rs::vf;
 /// moving a set of costs with it.
 pub fn transfer<T: Into<NumericUnion>>(caller: &User, member: &Member, company_from: &Company, company_to: &Company, agreement: &Agreement, id: EventID, resource_from: Resource, resource_to: ResourceMover, move_costs: Costs, move_measure: T, agreed_in: Option<Url>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company_from.id(), CompanyPermission::Transfer)?;
+    member.access_check(caller.id(), company_from, CompanyPermission::Transfer, now)?;
+    member.acl_check(resource_from.acl(), &CompanyPermission::Transfer)?;
+    if let ResourceMover::Update(resource) = &resource_to {
+        member.acl_check(resource.acl(), &CompanyPermission::Transfer)?;
+    }
     if !company_from.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
     if !company_to.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
-    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_from.agent_id()) {
+    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_to.agent_id()) {
         // can't create an event for an agreement you are not party to
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::AgreementParticipantMismatch, company_from.id().clone()))?;
+    }
+    if !agreement.is_binding() {
+        // can't create an event for an agreement that hasn't been accepted by
+        // all its participants
+        Err(Error::AgreementNotBinding)?;
     }
     let measure = {
         let unit = resource_from.get_unit().ok_or(Error::ResourceMeasureMissing)?;
@@ -96,23 +105,32 @@ pub fn transfer<T: Into<NumericUnion>>(caller: &User, member: &Member, company_f
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::transfer::transfer", caller, Some(member), Some(company_from.id()), vec![Permission::EventCreate.into(), CompanyPermission::Transfer.into()], now)
 }
 
 /// Transfer ownership (but not custody) of a resource from one company to
 /// another, moving a set of costs with it.
 pub fn transfer_all_rights<T: Into<NumericUnion>>(caller: &User, member: &Member, company_from: &Company, company_to: &Company, agreement: &Agreement, id: EventID, resource_from: Resource, resource_to: ResourceMover, move_costs: Costs, move_measure: T, agreed_in: Option<Url>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company_from.id(), CompanyPermission::TransferAllRights)?;
+    member.access_check(caller.id(), company_from, CompanyPermission::TransferAllRights, now)?;
+    member.acl_check(resource_from.acl(), &CompanyPermission::TransferAllRights)?;
+    if let ResourceMover::Update(resource) = &resource_to {
+        member.acl_check(resource.acl(), &CompanyPermission::TransferAllRights)?;
+    }
     if !company_from.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
     if !company_to.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
-    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_from.agent_id()) {
+    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_to.agent_id()) {
         // can't create an event for an agreement you are not party to
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::AgreementParticipantMismatch, company_from.id().clone()))?;
+    }
+    if !agreement.is_binding() {
+        // can't create an event for an agreement that hasn't been accepted by
+        // all its participants
+        Err(Error::AgreementNotBinding)?;
     }
     let measure = {
         let unit = resource_from.get_unit().ok_or(Error::ResourceMeasureMissing)?;
@@ -165,23 +183,32 @@ pub fn transfer_all_rights<T: Into<NumericUnion>>(caller: &User, member: &Member
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::transfer::transfer_all_rights", caller, Some(member), Some(company_from.id()), vec![Permission::EventCreate.into(), CompanyPermission::TransferAllRights.into()], now)
 }
 
 /// Transfer custody (but not ownership) of a resource from one company to
 /// another, moving a set of costs with it.
 pub fn transfer_custody<T: Into<NumericUnion>>(caller: &User, member: &Member, company_from: &Company, company_to: &Company, agreement: &Agreement, id: EventID, resource_from: Resource, resource_to: ResourceMover, move_costs: Costs, move_measure: T, agreed_in: Option<Url>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::EventCreate)?;
-    member.access_check(caller.id(), company_from.id(), CompanyPermission::TransferCustody)?;
+    member.access_check(caller.id(), company_from, CompanyPermission::TransferCustody, now)?;
+    member.acl_check(resource_from.acl(), &CompanyPermission::TransferCustody)?;
+    if let ResourceMover::Update(resource) = &resource_to {
+        member.acl_check(resource.acl(), &CompanyPermission::TransferCustody)?;
+    }
     if !company_from.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
     if !company_to.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
-    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_from.agent_id()) {
+    if !agreement.has_participant(&company_from.agent_id()) || !agreement.has_participant(&company_to.agent_id()) {
         // can't create an event for an agreement you are not party to
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::AgreementParticipantMismatch, company_from.id().clone()))?;
+    }
+    if !agreement.is_binding() {
+        // can't create an event for an agreement that hasn't been accepted by
+        // all its participants
+        Err(Error::AgreementNotBinding)?;
     }
     let measure = {
         let unit = resource_from.get_unit().ok_or(Error::ResourceMeasureMissing)?;
@@ -234,18 +261,20 @@ pub fn transfer_custody<T: Into<NumericUnion>>(caller: &User, member: &Member, c
     for evmod in evmods {
         mods.push_raw(evmod);
     }
-    Ok(mods)
+    mods.audit("event::transfer::transfer_custody", caller, Some(member), Some(company_from.id()), vec![Permission::EventCreate.into(), CompanyPermission::TransferCustody.into()], now)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        access::{Acl, AclEntry, AclSubject},
         models::{
             agreement::AgreementID,
             company::CompanyID,
             event::{EventID, EventError},
             lib::agent::Agent,
+            member::MemberID,
             resource::ResourceID,
         },
         util::{self, test::{self, *}},
@@ -282,9 +311,19 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn_update);
         test::standard_transaction_tests(&state, &testfn_create);
 
+        // an ACL on either resource can stop the transfer
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::Transfer])]);
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_update(&state2), DenyReason::ObjectAcl);
+        let mut state2 = state.clone();
+        state2.model2_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_update(&state2), DenyReason::ObjectAcl);
+        assert!(testfn_create(&state2).is_ok());
+
         // test ResourceMover::Update()
         let mods = testfn_update(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource2 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_to2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -323,7 +362,7 @@ mod tests {
 
         // test ResourceMover::Create()
         let mods = testfn_create(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource3 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_created = mods[2].clone().expect_op::<Resource>(Op::Create).unwrap();
@@ -385,9 +424,18 @@ mod tests {
         let mut agreement2 = agreement.clone();
         agreement2.set_participants(vec![company_to.agent_id()]);
         let res = testfn_inner(&state, &company_from, &company_to, &agreement2, ResourceMover::Update(state.model2().clone()));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
         let res = testfn_inner(&state, &company_from, &company_to, &agreement2, ResourceMover::Create(state.model2().id().clone()));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
+
+        // can't add an event unless the agreement has been accepted by all
+        // participants
+        let mut agreement3 = agreement.clone();
+        agreement3.reset_signatures();
+        let res = testfn_inner(&state, &company_from, &company_to, &agreement3, ResourceMover::Update(state.model2().clone()));
+        assert_eq!(res, Err(Error::AgreementNotBinding));
+        let res = testfn_inner(&state, &company_from, &company_to, &agreement3, ResourceMover::Create(state.model2().id().clone()));
+        assert_eq!(res, Err(Error::AgreementNotBinding));
 
         let mut state5 = state.clone();
         state5.company = Some(company_to.clone());
@@ -423,9 +471,19 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn_update);
         test::standard_transaction_tests(&state, &testfn_create);
 
+        // an ACL on either resource can stop the transfer
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::TransferAllRights])]);
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_update(&state2), DenyReason::ObjectAcl);
+        let mut state2 = state.clone();
+        state2.model2_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_update(&state2), DenyReason::ObjectAcl);
+        assert!(testfn_create(&state2).is_ok());
+
         // test ResourceMover::Update()
         let mods = testfn_update(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource2 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_to2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -464,7 +522,7 @@ mod tests {
 
         // test ResourceMover::Create()
         let mods = testfn_create(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource3 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_created = mods[2].clone().expect_op::<Resource>(Op::Create).unwrap();
@@ -518,9 +576,18 @@ mod tests {
         let mut agreement2 = agreement.clone();
         agreement2.set_participants(vec![company_to.agent_id()]);
         let res = testfn_inner(&state, &company_from, &company_to, &agreement2, ResourceMover::Update(state.model2().clone()));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
         let res = testfn_inner(&state, &company_from, &company_to, &agreement2, ResourceMover::Create(state.model2().id().clone()));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
+
+        // can't add an event unless the agreement has been accepted by all
+        // participants
+        let mut agreement3 = agreement.clone();
+        agreement3.reset_signatures();
+        let res = testfn_inner(&state, &company_from, &company_to, &agreement3, ResourceMover::Update(state.model2().clone()));
+        assert_eq!(res, Err(Error::AgreementNotBinding));
+        let res = testfn_inner(&state, &company_from, &company_to, &agreement3, ResourceMover::Create(state.model2().id().clone()));
+        assert_eq!(res, Err(Error::AgreementNotBinding));
 
         let mut state5 = state.clone();
         state5.company = Some(company_to.clone());
@@ -556,9 +623,19 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn_update);
         test::standard_transaction_tests(&state, &testfn_create);
 
+        // an ACL on either resource can stop the transfer
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::TransferCustody])]);
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_update(&state2), DenyReason::ObjectAcl);
+        let mut state2 = state.clone();
+        state2.model2_mut().set_acl(Some(acl.clone()));
+        expect_denied(testfn_update(&state2), DenyReason::ObjectAcl);
+        assert!(testfn_create(&state2).is_ok());
+
         // test ResourceMover::Update()
         let mods = testfn_update(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource2 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_to2 = mods[2].clone().expect_op::<Resource>(Op::Update).unwrap();
@@ -597,7 +674,7 @@ mod tests {
 
         // test ResourceMover::Create()
         let mods = testfn_create(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 3);
+        assert_eq!(mods.len(), 4);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
         let resource3 = mods[1].clone().expect_op::<Resource>(Op::Update).unwrap();
         let resource_created = mods[2].clone().expect_op::<Resource>(Op::Create).unwrap();
@@ -650,9 +727,18 @@ mod tests {
         let mut agreement2 = agreement.clone();
         agreement2.set_participants(vec![company_to.agent_id()]);
         let res = testfn_inner(&state, &company_from, &company_to, &agreement2, ResourceMover::Update(state.model2().clone()));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
         let res = testfn_inner(&state, &company_from, &company_to, &agreement2, ResourceMover::Create(state.model2().id().clone()));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::AgreementParticipantMismatch);
+
+        // can't add an event unless the agreement has been accepted by all
+        // participants
+        let mut agreement3 = agreement.clone();
+        agreement3.reset_signatures();
+        let res = testfn_inner(&state, &company_from, &company_to, &agreement3, ResourceMover::Update(state.model2().clone()));
+        assert_eq!(res, Err(Error::AgreementNotBinding));
+        let res = testfn_inner(&state, &company_from, &company_to, &agreement3, ResourceMover::Create(state.model2().id().clone()));
+        assert_eq!(res, Err(Error::AgreementNotBinding));
 
         let mut state5 = state.clone();
         state5.company = Some(company_to.clone());
@@ -11,13 +11,15 @@ use crate::{
     models::{
         Op,
         Modifications,
-        event::{Event, EventID, EventProcessState},
+        event::{Event, EventError, EventID, EventProcessState},
         company::{Company, Permission as CompanyPermission},
         member::Member,
         lib::basis_model::Model,
+        payroll::PayPeriod,
         process::Process,
         user::User,
     },
+    util::measure,
 };
 use om2::{Measure, Unit};
 use rust_decimal::prelude::*;
@@ -30,7 +32,7 @@ use vf_rs::vf;
 /// (`begin`/`end`) data together, unless you're truly tracking them separately.
 /// Sometimes you might not know or care to track detailed hourly data (as with
 /// salary) but it can be estimated to some extent using data in the worker's
-/// Member record.
+/// Member record (see [work_estimated](fn.work_estimated.html)).
 ///
 /// Note that this creates a full work event with a defined start and end. This
 /// function cannot create pending work events.
@@ -38,11 +40,14 @@ pub fn work(caller: &User, member: &Member, company: &Company, id: EventID, work
     caller.access_check(Permission::EventCreate)?;
     // if we're recording our own work event, we can just check the regular
     // `Work` permission, otherwise we need admin privs
-    if member.id() == worker.id() {
-        member.access_check(caller.id(), company.id(), CompanyPermission::Work)?;
+    let permission = if member.id() == worker.id() {
+        CompanyPermission::Work
     } else {
-        member.access_check(caller.id(), company.id(), CompanyPermission::WorkAdmin)?;
-    }
+        CompanyPermission::WorkAdmin
+    };
+    member.access_check(caller.id(), company, permission.clone(), now)?;
+    // the process's ACL applies to whoever is doing the work
+    worker.acl_check(process.acl(), &CompanyPermission::Work)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -57,6 +62,58 @@ pub fn work(caller: &User, member: &Member, company: &Company, id: EventID, work
         Some(val) => Costs::new_with_labor(occupation_id, val),
         None => Costs::new(),
     };
+    create_work_event(company, id, worker, process, effort, costs, begin, end, note, now)?
+        .audit("event::work::work", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), permission.into()], now)
+}
+
+/// Create a work event for a salaried worker over the given date range, using
+/// the worker's compensation to estimate both the hours worked and the wage
+/// costs.
+///
+/// The hours are estimated from the `est_hours_per_week` field of the worker's
+/// salary, and the wage costs are the yearly salary prorated over the range.
+/// This keeps labor-hours tracking complete for salaried workers, who likely
+/// aren't clocking in and out. Generally you'd call this once per pay period
+/// (or once per week, etc) for each salaried worker.
+///
+/// `end` must come after `begin`, otherwise we'd be estimating negative hours
+/// and wages.
+pub fn work_estimated(caller: &User, member: &Member, company: &Company, id: EventID, worker: Member, process: Process, begin: DateTime<Utc>, end: DateTime<Utc>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::EventCreate)?;
+    let permission = if member.id() == worker.id() {
+        CompanyPermission::Work
+    } else {
+        CompanyPermission::WorkAdmin
+    };
+    member.access_check(caller.id(), company, permission.clone(), now)?;
+    worker.acl_check(process.acl(), &CompanyPermission::Work)?;
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if end <= begin {
+        Err(EventError::DateEndBeforeBegin)?;
+    }
+
+    let occupation_id = worker.occupation_id().ok_or(Error::MemberMustBeWorker)?.clone();
+    let compensation = worker.compensation().ok_or(Error::CompensationNotSalaried)?;
+    let est_hours_per_week = match (compensation.wage().has_unit(), compensation.est_hours_per_week()) {
+        (Unit::Year, Some(hours)) => *hours,
+        _ => Err(Error::CompensationNotSalaried)?,
+    };
+    let effort = {
+        let milliseconds = end.timestamp_millis() - begin.timestamp_millis();
+        let weeks = Decimal::from(milliseconds) / Decimal::from(1000 * 60 * 60 * 24 * 7);
+        Measure::new(weeks * est_hours_per_week, Unit::Hour)
+    };
+    let salary = measure::to_decimal(compensation.wage())?;
+    let wage_cost = PayPeriod::new(begin, end).prorate_yearly(salary)?;
+    let costs = Costs::new_with_labor(occupation_id, wage_cost);
+    create_work_event(company, id, worker, process, effort, costs, begin, end, note, now)?
+        .audit("event::work::work_estimated", caller, Some(member), Some(company.id()), vec![Permission::EventCreate.into(), permission.into()], now)
+}
+
+/// Builds a work event and runs it against the given process.
+fn create_work_event(company: &Company, id: EventID, worker: Member, process: Process, effort: Measure, costs: Costs, begin: DateTime<Utc>, end: DateTime<Utc>, note: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
     let process_id = process.id().clone();
     let member_id = worker.id().clone();
     let agreement = worker.agreement().clone();
@@ -101,10 +158,12 @@ pub fn work(caller: &User, member: &Member, company: &Company, id: EventID, work
 mod tests {
     use super::*;
     use crate::{
+        access::{Acl, AclEntry, AclSubject, DenyReason},
         models::{
+            account::AccountID,
             company::CompanyID,
             member::*,
-            event::{Event, EventID, EventError},
+            event::{Event, EventID},
             lib::agent::Agent,
             process::ProcessID,
         },
@@ -130,7 +189,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 2);
+        assert_eq!(mods.len(), 3);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
 
         assert_eq!(event.id(), &id);
@@ -148,7 +207,9 @@ mod tests {
 
         let mut costs2 = Costs::new();
         costs2.track_labor(occupation_id.clone(), dec!(177.5) + dec!(78.4));
-        costs2.track_labor_hours(occupation_id.clone(), dec!(6.8666666666666666666666666666));
+        // 6h52m, computed rather than spelled out so we don't depend on how
+        // rust_decimal rounds the last digit
+        costs2.track_labor_hours(occupation_id.clone(), dec!(412) / dec!(60));
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
         assert_eq!(process2.id(), state.model2().id());
         assert_eq!(process2.company_id(), state.company().id());
@@ -159,10 +220,10 @@ mod tests {
         let mut state2 = state.clone();
         state2.model_mut().set_id(MemberID::create());
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
         state2.member_mut().set_permissions(vec![CompanyPermission::WorkAdmin]);
         let mods = testfn(&state2).unwrap().into_vec();
-        assert_eq!(mods.len(), 2);
+        assert_eq!(mods.len(), 3);
         let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
 
         assert_eq!(event.id(), &id);
@@ -180,7 +241,9 @@ mod tests {
 
         let mut costs2 = Costs::new();
         costs2.track_labor(occupation_id.clone(), dec!(177.5) + dec!(78.4));
-        costs2.track_labor_hours(occupation_id.clone(), dec!(6.8666666666666666666666666666));
+        // 6h52m, computed rather than spelled out so we don't depend on how
+        // rust_decimal rounds the last digit
+        costs2.track_labor_hours(occupation_id.clone(), dec!(412) / dec!(60));
         let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
 
         assert_eq!(process2.id(), state.model2().id());
@@ -202,5 +265,77 @@ mod tests {
         let res = testfn(&state4);
         assert_eq!(res, Err(Error::MemberMustBeWorker));
     }
-}
 
+    #[test]
+    fn can_work_estimated() {
+        let now: DateTime<Utc> = "2020-02-17T00:00:00Z".parse().unwrap();
+        let begin: DateTime<Utc> = "2020-02-03T00:00:00Z".parse().unwrap();
+        let id = EventID::create();
+        let mut state = TestState::standard(vec![CompanyPermission::Work], &now);
+        let occupation_id = state.member().occupation_id().unwrap().clone();
+        let mut worker = state.member().clone();
+        match worker.class_mut() {
+            MemberClass::Worker(class) => { class.set_compensation(Some(Compensation::new_salary(dec!(73200), AccountID::create(), dec!(40)))); }
+            _ => panic!("work::tests::can_work_estimated() -- bad class"),
+        }
+        state.member = Some(worker.clone());
+        let process = make_process(&ProcessID::create(), state.company().id(), "make widgets", &Costs::new(), &now);
+        state.model = Some(worker);
+        state.model2 = Some(process);
+
+        let testfn = |state: &TestState<Member, Process>| {
+            work_estimated(state.user(), state.member(), state.company(), id.clone(), state.model().clone(), state.model2().clone(), begin, now, None, &now)
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 3);
+        let event = mods[0].clone().expect_op::<Event>(Op::Create).unwrap();
+
+        // two weeks at 40 hours a week, and two weeks of salary in a leap year
+        assert_eq!(event.id(), &id);
+        assert_eq!(event.inner().effort_quantity(), &Some(Measure::new(dec!(80), Unit::Hour)));
+        assert_eq!(event.inner().has_beginning(), &Some(begin));
+        assert_eq!(event.inner().has_end(), &Some(now));
+        assert_eq!(event.inner().provider().clone(), state.model().agent_id());
+        assert_eq!(event.inner().receiver().clone(), state.company().agent_id());
+        assert_eq!(event.move_costs(), &Some(Costs::new_with_labor(occupation_id.clone(), dec!(2800))));
+
+        let mut costs2 = Costs::new();
+        costs2.track_labor(occupation_id.clone(), dec!(2800));
+        costs2.track_labor_hours(occupation_id.clone(), dec!(80));
+        let process2 = mods[1].clone().expect_op::<Process>(Op::Update).unwrap();
+        assert_eq!(process2.id(), state.model2().id());
+        assert_eq!(process2.costs(), &costs2);
+
+        // hourly workers must record their hours
+        let mut state2 = state.clone();
+        match state2.model_mut().class_mut() {
+            MemberClass::Worker(class) => { class.set_compensation(Some(Compensation::new_hourly(dec!(32), AccountID::create()))); }
+            _ => panic!("work::tests::can_work_estimated() -- bad class"),
+        }
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::CompensationNotSalaried));
+        state2.model_mut().set_class(MemberClass::Worker(MemberWorker::new(occupation_id.clone(), None)));
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::CompensationNotSalaried));
+        state2.model_mut().set_class(MemberClass::User(MemberUser::new()));
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::MemberMustBeWorker));
+
+        // reversed (or empty) ranges don't get estimated
+        let res = work_estimated(state.user(), state.member(), state.company(), id.clone(), state.model().clone(), state.model2().clone(), now, begin, None, &now);
+        assert_eq!(res, Err(Error::Event(EventError::DateEndBeforeBegin)));
+        let res = work_estimated(state.user(), state.member(), state.company(), id.clone(), state.model().clone(), state.model2().clone(), begin, begin, None, &now);
+        assert_eq!(res, Err(Error::Event(EventError::DateEndBeforeBegin)));
+
+        // the process's ACL is checked against the worker
+        let mut state3 = state.clone();
+        state3.model2_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::Work])])));
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::ObjectAcl);
+        let worker_id = state3.model().id().clone();
+        state3.model2_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(worker_id), vec![CompanyPermission::Work])])));
+        assert!(testfn(&state3).is_ok());
+    }
+}
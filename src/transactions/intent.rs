@@ -10,7 +10,7 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, DenyReason, Permission},
     costs::Costs,
     error::{Error, Result},
     models::{
@@ -36,7 +36,7 @@ use vf_rs::{vf, geo::SpatialThing};
 /// Create a new intent
 pub fn create(caller: &User, member: &Member, company: &Company, id: IntentID, move_costs: Option<Costs>, action: OrderAction, agreed_in: Option<Url>, at_location: Option<SpatialThing>, available_quantity: Option<Measure>, due: Option<DateTime<Utc>>, effort_quantity: Option<Measure>, finished: Option<bool>, has_beginning: Option<DateTime<Utc>>, has_end: Option<DateTime<Utc>>, has_point_in_time: Option<DateTime<Utc>>, in_scope_of: Vec<AgentID>, name: Option<String>, note: Option<String>, provider: Option<AgentID>, receiver: Option<AgentID>, resource_conforms_to: Option<ResourceSpecID>, resource_inventoried_as: Option<ResourceID>, resource_quantity: Option<Measure>, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateIntents)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::IntentCreate)?;
+    member.access_check(caller.id(), company, CompanyPermission::IntentCreate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -47,7 +47,7 @@ pub fn create(caller: &User, member: &Member, company: &Company, id: IntentID, m
     }
     if (provider.is_some() && Some(&company_agent_id) != provider.as_ref()) || (receiver.is_some() && Some(&company_agent_id) != receiver.as_ref()) {
         // can't create an intent for a company you aren't a member of DUUUHHH
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
     }
     let event_action = match action {
         OrderAction::DeliverService => vf::Action::DeliverService,
@@ -85,13 +85,14 @@ pub fn create(caller: &User, member: &Member, company: &Company, id: IntentID, m
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("intent::create", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateIntents.into(), CompanyPermission::IntentCreate.into()], now)
 }
 
 /// Update an intent
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Intent, move_costs: Option<Option<Costs>>, action: Option<OrderAction>, agreed_in: Option<Option<Url>>, at_location: Option<Option<SpatialThing>>, available_quantity: Option<Option<Measure>>, due: Option<Option<DateTime<Utc>>>, effort_quantity: Option<Option<Measure>>, finished: Option<Option<bool>>, has_beginning: Option<Option<DateTime<Utc>>>, has_end: Option<Option<DateTime<Utc>>>, has_point_in_time: Option<Option<DateTime<Utc>>>, in_scope_of: Option<Vec<AgentID>>, name: Option<Option<String>>, note: Option<Option<String>>, provider: Option<Option<AgentID>>, receiver: Option<Option<AgentID>>, resource_conforms_to: Option<Option<ResourceSpecID>>, resource_inventoried_as: Option<Option<ResourceID>>, resource_quantity: Option<Option<Measure>>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateIntents)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::IntentUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::IntentUpdate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -100,17 +101,18 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: In
         // an intent must have a provider or receiver
         Err(Error::MissingFields(vec!["provider".into(), "receiver".into()]))?;
     }
+    let original = subject.clone();
     if let Some(provider) = provider {
         if provider.is_some() && Some(&company_agent_id) != provider.as_ref() {
             // can't create an intent for a company you aren't a member of DUUUHHH
-            Err(Error::InsufficientPrivileges)?;
+            Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
         }
         subject.inner_mut().set_provider(provider);
     }
     if let Some(receiver) = receiver {
         if receiver.is_some() && Some(&company_agent_id) != receiver.as_ref() {
             // can't create an intent for a company you aren't a member of DUUUHHH
-            Err(Error::InsufficientPrivileges)?;
+            Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
         }
         subject.inner_mut().set_receiver(receiver);
     }
@@ -178,13 +180,14 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: In
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("intent::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateIntents.into(), CompanyPermission::IntentUpdate.into()], now)
 }
 
 /// Delete an intent
 pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Intent, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateIntents)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::IntentDelete)?;
+    member.access_check(caller.id(), company, CompanyPermission::IntentDelete, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -192,7 +195,8 @@ pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: In
         Err(Error::ObjectIsDeleted("intent".into()))?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("intent::delete", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateIntents.into(), CompanyPermission::IntentDelete.into()], now)
 }
 
 #[cfg(test)]
@@ -222,7 +226,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let intent = mods[0].clone().expect_op::<Intent>(Op::Create).unwrap();
         assert_eq!(intent.id(), &id);
@@ -253,9 +257,9 @@ mod tests {
         let mut state2 = state.clone();
         state2.company_mut().set_id(CompanyID::new("bill's company"));
         let res = testfn_inner(&state2, Some(state.company().agent_id()), None);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongCompany);
         let res = testfn_inner(&state2, None, Some(state.company().agent_id()));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongCompany);
 
         let res = testfn_inner(&state, None, None);
         assert_eq!(res, Err(Error::MissingFields(vec!["provider".into(), "receiver".into()])));
@@ -313,9 +317,9 @@ mod tests {
         let mut state2 = state.clone();
         state2.company_mut().set_id(CompanyID::new("bill's company"));
         let res = testfn_inner(&state2, Some(Some(CompanyID::new("widgetzzz plus").into())), None);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongCompany);
         let res = testfn_inner(&state2, None, Some(Some(CompanyID::new("widgetzzz plus").into())));
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongCompany);
 
         let res = testfn_inner(&state, Some(None), Some(None));
         assert_eq!(res, Err(Error::MissingFields(vec!["provider".into(), "receiver".into()])));
@@ -340,7 +344,7 @@ mod tests {
         test::double_deleted_tester(&state, "intent", &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let intent2 = mods[0].clone().expect_op::<Intent>(Op::Delete).unwrap();
         assert_eq!(intent2.id(), state.model().id());
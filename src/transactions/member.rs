@@ -7,13 +7,13 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, CanAccess, DenyReason, Permission, RequiredPermission},
     error::{Error, Result},
     models::{
         Op,
         Modifications,
         company::{Company, Permission as CompanyPermission},
-        member::{Compensation, Member, MemberID, MemberClass},
+        member::{Compensation, Delegation, Member, MemberID, MemberClass, VoteProxy, resolve_vote_delegate},
         lib::{
             agent::Agent,
             basis_model::Model,
@@ -28,7 +28,7 @@ use vf_rs::vf;
 /// Create a new member.
 pub fn create<T: Agent>(caller: &User, member: &Member, id: MemberID, agent_from: T, agent_to: Company, class: MemberClass, permissions: Vec<CompanyPermission>, agreement: Option<Url>, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateMembers)?;
-    member.access_check(caller.id(), agent_to.id(), CompanyPermission::MemberCreate)?;
+    member.access_check(caller.id(), &agent_to, CompanyPermission::MemberCreate, now)?;
     if !agent_from.is_active() {
         Err(Error::ObjectIsInactive("agent".into()))?;
     }
@@ -53,20 +53,22 @@ pub fn create<T: Agent>(caller: &User, member: &Member, id: MemberID, agent_from
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("member::create", caller, Some(member), Some(agent_to.id()), vec![Permission::CompanyUpdateMembers.into(), CompanyPermission::MemberCreate.into()], now)
 }
 
 /// Update a member.
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Member, occupation_id: Option<OccupationID>, agreement: Option<Url>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateMembers)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::MemberUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::MemberUpdate, now)?;
     if company.id() != &subject.company_id()? {
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
     }
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
 
+    let original = subject.clone();
     if let Some(occupation_id) = occupation_id {
         match subject.class_mut() {
             MemberClass::Worker(worker) => {
@@ -82,36 +84,213 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Me
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("member::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateMembers.into(), CompanyPermission::MemberUpdate.into()], now)
 }
 
 /// Set a member's company permissions.
 pub fn set_permissions(caller: &User, member: &Member, company: &Company, mut subject: Member, permissions: Vec<CompanyPermission>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateMembers)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::MemberSetPermissions)?;
+    member.access_check(caller.id(), company, CompanyPermission::MemberSetPermissions, now)?;
     if company.id() != &subject.company_id()? {
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
     }
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
 
+    let original = subject.clone();
     subject.set_permissions(permissions);
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("member::set_permissions", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateMembers.into(), CompanyPermission::MemberSetPermissions.into()], now)
+}
+
+/// Set the company roles a member holds. The roles must be defined by the
+/// company.
+pub fn set_roles(caller: &User, member: &Member, company: &Company, mut subject: Member, roles: Vec<String>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateMembers)?;
+    member.access_check(caller.id(), company, CompanyPermission::MemberSetPermissions, now)?;
+    if company.id() != &subject.company_id()? {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if let Some(missing) = roles.iter().find(|name| company.role(name).is_none()) {
+        Err(Error::CompanyRoleNotFound(missing.clone()))?;
+    }
+
+    let original = subject.clone();
+    subject.set_roles(roles);
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("member::set_roles", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateMembers.into(), CompanyPermission::MemberSetPermissions.into()], now)
+}
+
+/// Delegate some of the calling member's permissions to another member of the
+/// same company for a limited time.
+///
+/// The caller must hold every permission they're delegating (through their own
+/// permissions or roles, permissions delegated *to* the caller cannot be passed
+/// on). Any previous delegation from the caller to this member is replaced.
+pub fn delegate(caller: &User, member: &Member, company: &Company, mut subject: Member, permissions: Vec<CompanyPermission>, start: DateTime<Utc>, expires: DateTime<Utc>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateMembers)?;
+    if member.member_id() != &caller.agent_id() {
+        Err(AccessDenied::for_actor(DenyReason::WrongUser, member.id().clone()))?;
+    }
+    if !member.in_scope(company) {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if company.id() != &subject.company_id()? {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if let Some(perm) = permissions.iter().find(|perm| !member.can(company, perm)) {
+        // no delegating permissions we don't hold ourselves
+        Err(AccessDenied::new(DenyReason::MissingPermission, Some(perm.clone().into()), Some(member.agent_id())))?;
+    }
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("member".into()))?;
+    }
+    if permissions.is_empty() || member.id() == subject.id() || expires <= start || &expires <= now {
+        Err(Error::DelegationInvalid)?;
+    }
+
+    let original = subject.clone();
+    subject.delegate(Delegation::new(member.id().clone(), permissions, start, expires));
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("member::delegate", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateMembers.into()], now)
+}
+
+/// Revoke the permissions `grantor` has delegated to another member.
+///
+/// Members can revoke their own delegations. Revoking someone else's (for
+/// instance, one made by a member who has since been deleted) requires
+/// `MemberSetPermissions`.
+pub fn revoke_delegation(caller: &User, member: &Member, company: &Company, mut subject: Member, grantor: &MemberID, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateMembers)?;
+    let mut permissions: Vec<RequiredPermission> = vec![Permission::CompanyUpdateMembers.into()];
+    if member.member_id() != &caller.agent_id() {
+        Err(AccessDenied::for_actor(DenyReason::WrongUser, member.id().clone()))?;
+    }
+    if !member.in_scope(company) {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if !member.is_active() {
+        Err(AccessDenied::for_actor(DenyReason::Inactive, member.id().clone()))?;
+    }
+    if grantor != member.id() {
+        member.access_check(caller.id(), company, CompanyPermission::MemberSetPermissions, now)?;
+        permissions.push(CompanyPermission::MemberSetPermissions.into());
+    }
+    if company.id() != &subject.company_id()? {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    let original = subject.clone();
+    if !subject.revoke_delegation(grantor) {
+        Err(Error::DelegationNotFound)?;
+    }
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("member::revoke_delegation", caller, Some(member), Some(company.id()), permissions, now)
+}
+
+/// Delegate the calling member's vote on company proposals to another member
+/// of the same company, either for a specific proposal topic or (if `topic` is
+/// `None`) for everything not covered by a topic-specific delegation. Any
+/// previous delegation for the same topic is replaced.
+///
+/// Delegations are transitive: if our delegate doesn't vote, our vote follows
+/// *their* delegation. `members` holds the company's member records, and is
+/// used to make sure the new delegation doesn't loop back to the caller.
+pub fn delegate_vote(caller: &User, member: &Member, company: &Company, delegate: &Member, topic: Option<String>, members: &[Member], now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ProposalVote)?;
+    if member.member_id() != &caller.agent_id() {
+        Err(AccessDenied::for_actor(DenyReason::WrongUser, member.id().clone()))?;
+    }
+    if !member.in_scope(company) {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if !member.is_active() {
+        Err(AccessDenied::for_actor(DenyReason::Inactive, member.id().clone()))?;
+    }
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if delegate.id() == member.id() || !delegate.in_scope(company) || !delegate.is_active() {
+        Err(Error::VoteDelegationInvalid)?;
+    }
+    // a topic-specific delegation only matters for its own topic, but a
+    // general delegation is followed for any topic we don't have a specific
+    // delegation for
+    let mut topics = vec![topic.clone()];
+    if topic.is_none() {
+        let other_topics = members.iter()
+            .flat_map(|other| other.vote_proxies().iter())
+            .filter_map(|proxy| proxy.topic().clone())
+            .filter(|other_topic| member.vote_proxies().iter().all(|proxy| proxy.topic().as_ref() != Some(other_topic)));
+        for other_topic in other_topics {
+            if !topics.contains(&Some(other_topic.clone())) {
+                topics.push(Some(other_topic));
+            }
+        }
+    }
+    let loops_back = topics.iter()
+        .any(|topic| resolve_vote_delegate(members, delegate.id(), topic.as_ref(), |member_id| member_id == member.id()).is_some());
+    if loops_back {
+        Err(Error::VoteDelegationCycle)?;
+    }
+
+    let mut subject = member.clone();
+    let original = subject.clone();
+    subject.delegate_vote(VoteProxy::new(delegate.id().clone(), topic));
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("member::delegate_vote", caller, Some(member), Some(company.id()), vec![Permission::ProposalVote.into()], now)
+}
+
+/// Revoke the calling member's vote delegation for the given topic (or their
+/// general delegation, if `topic` is `None`).
+pub fn revoke_vote_delegation(caller: &User, member: &Member, company: &Company, topic: Option<String>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ProposalVote)?;
+    if member.member_id() != &caller.agent_id() {
+        Err(AccessDenied::for_actor(DenyReason::WrongUser, member.id().clone()))?;
+    }
+    if !member.in_scope(company) {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    let mut subject = member.clone();
+    let original = subject.clone();
+    if !subject.revoke_vote_proxy(topic.as_ref()) {
+        Err(Error::DelegationNotFound)?;
+    }
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("member::revoke_vote_delegation", caller, Some(member), Some(company.id()), vec![Permission::ProposalVote.into()], now)
 }
 
 /// Set a member's compensation.
 pub fn set_compensation(caller: &User, member: &Member, company: &Company, mut subject: Member, compensation: Compensation, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateMembers)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::MemberSetCompensation)?;
+    member.access_check(caller.id(), company, CompanyPermission::MemberSetCompensation, now)?;
     if company.id() != &subject.company_id()? {
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
     }
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
 
+    let original = subject.clone();
     match subject.class_mut() {
         MemberClass::Worker(worker) => {
             worker.set_compensation(Some(compensation));
@@ -119,15 +298,16 @@ pub fn set_compensation(caller: &User, member: &Member, company: &Company, mut s
         _ => Err(Error::MemberMustBeWorker)?,
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("member::set_compensation", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateMembers.into(), CompanyPermission::MemberSetCompensation.into()], now)
 }
 
 /// Delete a member.
 pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Member, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateMembers)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::MemberDelete)?;
+    member.access_check(caller.id(), company, CompanyPermission::MemberDelete, now)?;
     if company.id() != &subject.company_id()? {
-        Err(Error::InsufficientPrivileges)?;
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
     }
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
@@ -137,7 +317,8 @@ pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Me
     }
 
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("member::delete", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateMembers.into(), CompanyPermission::MemberDelete.into()], now)
 }
 
 #[cfg(test)]
@@ -147,6 +328,8 @@ mod tests {
         models::{
             member::*,
             account::AccountID,
+            audit_record::AuditRecord,
+            company::{CompanyID, Role},
             lib::{
                 agent::Agent,
                 basis_model::Model,
@@ -175,7 +358,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let member = mods[0].clone().expect_op::<Member>(Op::Create).unwrap();
         assert_eq!(member.id(), &id);
         assert_eq!(member.inner().subject(), &state.model().agent_id());
@@ -216,7 +399,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let member2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
         assert_eq!(state.model().id(), member2.id());
         assert_eq!(state.model().created(), member2.created());
@@ -230,12 +413,12 @@ mod tests {
         let mut state2 = state.clone();
         state2.member = state.model.clone();
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongUser);
 
         let mut state3 = state.clone();
         state3.user = Some(new_user.clone());
         let res = testfn(&state3);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::WrongUser);
 
         let mut state4 = state.clone();
         state4.model_mut().set_class(MemberClass::User(MemberUser::new()));
@@ -265,11 +448,11 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let member2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
         assert_eq!(member2.permissions(), &vec![CompanyPermission::ResourceSpecCreate]);
-        assert!(!state.model().can(&CompanyPermission::ResourceSpecCreate));
-        assert!(member2.can(&CompanyPermission::ResourceSpecCreate));
+        assert!(!state.model().can(state.company(), &CompanyPermission::ResourceSpecCreate));
+        assert!(member2.can(state.company(), &CompanyPermission::ResourceSpecCreate));
         assert_eq!(member2.updated(), &now2);
 
         let mut state2 = state.clone();
@@ -281,6 +464,303 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn can_set_roles() {
+        let now = util::time::now();
+        let id = MemberID::create();
+        let mut state = TestState::standard(vec![CompanyPermission::MemberCreate, CompanyPermission::MemberSetPermissions], &now);
+        state.company_mut().set_roles(vec![Role::new("coordinator", vec![CompanyPermission::AgreementCreate, CompanyPermission::Transfer])]);
+        let new_user = make_user(&UserID::create(), None, &now);
+        let new_class = MemberClass::Worker(MemberWorker::new(OccupationID::create(), None));
+        let mods = create(state.user(), state.member(), id.clone(), new_user.clone(), state.company().clone(), new_class.clone(), vec![], None, true, &now).unwrap().into_vec();
+        let member = mods[0].clone().expect_op::<Member>(Op::Create).unwrap();
+        state.model = Some(member);
+
+        let now2 = util::time::now();
+        let testfn_inner = |state: &TestState<Member, Member>, roles: Vec<String>| {
+            set_roles(state.user(), state.member(), state.company(), state.model().clone(), roles, &now2)
+        };
+        let testfn = |state: &TestState<Member, Member>| {
+            testfn_inner(state, vec!["coordinator".into()])
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let member2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(member2.roles(), &vec!["coordinator".to_string()]);
+        assert_eq!(member2.permissions().len(), 0);
+        assert!(!state.model().can(state.company(), &CompanyPermission::Transfer));
+        assert!(member2.can(state.company(), &CompanyPermission::Transfer));
+        assert_eq!(member2.updated(), &now2);
+
+        let res = testfn_inner(&state, vec!["coordinator".into(), "janitor".into()]);
+        assert_eq!(res, Err(Error::CompanyRoleNotFound("janitor".into())));
+
+        let mut state2 = state.clone();
+        state2.model_mut().inner_mut().set_object(CompanyID::create().into());
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::WrongCompany);
+    }
+
+    #[test]
+    fn can_delegate() {
+        let now: DateTime<Utc> = "2020-06-01T00:00:00Z".parse().unwrap();
+        let start: DateTime<Utc> = "2020-06-08T00:00:00Z".parse().unwrap();
+        let expires: DateTime<Utc> = "2020-06-22T00:00:00Z".parse().unwrap();
+        let mut state = TestState::standard(vec![CompanyPermission::MemberCreate, CompanyPermission::AgreementCreate, CompanyPermission::Transfer], &now);
+        let new_user = make_user(&UserID::create(), None, &now);
+        let new_class = MemberClass::Worker(MemberWorker::new(OccupationID::create(), None));
+        let mods = create(state.user(), state.member(), MemberID::create(), new_user.clone(), state.company().clone(), new_class.clone(), vec![], None, true, &now).unwrap().into_vec();
+        let colleague = mods[0].clone().expect_op::<Member>(Op::Create).unwrap();
+        state.model = Some(colleague);
+
+        let testfn_inner = |state: &TestState<Member, Member>, permissions: Vec<CompanyPermission>| {
+            delegate(state.user(), state.member(), state.company(), state.model().clone(), permissions, start, expires, &now)
+        };
+        let testfn = |state: &TestState<Member, Member>| {
+            testfn_inner(state, vec![CompanyPermission::AgreementCreate, CompanyPermission::Transfer])
+        };
+        test::standard_transaction_tests(&state, &testfn);
+        test::double_deleted_tester(&state, "member", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let colleague2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(colleague2.delegations(), &vec![Delegation::new(state.member().id().clone(), vec![CompanyPermission::AgreementCreate, CompanyPermission::Transfer], start, expires)]);
+        assert_eq!(colleague2.updated(), &now);
+
+        // the delegation isn't honored until it's been checked against the
+        // grantor's member record, and then only while it's in effect
+        let user_id = new_user.id();
+        let during: DateTime<Utc> = "2020-06-10T00:00:00Z".parse().unwrap();
+        let unverified = colleague2.clone();
+        assert!(unverified.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_err());
+        let mut colleague2 = colleague2;
+        colleague2.verify_delegations(state.company(), &[state.member().clone()]);
+        assert!(colleague2.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_ok());
+        assert!(colleague2.access_check(user_id, state.company(), CompanyPermission::MemberCreate, &during).is_err());
+        assert!(colleague2.access_check(user_id, state.company(), CompanyPermission::Transfer, &now).is_err());
+        assert!(colleague2.access_check(user_id, state.company(), CompanyPermission::Transfer, &expires).is_err());
+
+        // verification isn't stored
+        let loaded: Member = serde_json::from_str(&serde_json::to_string(&colleague2).unwrap()).unwrap();
+        assert!(loaded.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_err());
+
+        // the grantor has to still hold the permission, and still be an active
+        // member
+        let verified = |grantors: Vec<Member>| {
+            let mut colleague = unverified.clone();
+            colleague.verify_delegations(state.company(), &grantors);
+            colleague
+        };
+        let mut grantor = state.member().clone();
+        grantor.set_permissions(vec![CompanyPermission::AgreementCreate]);
+        let colleague = verified(vec![grantor]);
+        assert!(colleague.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_err());
+        assert!(colleague.access_check(user_id, state.company(), CompanyPermission::AgreementCreate, &during).is_ok());
+        let mut grantor = state.member().clone();
+        grantor.set_active(false);
+        let colleague = verified(vec![grantor]);
+        assert!(colleague.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_err());
+        let mut grantor = state.member().clone();
+        grantor.set_deleted(Some(now));
+        let colleague = verified(vec![grantor]);
+        assert!(colleague.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_err());
+        let colleague = verified(vec![]);
+        assert!(colleague.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_err());
+
+        // can't delegate what we don't have
+        let res = testfn_inner(&state, vec![CompanyPermission::Transfer, CompanyPermission::CompanyDelete]);
+        expect_denied(res, DenyReason::MissingPermission);
+        let res = testfn_inner(&state, vec![]);
+        assert_eq!(res, Err(Error::DelegationInvalid));
+
+        // delegated permissions can't be passed on
+        let mut state2 = state.clone();
+        state2.user = Some(new_user.clone());
+        state2.member = Some(colleague2.clone());
+        state2.model = Some(state.member().clone());
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+
+        // can't delegate to ourselves
+        let mut state3 = state.clone();
+        state3.model = Some(state.member().clone());
+        let res = testfn(&state3);
+        assert_eq!(res, Err(Error::DelegationInvalid));
+
+        let res = delegate(state.user(), state.member(), state.company(), state.model().clone(), vec![CompanyPermission::Transfer], expires, start, &now);
+        assert_eq!(res, Err(Error::DelegationInvalid));
+
+        // a new delegation from the same member replaces the old one
+        let mut state4 = state.clone();
+        state4.model = Some(colleague2.clone());
+        let mods = testfn_inner(&state4, vec![CompanyPermission::AgreementCreate]).unwrap().into_vec();
+        let mut colleague3 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        colleague3.verify_delegations(state.company(), &[state.member().clone()]);
+        assert_eq!(colleague3.delegations().len(), 1);
+        assert!(colleague3.access_check(user_id, state.company(), CompanyPermission::Transfer, &during).is_err());
+        assert!(colleague3.access_check(user_id, state.company(), CompanyPermission::AgreementCreate, &during).is_ok());
+    }
+
+    #[test]
+    fn can_revoke_delegation() {
+        let now: DateTime<Utc> = "2020-06-01T00:00:00Z".parse().unwrap();
+        let start: DateTime<Utc> = "2020-06-01T00:00:00Z".parse().unwrap();
+        let expires: DateTime<Utc> = "2020-06-22T00:00:00Z".parse().unwrap();
+        let mut state = TestState::standard(vec![CompanyPermission::MemberCreate, CompanyPermission::Transfer], &now);
+        let new_user = make_user(&UserID::create(), None, &now);
+        let new_class = MemberClass::Worker(MemberWorker::new(OccupationID::create(), None));
+        let mods = create(state.user(), state.member(), MemberID::create(), new_user.clone(), state.company().clone(), new_class.clone(), vec![], None, true, &now).unwrap().into_vec();
+        let colleague = mods[0].clone().expect_op::<Member>(Op::Create).unwrap();
+        state.model = Some(colleague);
+        let mods = delegate(state.user(), state.member(), state.company(), state.model().clone(), vec![CompanyPermission::Transfer], start, expires, &now).unwrap().into_vec();
+        let mut colleague2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        colleague2.verify_delegations(state.company(), &[state.member().clone()]);
+        assert!(colleague2.access_check(new_user.id(), state.company(), CompanyPermission::Transfer, &now).is_ok());
+        state.model = Some(colleague2);
+
+        let now2: DateTime<Utc> = "2020-06-05T00:00:00Z".parse().unwrap();
+        let testfn_inner = |state: &TestState<Member, Member>, grantor: &MemberID| {
+            revoke_delegation(state.user(), state.member(), state.company(), state.model().clone(), grantor, &now2)
+        };
+        let testfn = |state: &TestState<Member, Member>| {
+            testfn_inner(state, state.member().id())
+        };
+        test::deleted_company_tester(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let colleague3 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(colleague3.delegations().len(), 0);
+        assert_eq!(colleague3.updated(), &now2);
+        assert!(colleague3.access_check(new_user.id(), state.company(), CompanyPermission::Transfer, &now2).is_err());
+
+        let mut state2 = state.clone();
+        state2.model = Some(colleague3);
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::DelegationNotFound));
+
+        // only the grantor can revoke
+        let mut state3 = state.clone();
+        state3.user_mut().set_id(UserID::create());
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::WrongUser);
+
+        // ...unless the caller can set members' permissions, which lets them
+        // clean up delegations from members who have since been deleted
+        let gone = MemberID::create();
+        let mut state4 = state.clone();
+        state4.model_mut().delegate(Delegation::new(gone.clone(), vec![CompanyPermission::Transfer], start, expires));
+        let res = testfn_inner(&state4, &gone);
+        expect_denied(res, DenyReason::MissingPermission);
+        state4.member_mut().set_permissions(vec![CompanyPermission::MemberSetPermissions]);
+        let mods = testfn_inner(&state4, &gone).unwrap().into_vec();
+        let colleague4 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(colleague4.delegations().len(), 1);
+        assert_eq!(colleague4.delegations()[0].grantor(), state.member().id());
+        let record = mods[1].clone().expect_op::<AuditRecord>(Op::Create).unwrap();
+        assert!(record.permissions().contains(&CompanyPermission::MemberSetPermissions.into()));
+    }
+
+    #[test]
+    fn can_delegate_vote() {
+        let now = util::time::now();
+        let state: TestState<Member, Member> = TestState::standard(vec![], &now);
+        let company_id = state.company().id().clone();
+        let colleagues = (0..2)
+            .map(|_| make_member_worker(&MemberID::create(), &UserID::create(), &company_id, &OccupationID::create(), vec![], &now))
+            .collect::<Vec<_>>();
+        let mut members = colleagues.clone();
+        members.push(state.member().clone());
+
+        let testfn_inner = |state: &TestState<Member, Member>, delegate: &Member, topic: Option<&str>, members: &Vec<Member>| {
+            delegate_vote(state.user(), state.member(), state.company(), delegate, topic.map(|topic| topic.into()), members, &now)
+        };
+        let testfn = |state: &TestState<Member, Member>| {
+            testfn_inner(state, &colleagues[0], None, &members)
+        };
+        test::deleted_company_tester(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let member2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(member2.id(), state.member().id());
+        assert_eq!(member2.vote_proxies(), &vec![VoteProxy::new(colleagues[0].id().clone(), None)]);
+        assert_eq!(member2.updated(), &now);
+
+        // topic delegations sit alongside the general one, and replace any
+        // previous delegation for the same topic
+        let mut state2 = state.clone();
+        state2.member = Some(member2.clone());
+        let mods = testfn_inner(&state2, &colleagues[1], Some("budget"), &members).unwrap().into_vec();
+        let member3 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(member3.vote_delegate(None), Some(colleagues[0].id()));
+        assert_eq!(member3.vote_delegate(Some(&"budget".into())), Some(colleagues[1].id()));
+        assert_eq!(member3.vote_delegate(Some(&"parks".into())), Some(colleagues[0].id()));
+        state2.member = Some(member3.clone());
+        let mods = testfn_inner(&state2, &colleagues[0], Some("budget"), &members).unwrap().into_vec();
+        let member4 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(member4.vote_proxies().len(), 2);
+        assert_eq!(member4.vote_delegate(Some(&"budget".into())), Some(colleagues[0].id()));
+
+        // no delegating to ourselves, or to someone outside the company
+        let res = testfn_inner(&state, state.member(), None, &members);
+        assert_eq!(res, Err(Error::VoteDelegationInvalid));
+        let outsider = make_member_worker(&MemberID::create(), &UserID::create(), &CompanyID::create(), &OccupationID::create(), vec![], &now);
+        let res = testfn_inner(&state, &outsider, None, &members);
+        assert_eq!(res, Err(Error::VoteDelegationInvalid));
+
+        // no loops, including ones that only happen for a specific topic
+        let mut members2 = colleagues.clone();
+        members2[1].set_vote_proxies(vec![VoteProxy::new(colleagues[0].id().clone(), None)]);
+        members2[0].set_vote_proxies(vec![VoteProxy::new(state.member().id().clone(), Some("budget".into()))]);
+        members2.push(state.member().clone());
+        let res = testfn_inner(&state, &colleagues[1], Some("budget"), &members2);
+        assert_eq!(res, Err(Error::VoteDelegationCycle));
+        let res = testfn_inner(&state, &colleagues[1], None, &members2);
+        assert_eq!(res, Err(Error::VoteDelegationCycle));
+        assert!(testfn_inner(&state, &colleagues[1], Some("parks"), &members2).is_ok());
+
+        let mut state3 = state.clone();
+        state3.user_mut().set_id(UserID::create());
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::WrongUser);
+        let mut state4 = state.clone();
+        state4.user_mut().set_roles(vec![]);
+        let res = testfn(&state4);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_revoke_vote_delegation() {
+        let now = util::time::now();
+        let mut state: TestState<Member, Member> = TestState::standard(vec![], &now);
+        let delegate_id = MemberID::create();
+        state.member_mut().set_vote_proxies(vec![VoteProxy::new(delegate_id.clone(), None), VoteProxy::new(delegate_id.clone(), Some("budget".into()))]);
+
+        let testfn = |state: &TestState<Member, Member>| {
+            revoke_vote_delegation(state.user(), state.member(), state.company(), Some("budget".into()), &now)
+        };
+        test::deleted_company_tester(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let member2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
+        assert_eq!(member2.vote_proxies(), &vec![VoteProxy::new(delegate_id.clone(), None)]);
+
+        let mut state2 = state.clone();
+        state2.member = Some(member2);
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::DelegationNotFound));
+
+        let mut state3 = state.clone();
+        state3.user_mut().set_id(UserID::create());
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::WrongUser);
+    }
+
     #[test]
     fn can_set_compensation() {
         let now = util::time::now();
@@ -301,7 +781,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let member2 = mods[0].clone().expect_op::<Member>(Op::Update).unwrap();
         assert_eq!(state.model().compensation(), None);
         assert_eq!(member2.compensation().unwrap().wage(), &Measure::new(dec!(32), Unit::Hour));
@@ -337,7 +817,7 @@ mod tests {
         test::double_deleted_tester(&state, "member", &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let member2 = mods[0].clone().expect_op::<Member>(Op::Delete).unwrap();
         assert_eq!(member2.deleted(), &Some(now2.clone()));
         assert!(member2.is_deleted());
@@ -23,18 +23,22 @@ pub enum OrderAction {
     TransferCustody,
 }
 
-//pub mod account;
+pub mod account;
 pub mod agreement;
+pub mod ballot;
 pub mod commitment;
 pub mod company;
 pub mod member;
-//pub mod currency;
+pub mod currency;
 pub mod event;
 pub mod intent;
 pub mod occupation;
+pub mod payroll;
 pub mod process;
 pub mod process_spec;
+pub mod proposal;
 pub mod resource;
+pub mod resource_group;
 pub mod resource_spec;
 pub mod user;
 
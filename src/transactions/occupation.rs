@@ -36,12 +36,14 @@ pub fn create<T: Into<String>>(caller: &User, id: OccupationID, label: T, note:
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("occupation::create", caller, None, None, vec![Permission::OccupationCreate.into()], now)
 }
 
 /// Update an existing `Occupation`
 pub fn update(caller: &User, mut subject: Occupation, label: Option<String>, note: Option<String>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::OccupationUpdate)?;
+    let original = subject.clone();
     if let Some(label) = label {
         subject.inner_mut().set_role_label(label);
     }
@@ -52,7 +54,8 @@ pub fn update(caller: &User, mut subject: Occupation, label: Option<String>, not
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("occupation::update", caller, None, None, vec![Permission::OccupationUpdate.into()], now)
 }
 
 /// Delete an `Occupation`
@@ -62,14 +65,15 @@ pub fn delete(caller: &User, mut subject: Occupation, now: &DateTime<Utc>) -> Re
         Err(Error::ObjectIsDeleted("occupation".into()))?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("occupation::delete", caller, None, None, vec![Permission::OccupationDelete.into()], now)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        access::Role,
+        access::{DenyReason, Role},
         models::{
             Op,
 
@@ -90,7 +94,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let occupation = mods[0].clone().expect_op::<Occupation>(Op::Create).unwrap();
         assert_eq!(occupation.id(), &id);
@@ -101,7 +105,7 @@ mod tests {
         let mut state2 = state.clone();
         state2.user_mut().set_roles(vec![Role::User]);
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
     }
 
     #[test]
@@ -132,7 +136,7 @@ mod tests {
         let mut state2 = state.clone();
         state2.user_mut().set_roles(vec![Role::User]);
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
     }
 
     #[test]
@@ -153,7 +157,7 @@ mod tests {
         test::double_deleted_tester(&state, "occupation", &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let occupation2 = mods[0].clone().expect_op::<Occupation>(Op::Delete).unwrap();
         assert_eq!(occupation2.id(), &id);
         assert_eq!(occupation2.created(), &now);
@@ -162,7 +166,7 @@ mod tests {
         let mut state2 = state.clone();
         state2.user_mut().set_roles(vec![Role::User]);
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
     }
 }
 
@@ -0,0 +1,315 @@
+//! Payroll turns the labor a worker has recorded (via [work events][1]) into
+//! credits paid into the account named by their [compensation][2].
+//!
+//! Payroll is run per-worker, per-period. The period is determined by the
+//! worker's payroll schedule and the pay date given. Hourly workers are paid
+//! for the hours recorded in their work events, and salaried workers are paid
+//! their yearly salary prorated over the days they were members during the
+//! period.
+//!
+//! See the [payroll model.][3]
+//!
+//! [1]: ../event/work/index.html
+//! [2]: ../../models/member/struct.Compensation.html
+//! [3]: ../../models/payroll/index.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    access::{AccessDenied, DenyReason, Permission},
+    costs::Costs,
+    error::{Error, Result},
+    models::{
+        Op,
+        Modifications,
+        account::Account,
+        company::{Company, Permission as CompanyPermission},
+        event::Event,
+        lib::{
+            agent::Agent,
+            basis_model::Model,
+        },
+        member::Member,
+        payroll::{PayPeriod, Payroll, PayrollID},
+        user::User,
+    },
+    util::measure,
+};
+use om2::Unit;
+use rust_decimal::prelude::*;
+use vf_rs::vf;
+
+/// Determines if the given work event should be paid out in the given period.
+///
+/// We only pay for completed work events the worker performed for this company
+/// that started within the period.
+fn is_payable(event: &Event, worker: &Member, company: &Company, period: &PayPeriod) -> bool {
+    event.is_active() &&
+        event.inner().action() == &vf::Action::Work &&
+        event.inner().provider() == &worker.agent_id() &&
+        event.inner().receiver() == &company.agent_id() &&
+        event.inner().has_end().is_some() &&
+        event.inner().has_beginning().as_ref().map(|begin| period.contains(begin)).unwrap_or(false)
+}
+
+/// Run payroll for a worker over the pay period containing `pay_date`.
+///
+/// This takes the worker's work events (any events that aren't the worker's
+/// completed work for this company within the period are ignored), totals the
+/// hours and wage costs recorded, and credits the worker's account with their
+/// pay. A `Payroll` record is created to document the run.
+///
+/// Partial periods are handled by only paying salaried workers for the part
+/// of the period they were members of the company (between the creation and
+/// deletion of the worker's member record). Payroll can't be run until the
+/// period is over, so salaried workers are never paid for days they haven't
+/// worked yet.
+///
+/// Note that because we have no view of storage, it's up to the caller to make
+/// sure payroll is not run twice for the same worker and period.
+pub fn run(caller: &User, member: &Member, company: &Company, id: PayrollID, worker: Member, mut account: Account, events: &[Event], pay_date: &DateTime<Utc>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyPayroll)?;
+    member.access_check(caller.id(), company, CompanyPermission::Payroll, now)?;
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if company.id() != &worker.company_id()? {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    let compensation = worker.compensation().ok_or(Error::MemberMustBeWorker)?.clone();
+    if account.id() != compensation.pay_into() {
+        Err(Error::AccountMismatch)?;
+    }
+    if worker.member_id() != &account.user_id().clone().into() {
+        // only pay into the worker's own account
+        Err(Error::AccountMismatch)?;
+    }
+    if !account.is_active() {
+        Err(Error::ObjectIsInactive("account".into()))?;
+    }
+
+    let period = PayPeriod::from_schedule(compensation.schedule(), pay_date)?;
+    if period.end() > now {
+        Err(Error::PayPeriodNotEnded)?;
+    }
+    let payable = events.iter()
+        .filter(|event| is_payable(event, &worker, company, &period))
+        .collect::<Vec<_>>();
+    let mut hours = Decimal::zero();
+    let mut costs = Costs::new();
+    for event in &payable {
+        if let Some(effort) = event.inner().effort_quantity().as_ref() {
+            if effort.has_unit() != &Unit::Hour {
+                Err(Error::MeasureUnitsMismatched)?;
+            }
+            hours += measure::to_decimal(effort)?;
+        }
+        if let Some(move_costs) = event.move_costs().as_ref() {
+            costs = costs + move_costs.clone();
+        }
+    }
+
+    let wage = measure::to_decimal(compensation.wage())?;
+    let amount = match compensation.wage().has_unit() {
+        Unit::Hour => hours * wage,
+        Unit::Year => {
+            // clamp the period to the dates the worker was a member
+            let employed_until = worker.deleted().unwrap_or(*period.end());
+            match period.clamp(worker.created(), &employed_until) {
+                Some(employed) => employed.prorate_yearly(wage)?,
+                None => Decimal::zero(),
+            }
+        }
+        _ => Err(Error::MeasureUnitsMismatched)?,
+    };
+
+    let original = account.clone();
+    let balance = *account.balance() + amount;
+    account.set_balance(balance);
+    account.set_updated(*now);
+
+    let model = Payroll::builder()
+        .id(id)
+        .member_id(worker.id().clone())
+        .company_id(company.id().clone())
+        .account_id(account.id().clone())
+        .period(period)
+        .events(payable.iter().map(|x| x.id().clone()).collect::<Vec<_>>())
+        .hours(hours)
+        .costs(costs)
+        .amount(amount)
+        .active(true)
+        .created(*now)
+        .updated(*now)
+        .build()
+        .map_err(Error::BuilderFailed)?;
+    let mut mods = Modifications::new();
+    mods.push(Op::Create, model);
+    mods.push_update(original, account);
+    mods.audit("payroll::run", caller, Some(member), Some(company.id()), vec![Permission::CompanyPayroll.into(), CompanyPermission::Payroll.into()], now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{
+            account::AccountID,
+            event::EventID,
+            member::*,
+            process::ProcessID,
+            user::UserID,
+        },
+        transactions::event::work,
+        util::test::{self, *},
+    };
+    use rust_decimal_macros::*;
+
+    fn date(val: &str) -> DateTime<Utc> {
+        val.parse().unwrap()
+    }
+
+    fn make_account(id: &AccountID, user_id: &UserID, now: &DateTime<Utc>) -> Account {
+        Account::builder()
+            .id(id.clone())
+            .user_id(user_id.clone())
+            .name("checking")
+            .description("my checking account")
+            .balance(dec!(10))
+            .active(true)
+            .created(*now)
+            .updated(*now)
+            .build().unwrap()
+    }
+
+    fn make_work(state: &TestState<Member, Account>, begin: &str, end: &str, wage: Option<Decimal>) -> Event {
+        let process = make_process(&ProcessID::create(), state.company().id(), "make widgets", &Costs::new(), &date(begin));
+        let mods = work::work(state.user(), state.member(), state.company(), EventID::create(), state.model().clone(), process, wage, date(begin), date(end), None, &date(end)).unwrap().into_vec();
+        mods[0].clone().expect_op::<Event>(Op::Create).unwrap()
+    }
+
+    fn set_compensation(member: &mut Member, compensation: Compensation) {
+        match member.class_mut() {
+            MemberClass::Worker(worker) => { worker.set_compensation(Some(compensation)); }
+            _ => panic!("payroll::tests::set_compensation() -- bad class"),
+        }
+    }
+
+    #[test]
+    fn can_run_hourly() {
+        let now = date("2020-01-01T00:00:00Z");
+        let mut state = TestState::standard(vec![CompanyPermission::Payroll, CompanyPermission::Work], &now);
+        let account = make_account(&AccountID::create(), state.user().id(), &now);
+        set_compensation(state.member_mut(), Compensation::new_hourly(dec!(20), account.id().clone()));
+        state.model = Some(state.member().clone());
+        state.model2 = Some(account);
+
+        let events = vec![
+            make_work(&state, "2020-02-24T08:00:00Z", "2020-02-24T16:00:00Z", Some(dec!(160))),
+            make_work(&state, "2020-02-29T08:00:00Z", "2020-02-29T12:30:00Z", Some(dec!(90))),
+            // outside of the period
+            make_work(&state, "2020-03-09T08:00:00Z", "2020-03-09T16:00:00Z", Some(dec!(160))),
+        ];
+        let id = PayrollID::create();
+        let pay_date = date("2020-03-06T00:00:00Z");
+        let now2 = date("2020-03-09T00:00:00Z");
+
+        let testfn = |state: &TestState<Member, Account>| {
+            run(state.user(), state.member(), state.company(), id.clone(), state.model().clone(), state.model2().clone(), &events, &pay_date, &now2)
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 3);
+        let payroll = mods[0].clone().expect_op::<Payroll>(Op::Create).unwrap();
+        let account = mods[1].clone().expect_op::<Account>(Op::Update).unwrap();
+
+        let occupation_id = state.member().occupation_id().unwrap().clone();
+        assert_eq!(payroll.id(), &id);
+        assert_eq!(payroll.member_id(), state.member().id());
+        assert_eq!(payroll.company_id(), state.company().id());
+        assert_eq!(payroll.account_id(), state.model2().id());
+        assert_eq!(payroll.period(), &PayPeriod::new(date("2020-02-24T00:00:00Z"), date("2020-03-09T00:00:00Z")));
+        assert_eq!(payroll.events(), &vec![events[0].id().clone(), events[1].id().clone()]);
+        assert_eq!(payroll.hours(), &dec!(12.5));
+        assert_eq!(payroll.costs(), &Costs::new_with_labor(occupation_id.clone(), dec!(250)));
+        assert_eq!(payroll.amount(), &dec!(250));
+        assert_eq!(payroll.created(), &now2);
+        assert_eq!(account.balance(), &dec!(260));
+        assert_eq!(account.updated(), &now2);
+
+        // can't pay into an account that isn't in our compensation
+        let mut state2 = state.clone();
+        state2.model2_mut().set_id(AccountID::create());
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::AccountMismatch));
+
+        // can't pay into an account the worker doesn't own
+        let mut state3 = state.clone();
+        state3.model2_mut().set_user_id(UserID::create());
+        let res = testfn(&state3);
+        assert_eq!(res, Err(Error::AccountMismatch));
+
+        let mut state4 = state.clone();
+        state4.model2_mut().set_active(false);
+        let res = testfn(&state4);
+        assert_eq!(res, Err(Error::ObjectIsInactive("account".into())));
+
+        // workers need compensation
+        let mut state5 = state.clone();
+        state5.model_mut().set_class(MemberClass::Worker(MemberWorker::new("machinist", None)));
+        let res = testfn(&state5);
+        assert_eq!(res, Err(Error::MemberMustBeWorker));
+
+        // can't run payroll for a worker at another company
+        let mut state6 = state.clone();
+        state6.model_mut().inner_mut().set_object(crate::models::company::CompanyID::create().into());
+        let res = testfn(&state6);
+        expect_denied(res, DenyReason::WrongCompany);
+    }
+
+    #[test]
+    fn can_run_salary() {
+        let hired = date("2020-02-20T00:00:00Z");
+        let mut state = TestState::standard(vec![CompanyPermission::Payroll, CompanyPermission::Work], &hired);
+        let account = make_account(&AccountID::create(), state.user().id(), &hired);
+        set_compensation(state.member_mut(), Compensation::new_salary(dec!(73200), account.id().clone(), dec!(40)));
+        state.model = Some(state.member().clone());
+        state.model2 = Some(account);
+
+        let events = vec![
+            make_work(&state, "2020-02-24T08:00:00Z", "2020-02-24T16:00:00Z", None),
+        ];
+        let now2 = date("2020-03-01T00:00:00Z");
+
+        // our worker was hired on the 20th so only gets paid for 10 of the 14
+        // days in the period. this is a leap year, so a day's pay is 1/366 of
+        // the salary.
+        let mods = run(state.user(), state.member(), state.company(), PayrollID::create(), state.model().clone(), state.model2().clone(), &events, &date("2020-02-29T00:00:00Z"), &now2).unwrap().into_vec();
+        let payroll = mods[0].clone().expect_op::<Payroll>(Op::Create).unwrap();
+        let account = mods[1].clone().expect_op::<Account>(Op::Update).unwrap();
+        assert_eq!(payroll.period(), &PayPeriod::new(date("2020-02-16T00:00:00Z"), date("2020-03-01T00:00:00Z")));
+        assert_eq!(payroll.hours(), &dec!(8));
+        assert_eq!(payroll.amount(), &dec!(2000));
+        assert_eq!(account.balance(), &dec!(2010));
+
+        // no paying for days that haven't happened yet
+        let res = run(state.user(), state.member(), state.company(), PayrollID::create(), state.model().clone(), state.model2().clone(), &events, &date("2020-02-29T00:00:00Z"), &date("2020-02-29T00:00:00Z"));
+        assert_eq!(res, Err(Error::PayPeriodNotEnded));
+
+        // a deleted worker is only paid up until they were deleted
+        let mut state2 = state.clone();
+        state2.model_mut().set_deleted(Some(date("2020-02-25T00:00:00Z")));
+        let mods = run(state2.user(), state2.member(), state2.company(), PayrollID::create(), state2.model().clone(), state2.model2().clone(), &events, &date("2020-02-29T00:00:00Z"), &now2).unwrap().into_vec();
+        let payroll = mods[0].clone().expect_op::<Payroll>(Op::Create).unwrap();
+        assert_eq!(payroll.amount(), &dec!(1000));
+
+        // a full period in a non-leap year
+        let mut state3 = state.clone();
+        state3.model_mut().set_created(date("2019-01-01T00:00:00Z"));
+        let mods = run(state3.user(), state3.member(), state3.company(), PayrollID::create(), state3.model().clone(), state3.model2().clone(), &[], &date("2019-02-01T00:00:00Z"), &now2).unwrap().into_vec();
+        let payroll = mods[0].clone().expect_op::<Payroll>(Op::Create).unwrap();
+        assert_eq!(payroll.hours(), &dec!(0));
+        assert_eq!(payroll.events(), &vec![]);
+        assert_eq!(payroll.amount(), &((dec!(73200) * dec!(15)) / dec!(365)));
+    }
+}
@@ -19,7 +19,7 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, Acl, DenyReason, Permission},
     costs::Costs,
     error::{Error, Result},
     models::{
@@ -42,7 +42,7 @@ use vf_rs::vf;
 /// Create a new process
 pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company, id: ProcessID, spec_id: ProcessSpecID, name: T, note: T, classifications: Vec<Url>, has_beginning: Option<DateTime<Utc>>, has_end: Option<DateTime<Utc>>, in_scope_of: Vec<AgentID>, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateProcesses)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ProcessCreate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ProcessCreate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -67,16 +67,19 @@ pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("process::create", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateProcesses.into(), CompanyPermission::ProcessCreate.into()], now)
 }
 
 /// Update a process
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Process, name: Option<String>, note: Option<String>, classifications: Option<Vec<Url>>, finished: Option<bool>, has_beginning: Option<DateTime<Utc>>, has_end: Option<DateTime<Utc>>, in_scope_of: Option<Vec<AgentID>>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateProcesses)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ProcessUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ProcessUpdate, now)?;
+    member.acl_check(subject.acl(), &CompanyPermission::ProcessUpdate)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
+    let original = subject.clone();
     if let Some(name) = name {
         subject.inner_mut().set_name(name);
     }
@@ -102,13 +105,39 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Pr
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("process::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateProcesses.into(), CompanyPermission::ProcessUpdate.into()], now)
+}
+
+/// Set (or clear) the ACL restricting which members can act on a process
+///
+/// Changing an ACL is itself restricted by the object's current ACL.
+pub fn set_acl(caller: &User, member: &Member, company: &Company, mut subject: Process, acl: Option<Acl>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateProcesses)?;
+    member.access_check(caller.id(), company, CompanyPermission::ProcessUpdate, now)?;
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if subject.company_id() != company.id() {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("process".into()))?;
+    }
+    // the current ACL decides who gets to change it
+    member.acl_check(subject.acl(), &CompanyPermission::ProcessUpdate)?;
+    let original = subject.clone();
+    subject.set_acl(acl);
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("process::set_acl", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateProcesses.into(), CompanyPermission::ProcessUpdate.into()], now)
 }
 
 /// Delete a process
 pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Process, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateProcesses)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ProcessDelete)?;
+    member.access_check(caller.id(), company, CompanyPermission::ProcessDelete, now)?;
+    member.acl_check(subject.acl(), &CompanyPermission::ProcessDelete)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -119,15 +148,20 @@ pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Pr
         Err(Error::CannotEraseCosts)?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("process::delete", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateProcesses.into(), CompanyPermission::ProcessDelete.into()], now)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        access::{AclEntry, AclSubject},
+        costs::Costs,
         models::{
+            company::CompanyID,
             lib::agent::Agent,
+            member::MemberID,
             process_spec::ProcessSpecID,
         },
         util::{self, test::{self, *}},
@@ -146,7 +180,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let process = mods[0].clone().expect_op::<Process>(Op::Create).unwrap();
         assert_eq!(process.id(), &id);
@@ -182,8 +216,16 @@ mod tests {
         };
         test::standard_transaction_tests(&state, &testfn);
 
+        // the process's ACL can keep members out
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::ProcessUpdate])])));
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::ObjectAcl);
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(state.member().id().clone()), vec![CompanyPermission::ProcessUpdate])])));
+        assert!(testfn(&state2).is_ok());
+
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let process2 = mods[0].clone().expect_op::<Process>(Op::Update).unwrap();
         assert_eq!(process2.id(), &id);
@@ -202,6 +244,41 @@ mod tests {
         assert_eq!(process2.deleted(), &None);
     }
 
+    #[test]
+    fn can_set_acl() {
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![CompanyPermission::ProcessUpdate], &now);
+        state.model = Some(make_process(&ProcessID::create(), state.company().id(), "make widgets", &Costs::new(), &now));
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Member(state.member().id().clone()), vec![CompanyPermission::Work])]);
+
+        let now2 = util::time::now();
+        let testfn = |state: &TestState<Process, Process>| {
+            set_acl(state.user(), state.member(), state.company(), state.model().clone(), Some(acl.clone()), &now2)
+        };
+        test::standard_transaction_tests(&state, &testfn);
+        test::double_deleted_tester(&state, "process", &testfn);
+
+        let mut state2 = state.clone();
+        state2.model_mut().set_company_id(CompanyID::create());
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::WrongCompany);
+
+        // members the current ACL doesn't let update the process can't change its ACL
+        let mut state3 = state.clone();
+        state3.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::ProcessUpdate])])));
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::ObjectAcl);
+        state3.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(state.member().id().clone()), vec![CompanyPermission::ProcessUpdate])])));
+        assert!(testfn(&state3).is_ok());
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let process2 = mods[0].clone().expect_op::<Process>(Op::Update).unwrap();
+        assert_eq!(process2.id(), state.model().id());
+        assert_eq!(process2.acl(), &Some(acl.clone()));
+        assert_eq!(process2.updated(), &now2);
+    }
+
     #[test]
     fn can_delete() {
         let now = util::time::now();
@@ -220,8 +297,16 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
         test::double_deleted_tester(&state, "process", &testfn);
 
+        // the process's ACL can keep members out
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::ProcessDelete])])));
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::ObjectAcl);
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(state.member().id().clone()), vec![CompanyPermission::ProcessDelete])])));
+        assert!(testfn(&state2).is_ok());
+
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let process2 = mods[0].clone().expect_op::<Process>(Op::Delete).unwrap();
         assert_eq!(process2.id(), &id);
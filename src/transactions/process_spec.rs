@@ -28,7 +28,7 @@ use vf_rs::vf;
 /// Create a new ProcessSpec
 pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company, id: ProcessSpecID, name: T, note: T, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateProcessSpecs)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ProcessSpecCreate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ProcessSpecCreate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -47,16 +47,18 @@ pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("process_spec::create", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateProcessSpecs.into(), CompanyPermission::ProcessSpecCreate.into()], now)
 }
 
 /// Update a resource spec
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: ProcessSpec, name: Option<String>, note: Option<String>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateProcessSpecs)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ProcessSpecUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ProcessSpecUpdate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
+    let original = subject.clone();
     if let Some(name) = name {
         subject.inner_mut().set_name(name);
     }
@@ -67,13 +69,14 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Pr
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("process_spec::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateProcessSpecs.into(), CompanyPermission::ProcessSpecUpdate.into()], now)
 }
 
 /// Delete a resource spec
 pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: ProcessSpec, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateProcessSpecs)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ProcessSpecDelete)?;
+    member.access_check(caller.id(), company, CompanyPermission::ProcessSpecDelete, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -81,7 +84,8 @@ pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Pr
         Err(Error::ObjectIsDeleted("process_spec".into()))?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("process_spec::delete", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateProcessSpecs.into(), CompanyPermission::ProcessSpecDelete.into()], now)
 }
 
 #[cfg(test)]
@@ -105,7 +109,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let recspec = mods[0].clone().expect_op::<ProcessSpec>(Op::Create).unwrap();
         assert_eq!(recspec.id(), &id);
@@ -133,7 +137,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let procspec2 = mods[0].clone().expect_op::<ProcessSpec>(Op::Update).unwrap();
         assert_eq!(procspec2.id(), &id);
@@ -163,7 +167,7 @@ mod tests {
         test::double_deleted_tester(&state, "process_spec", &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let procspec2 = mods[0].clone().expect_op::<ProcessSpec>(Op::Delete).unwrap();
         assert_eq!(procspec2.id(), &id);
@@ -0,0 +1,376 @@
+//! Proposals let users (or a company's members) vote on running a transaction.
+//! Once the voting period ends, closing the proposal tallies the ballots and,
+//! if it passed, runs the proposed transaction as a [Vote] user.
+//!
+//! See the [proposal model.][1]
+//!
+//! [Vote]: ../../system/vote/struct.Vote.html
+//! [1]: ../../models/proposal/index.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    access::{AccessDenied, DenyReason, Permission, RequiredPermission},
+    error::{Error, Result},
+    models::{
+        Op,
+        Modifications,
+        audit_record::AuditRecord,
+        ballot::Ballot,
+        company::{Company, Permission as CompanyPermission},
+        lib::{
+            agent::AgentID,
+            basis_model::Model,
+        },
+        member::{Member, MemberID},
+        proposal::{Electorate, Proposal, ProposalID, ProposalScope, ProposalStatus, VoteRules},
+        user::{User, UserID},
+    },
+    system::vote::Vote,
+};
+
+/// Create a new proposal. Passing a company (and the caller's member record in
+/// that company) creates a proposal voted on by the company's members,
+/// otherwise the proposal is systemic.
+pub fn create<T: Into<String>>(caller: &User, member: Option<&Member>, company: Option<&Company>, id: ProposalID, title: T, description: Option<String>, topic: Option<String>, transaction: T, payload: T, rules: VoteRules, closes: DateTime<Utc>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ProposalCreate)?;
+    let mut permissions: Vec<RequiredPermission> = vec![Permission::ProposalCreate.into()];
+    let scope = match company {
+        Some(company) => {
+            let member = member.ok_or(AccessDenied::new(DenyReason::MissingPermission, Some(CompanyPermission::ProposalCreate.into()), Some(caller.id().clone().into())))?;
+            member.access_check(caller.id(), company, CompanyPermission::ProposalCreate, now)?;
+            if !company.is_active() {
+                Err(Error::ObjectIsInactive("company".into()))?;
+            }
+            permissions.push(CompanyPermission::ProposalCreate.into());
+            ProposalScope::Company(company.id().clone())
+        }
+        None => ProposalScope::Systemic,
+    };
+    if !rules.is_valid() || &closes <= now {
+        Err(Error::ProposalInvalid)?;
+    }
+    let model = Proposal::builder()
+        .id(id)
+        .scope(scope)
+        .title(title)
+        .description(description)
+        .topic(topic)
+        .transaction(transaction)
+        .payload(payload)
+        .rules(rules)
+        .closes(closes)
+        .status(ProposalStatus::Open)
+        .active(true)
+        .created(*now)
+        .updated(*now)
+        .build()
+        .map_err(Error::BuilderFailed)?;
+    Modifications::new_single(Op::Create, model)
+        .audit("proposal::create", caller, member, company.map(|company| company.id()), permissions, now)
+}
+
+/// Close a proposal whose voting period has ended and tally its ballots
+/// against the given [Electorate][electorate].
+///
+/// Company proposals must be given `Electorate::Members`, so members' vote
+/// delegations are honored. Only the active members of the proposal's company
+/// are counted, however many other member records are passed in. Systemic
+/// proposals must be given `Electorate::Voters` made up of users, and can only
+/// be closed by callers with `Permission::ProposalCloseSystemic`, since we
+/// have no way of checking the voter list ourselves. Anything else returns
+/// `Error::ProposalElectorateInvalid`.
+///
+/// If the proposal passed, a [Vote] is created from `vote_user_id` (and
+/// `vote_member_id`, for company proposals) and handed to `run` along with the
+/// proposal. `run` is expected to deserialize the proposal's payload and run
+/// the proposed transaction as the vote user, returning its modifications,
+/// which are included in the modifications returned here. If `run` runs some
+/// other transaction (or runs it as someone other than the vote user) we
+/// return `Error::ProposalTransactionMismatch`.
+///
+/// Company proposals must be given their company, so we can make sure the
+/// transaction only used permissions whose [bylaws][bylaw] the proposal's
+/// tally satisfies (otherwise we return `Error::BylawNotSatisfied`).
+///
+/// [electorate]: ../../models/proposal/enum.Electorate.html
+/// [bylaw]: ../../models/company/struct.Bylaw.html
+/// [Vote]: ../../system/vote/struct.Vote.html
+pub fn close<F>(caller: &User, mut subject: Proposal, company: Option<&Company>, ballots: &[Ballot], electorate: &Electorate, vote_user_id: UserID, vote_member_id: MemberID, now: &DateTime<Utc>, run: F) -> Result<Modifications>
+    where F: FnOnce(&Vote, &Proposal) -> Result<Modifications>
+{
+    caller.access_check(Permission::ProposalClose)?;
+    let mut permissions: Vec<RequiredPermission> = vec![Permission::ProposalClose.into()];
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("proposal".into()))?;
+    }
+    if subject.status() != &ProposalStatus::Open {
+        Err(Error::ProposalClosed)?;
+    }
+    if now < subject.closes() {
+        Err(Error::ProposalStillOpen)?;
+    }
+    let company_id = match subject.scope() {
+        ProposalScope::Company(company_id) => {
+            if company.map(|company| company.id()) != Some(company_id) {
+                Err(AccessDenied::for_actor(DenyReason::WrongCompany, caller.id().clone()))?;
+            }
+            // tallying members only counts the company's active members
+            if let Electorate::Voters(_) = electorate {
+                Err(Error::ProposalElectorateInvalid)?;
+            }
+            Some(company_id.clone())
+        }
+        ProposalScope::Systemic => {
+            caller.access_check(Permission::ProposalCloseSystemic)?;
+            permissions.push(Permission::ProposalCloseSystemic.into());
+            match electorate {
+                Electorate::Voters(voters) if voters.iter().all(|voter| matches!(voter, AgentID::UserID(_))) => {}
+                _ => Err(Error::ProposalElectorateInvalid)?,
+            }
+            None
+        }
+    };
+    let original = subject.clone();
+    let tally = subject.count_votes(ballots, electorate);
+    let passed = *tally.passed();
+    subject.set_status(if passed { ProposalStatus::Passed } else { ProposalStatus::Failed });
+    subject.set_result(Some(tally));
+    subject.set_updated(*now);
+
+    let mut mods = Modifications::new();
+    if passed {
+        let vote = Vote::from_proposal(&subject, vote_user_id, vote_member_id, now)?;
+        subject.set_vote_user_id(Some(vote.user().id().clone()));
+        let txmods = run(&vote, &subject)?.into_vec();
+        // make sure we ran the transaction that was voted on
+        let record = txmods.last()
+            .and_then(|modification| modification.clone().expect_op::<AuditRecord>(Op::Create).ok())
+            .ok_or(Error::ProposalTransactionMismatch)?;
+        if record.transaction() != subject.transaction() || record.user_id() != vote.user().id() {
+            Err(Error::ProposalTransactionMismatch)?;
+        }
+        // make sure the proposal carried enough votes for any permissions the
+        // company reserves for votes
+        if let (Some(company), Some(tally)) = (company, subject.result()) {
+            let unsatisfied = record.permissions().iter()
+                .filter_map(|permission| match permission {
+                    RequiredPermission::Company(permission) => company.bylaw(permission),
+                    RequiredPermission::System(_) => None,
+                })
+                .any(|bylaw| !bylaw.is_satisfied_by(tally));
+            if unsatisfied {
+                Err(Error::BylawNotSatisfied)?;
+            }
+        }
+        mods.push_update(original, subject);
+        for txmod in txmods {
+            mods.push_raw(txmod);
+        }
+    } else {
+        mods.push_update(original, subject);
+    }
+    mods.audit("proposal::close", caller, None, company_id.as_ref(), permissions, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::Role,
+        models::{
+            ballot::{BallotChoice, BallotID},
+            company::{Bylaw, BylawRequirement, CompanyID},
+            lib::agent::Agent,
+            occupation::OccupationID,
+            proposal::Threshold,
+        },
+        transactions,
+        util::{self, test::{self, *}},
+    };
+    use rust_decimal_macros::*;
+
+    #[test]
+    fn can_create() {
+        let now = util::time::now();
+        let closes = now + chrono::Duration::days(7);
+        let id = ProposalID::create();
+        let state = TestState::standard(vec![CompanyPermission::ProposalCreate], &now);
+
+        let testfn = |state: &TestState<Proposal, Proposal>| {
+            create(state.user(), Some(state.member()), Some(state.company()), id.clone(), "rename the company", None, None, "company::update", r#"{"name":"jerry's WIDGETS"}"#, VoteRules::new(dec!(0.5), Threshold::Majority), closes, &now)
+        };
+        test::standard_transaction_tests(&state, &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let proposal = mods[0].clone().expect_op::<Proposal>(Op::Create).unwrap();
+        assert_eq!(proposal.id(), &id);
+        assert_eq!(proposal.scope(), &ProposalScope::Company(state.company().id().clone()));
+        assert_eq!(proposal.title(), "rename the company");
+        assert_eq!(proposal.transaction(), "company::update");
+        assert_eq!(proposal.closes(), &closes);
+        assert_eq!(proposal.status(), &ProposalStatus::Open);
+        assert_eq!(proposal.result(), &None);
+        assert_eq!(proposal.vote_user_id(), &None);
+        assert!(proposal.is_open_at(&now));
+        assert!(!proposal.is_open_at(&closes));
+
+        // systemic proposals don't need a company
+        let mods = create(state.user(), None, None, id.clone(), "more parks", None, None, "occupation::create", "{}", VoteRules::new(dec!(0.5), Threshold::Majority), closes, &now).unwrap().into_vec();
+        let proposal = mods[0].clone().expect_op::<Proposal>(Op::Create).unwrap();
+        assert_eq!(proposal.scope(), &ProposalScope::Systemic);
+
+        let res = create(state.user(), Some(state.member()), Some(state.company()), id.clone(), "rename the company", None, None, "company::update", "{}", VoteRules::new(dec!(1.5), Threshold::Majority), closes, &now);
+        assert_eq!(res, Err(Error::ProposalInvalid));
+        let res = create(state.user(), Some(state.member()), Some(state.company()), id.clone(), "rename the company", None, None, "company::update", "{}", VoteRules::new(dec!(0.5), Threshold::Majority), now, &now);
+        assert_eq!(res, Err(Error::ProposalInvalid));
+    }
+
+    #[test]
+    fn can_close() {
+        let now = util::time::now();
+        let closes = now + chrono::Duration::days(7);
+        let state: TestState<Proposal, Proposal> = TestState::standard(vec![CompanyPermission::ProposalCreate, CompanyPermission::CompanyUpdate], &now);
+        let company = state.company().clone();
+        let mods = create(state.user(), Some(state.member()), Some(&company), ProposalID::create(), "rename the company", None, None, "company::update", "jerry's WIDGETS", VoteRules::new(dec!(0.5), Threshold::Majority), closes, &now).unwrap().into_vec();
+        let proposal = mods[0].clone().expect_op::<Proposal>(Op::Create).unwrap();
+
+        let mut members = (0..3)
+            .map(|_| make_member_worker(&MemberID::create(), &UserID::create(), company.id(), &OccupationID::create(), vec![], &now))
+            .collect::<Vec<_>>();
+        // members of other companies don't get a say, even if they're passed in
+        let outsider = make_member_worker(&MemberID::create(), &UserID::create(), &CompanyID::create(), &OccupationID::create(), vec![], &now);
+        members.push(outsider.clone());
+        let eligible = Electorate::Members(members.clone());
+        let members = members.iter().map(|member| member.id().clone()).collect::<Vec<_>>();
+        let make_ballot = |voter: &MemberID, choice: BallotChoice| {
+            Ballot::builder()
+                .id(BallotID::create())
+                .proposal_id(proposal.id().clone())
+                .voter(voter.clone())
+                .choice(choice)
+                .active(true)
+                .created(now)
+                .updated(now)
+                .build().unwrap()
+        };
+        let ballots = vec![
+            make_ballot(&members[0], BallotChoice::Yes),
+            make_ballot(&members[1], BallotChoice::Yes),
+            make_ballot(&members[2], BallotChoice::No),
+            make_ballot(outsider.id(), BallotChoice::Yes),
+        ];
+        let vote_user_id = UserID::create();
+        let run = |vote: &Vote, proposal: &Proposal| {
+            transactions::company::update(vote.user(), vote.member().as_ref(), company.clone(), Some(proposal.payload().clone()), None, None, &closes)
+        };
+
+        let res = close(state.user(), proposal.clone(), Some(&company), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &now, run);
+        assert_eq!(res, Err(Error::ProposalStillOpen));
+
+        let mods = close(state.user(), proposal.clone(), Some(&company), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, run).unwrap().into_vec();
+        assert_eq!(mods.len(), 4);
+        let proposal2 = mods[0].clone().expect_op::<Proposal>(Op::Update).unwrap();
+        assert_eq!(proposal2.status(), &ProposalStatus::Passed);
+        assert_eq!(proposal2.result().as_ref().unwrap().yes(), &2);
+        assert_eq!(proposal2.result().as_ref().unwrap().no(), &1);
+        assert_eq!(proposal2.result().as_ref().unwrap().eligible(), &3);
+        assert_eq!(proposal2.vote_user_id(), &Some(vote_user_id.clone()));
+        let company2 = mods[1].clone().expect_op::<Company>(Op::Update).unwrap();
+        assert_eq!(company2.inner().name(), "jerry's WIDGETS");
+        // the transaction was run by the vote user, and we can trace it back
+        // to the proposal
+        let record = mods[2].clone().expect_op::<AuditRecord>(Op::Create).unwrap();
+        assert_eq!(record.transaction(), "company::update");
+        assert_eq!(record.user_id(), &vote_user_id);
+        assert_eq!(record.vote(), &true);
+        let record = mods[3].clone().expect_op::<AuditRecord>(Op::Create).unwrap();
+        assert_eq!(record.transaction(), "proposal::close");
+        assert_eq!(record.user_id(), state.user().id());
+
+        let res = close(state.user(), proposal2.clone(), Some(&company), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, run);
+        assert_eq!(res, Err(Error::ProposalClosed));
+
+        // a failed proposal doesn't run anything
+        let ballots2 = vec![make_ballot(&members[0], BallotChoice::No)];
+        let mods = close(state.user(), proposal.clone(), Some(&company), &ballots2, &eligible, vote_user_id.clone(), MemberID::create(), &closes, |_, _| panic!("ran a failed proposal")).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let proposal3 = mods[0].clone().expect_op::<Proposal>(Op::Update).unwrap();
+        assert_eq!(proposal3.status(), &ProposalStatus::Failed);
+        assert_eq!(proposal3.vote_user_id(), &None);
+
+        // running something other than what was proposed is an error
+        let res = close(state.user(), proposal.clone(), Some(&company), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, |vote, _| {
+            transactions::company::delete(vote.user(), vote.member().as_ref(), company.clone(), &closes)
+        });
+        assert_eq!(res, Err(Error::ProposalTransactionMismatch));
+        let res = close(state.user(), proposal.clone(), Some(&company), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, |_, _| {
+            transactions::company::update(state.user(), Some(state.member()), company.clone(), Some("jerry's WIDGETS".into()), None, None, &closes)
+        });
+        assert_eq!(res, Err(Error::ProposalTransactionMismatch));
+
+        // the company's bylaws must be satisfied by the proposal's votes
+        let run_in = |company: Company| {
+            move |vote: &Vote, proposal: &Proposal| {
+                transactions::company::update(vote.user(), vote.member().as_ref(), company, Some(proposal.payload().clone()), None, None, &closes)
+            }
+        };
+        let mut company2 = company.clone();
+        company2.set_bylaws(vec![Bylaw::new(CompanyPermission::CompanyUpdate, BylawRequirement::Approvals(3))]);
+        let res = close(state.user(), proposal.clone(), Some(&company2), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, run_in(company2.clone()));
+        assert_eq!(res, Err(Error::BylawNotSatisfied));
+        company2.set_bylaws(vec![Bylaw::new(CompanyPermission::CompanyUpdate, BylawRequirement::Vote(VoteRules::new(dec!(0.5), Threshold::Supermajority(dec!(0.66)))))]);
+        assert!(close(state.user(), proposal.clone(), Some(&company2), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, run_in(company2.clone())).is_ok());
+        let res = close(state.user(), proposal.clone(), None, &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, run);
+        expect_denied(res, DenyReason::WrongCompany);
+
+        // company proposals are counted against the company's members, not a
+        // list of voters we're handed
+        let voters = Electorate::Voters(vec![state.member().agent_id()]);
+        let res = close(state.user(), proposal.clone(), Some(&company), &ballots, &voters, vote_user_id.clone(), MemberID::create(), &closes, run);
+        assert_eq!(res, Err(Error::ProposalElectorateInvalid));
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![]);
+        let res = close(state2.user(), proposal.clone(), Some(&company), &ballots, &eligible, vote_user_id.clone(), MemberID::create(), &closes, run);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_close_systemic() {
+        let now = util::time::now();
+        let closes = now + chrono::Duration::days(7);
+        let state: TestState<Proposal, Proposal> = TestState::standard(vec![], &now);
+        let mods = create(state.user(), None, None, ProposalID::create(), "more parks", None, None, "user::update", "{}", VoteRules::new(dec!(0.5), Threshold::Majority), closes, &now).unwrap().into_vec();
+        let proposal = mods[0].clone().expect_op::<Proposal>(Op::Create).unwrap();
+        let ballots = vec![
+            Ballot::builder()
+                .id(BallotID::create())
+                .proposal_id(proposal.id().clone())
+                .voter(state.user().id().clone())
+                .choice(BallotChoice::Yes)
+                .active(true)
+                .created(now)
+                .updated(now)
+                .build().unwrap(),
+        ];
+        let voters = Electorate::Voters(vec![state.user().agent_id()]);
+
+        // regular users can't vouch for who gets to vote on systemic proposals
+        let res = close(state.user(), proposal.clone(), None, &ballots, &voters, UserID::create(), MemberID::create(), &closes, |_, _| panic!("closed a systemic proposal"));
+        expect_denied(res, DenyReason::MissingPermission);
+
+        let admin = make_user(&UserID::create(), Some(vec![Role::SuperAdmin]), &now);
+        let mods = close(&admin, proposal.clone(), None, &[], &voters, UserID::create(), MemberID::create(), &closes, |_, _| panic!("ran a failed proposal")).unwrap().into_vec();
+        let proposal2 = mods[0].clone().expect_op::<Proposal>(Op::Update).unwrap();
+        assert_eq!(proposal2.status(), &ProposalStatus::Failed);
+        assert_eq!(proposal2.result().as_ref().unwrap().eligible(), &1);
+
+        // systemic proposals are voted on by users
+        let members = Electorate::Voters(vec![MemberID::create().into()]);
+        let res = close(&admin, proposal.clone(), None, &ballots, &members, UserID::create(), MemberID::create(), &closes, |_, _| panic!("ran a failed proposal"));
+        assert_eq!(res, Err(Error::ProposalElectorateInvalid));
+        let res = close(&admin, proposal.clone(), None, &ballots, &Electorate::Members(vec![state.member().clone()]), UserID::create(), MemberID::create(), &closes, |_, _| panic!("ran a failed proposal"));
+        assert_eq!(res, Err(Error::ProposalElectorateInvalid));
+    }
+}
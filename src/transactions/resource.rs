@@ -12,7 +12,7 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Permission,
+    access::{AccessDenied, Acl, DenyReason, Permission},
     costs::Costs,
     error::{Error, Result},
     models::{
@@ -36,7 +36,7 @@ use vf_rs::{vf, dfc};
 /// Create a new resource
 pub fn create(caller: &User, member: &Member, company: &Company, id: ResourceID, spec_id: ResourceSpecID, lot: Option<dfc::ProductBatch>, name: Option<String>, tracking_id: Option<String>, classifications: Vec<Url>, note: Option<String>, unit_of_effort: Option<Unit>, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateResources)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ResourceCreate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ResourceCreate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -62,16 +62,19 @@ pub fn create(caller: &User, member: &Member, company: &Company, id: ResourceID,
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("resource::create", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateResources.into(), CompanyPermission::ResourceCreate.into()], now)
 }
 
 /// Update a resource
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Resource, lot: Option<dfc::ProductBatch>, name: Option<String>, tracking_id: Option<String>, classifications: Option<Vec<Url>>, note: Option<String>, unit_of_effort: Option<Unit>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateResources)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ResourceUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ResourceUpdate, now)?;
+    member.acl_check(subject.acl(), &CompanyPermission::ResourceUpdate)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
+    let original = subject.clone();
     if lot.is_some() {
         subject.inner_mut().set_lot(lot);
     }
@@ -94,13 +97,39 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Re
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("resource::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateResources.into(), CompanyPermission::ResourceUpdate.into()], now)
+}
+
+/// Set (or clear) the ACL restricting which members can act on a resource
+///
+/// Changing an ACL is itself restricted by the object's current ACL.
+pub fn set_acl(caller: &User, member: &Member, company: &Company, mut subject: Resource, acl: Option<Acl>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::CompanyUpdateResources)?;
+    member.access_check(caller.id(), company, CompanyPermission::ResourceUpdate, now)?;
+    if !company.is_active() {
+        Err(Error::ObjectIsInactive("company".into()))?;
+    }
+    if subject.inner().primary_accountable().as_ref() != Some(&company.agent_id()) {
+        Err(AccessDenied::for_actor(DenyReason::WrongCompany, member.id().clone()))?;
+    }
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("resource".into()))?;
+    }
+    // the current ACL decides who gets to change it
+    member.acl_check(subject.acl(), &CompanyPermission::ResourceUpdate)?;
+    let original = subject.clone();
+    subject.set_acl(acl);
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("resource::set_acl", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateResources.into(), CompanyPermission::ResourceUpdate.into()], now)
 }
 
 /// Delete a resource
 pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Resource, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateResources)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ResourceDelete)?;
+    member.access_check(caller.id(), company, CompanyPermission::ResourceDelete, now)?;
+    member.acl_check(subject.acl(), &CompanyPermission::ResourceDelete)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -111,18 +140,24 @@ pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Re
         Err(Error::CannotEraseCosts)?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("resource::delete", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateResources.into(), CompanyPermission::ResourceDelete.into()], now)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        access::{AclEntry, AclSubject},
         models::{
+            company::CompanyID,
+            member::MemberID,
             resource_spec::ResourceSpecID,
         },
         util::{self, test::{self, *}},
     };
+    use om2::Measure;
+    use rust_decimal_macros::*;
 
     #[test]
     fn can_create() {
@@ -139,7 +174,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let resource = mods[0].clone().expect_op::<Resource>(Op::Create).unwrap();
         assert_eq!(resource.id(), &id);
@@ -176,8 +211,16 @@ mod tests {
             update(state.user(), state.member(), state.company(), state.model().clone(), None, Some("better widgets".into()), Some("444-computers-and-equipment".into()), None, None, Some(Unit::WattHour), Some(false), &now2)
         };
 
+        // the resource's ACL can keep members out
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::ResourceUpdate])])));
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::ObjectAcl);
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(state.member().id().clone()), vec![CompanyPermission::ResourceUpdate])])));
+        assert!(testfn(&state2).is_ok());
+
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let resource2 = mods[0].clone().expect_op::<Resource>(Op::Update).unwrap();
         assert_eq!(resource2.id(), &id);
@@ -195,6 +238,43 @@ mod tests {
         assert_eq!(resource2.deleted(), &None);
     }
 
+    #[test]
+    fn can_set_acl() {
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![CompanyPermission::ResourceCreate, CompanyPermission::ResourceUpdate], &now);
+        let acl = Acl::new(vec![AclEntry::new(AclSubject::Role("tool library".into()), vec![CompanyPermission::Use])]);
+        let mut resource = make_resource(&ResourceID::create(), state.company().id(), &Measure::new(dec!(5), Unit::One), &Costs::new(), &now);
+        resource.set_acl(Some(acl.clone()));
+        state.model = Some(resource);
+
+        let now2 = util::time::now();
+        let testfn = |state: &TestState<Resource, Resource>| {
+            set_acl(state.user(), state.member(), state.company(), state.model().clone(), None, &now2)
+        };
+        test::standard_transaction_tests(&state, &testfn);
+        test::double_deleted_tester(&state, "resource", &testfn);
+
+        let mut state2 = state.clone();
+        state2.model_mut().inner_mut().set_primary_accountable(Some(CompanyID::create().into()));
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::WrongCompany);
+
+        // members the current ACL doesn't let update the resource can't change its ACL
+        let mut state3 = state.clone();
+        state3.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::ResourceUpdate])])));
+        let res = testfn(&state3);
+        expect_denied(res, DenyReason::ObjectAcl);
+        state3.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(state.member().id().clone()), vec![CompanyPermission::ResourceUpdate])])));
+        assert!(testfn(&state3).is_ok());
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let resource2 = mods[0].clone().expect_op::<Resource>(Op::Update).unwrap();
+        assert_eq!(resource2.id(), state.model().id());
+        assert_eq!(resource2.acl(), &None);
+        assert_eq!(resource2.updated(), &now2);
+    }
+
     #[test]
     fn can_delete() {
         let now = util::time::now();
@@ -215,8 +295,16 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
         test::double_deleted_tester(&state, "resource", &testfn);
 
+        // the resource's ACL can keep members out
+        let mut state2 = state.clone();
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(MemberID::create()), vec![CompanyPermission::ResourceDelete])])));
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::ObjectAcl);
+        state2.model_mut().set_acl(Some(Acl::new(vec![AclEntry::new(AclSubject::Member(state.member().id().clone()), vec![CompanyPermission::ResourceDelete])])));
+        assert!(testfn(&state2).is_ok());
+
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let resource2 = mods[0].clone().expect_op::<Resource>(Op::Delete).unwrap();
         assert_eq!(resource2.id(), &id);
@@ -0,0 +1,302 @@
+//! Resource groups gather the resource specs that count as the same resource
+//! (ie, "iron") and give them a per-unit value. Like occupations, these are
+//! managed systemically rather than by any one company.
+//!
+//! See the [resource group model.][1]
+//!
+//! [1]: ../../models/resource_group/index.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    access::Permission,
+    error::{Error, Result},
+    models::{
+        Op,
+        Modifications,
+        lib::basis_model::Model,
+        resource_group::{ResourceGroup, ResourceGroupID},
+        resource_group_link::{ResourceGroupLink, ResourceGroupLinkID},
+        resource_spec::ResourceSpec,
+        user::User,
+    },
+};
+use rust_decimal::prelude::*;
+
+/// Create a new `ResourceGroup`.
+pub fn create<T: Into<String>>(caller: &User, id: ResourceGroupID, name: T, note: Option<String>, credit_cost_per_unit: Decimal, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ResourceGroupCreate)?;
+    if credit_cost_per_unit < Decimal::zero() {
+        Err(Error::NegativeCosts)?;
+    }
+    let model = ResourceGroup::builder()
+        .id(id)
+        .name(name)
+        .note(note)
+        .credit_cost_per_unit(credit_cost_per_unit)
+        .active(active)
+        .created(*now)
+        .updated(*now)
+        .build()
+        .map_err(Error::BuilderFailed)?;
+    Modifications::new_single(Op::Create, model)
+        .audit("resource_group::create", caller, None, None, vec![Permission::ResourceGroupCreate.into()], now)
+}
+
+/// Update an existing `ResourceGroup`
+pub fn update(caller: &User, mut subject: ResourceGroup, name: Option<String>, note: Option<String>, credit_cost_per_unit: Option<Decimal>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ResourceGroupUpdate)?;
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("resource_group".into()))?;
+    }
+    let original = subject.clone();
+    if let Some(name) = name {
+        subject.set_name(name);
+    }
+    if let Some(note) = note {
+        subject.set_note(Some(note));
+    }
+    if let Some(credit_cost_per_unit) = credit_cost_per_unit {
+        if credit_cost_per_unit < Decimal::zero() {
+            Err(Error::NegativeCosts)?;
+        }
+        subject.set_credit_cost_per_unit(credit_cost_per_unit);
+    }
+    if let Some(active) = active {
+        subject.set_active(active);
+    }
+    subject.set_updated(*now);
+    Modifications::new_update(original, subject)
+        .audit("resource_group::update", caller, None, None, vec![Permission::ResourceGroupUpdate.into()], now)
+}
+
+/// Delete a `ResourceGroup`
+pub fn delete(caller: &User, mut subject: ResourceGroup, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ResourceGroupDelete)?;
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("resource_group".into()))?;
+    }
+    subject.set_deleted(Some(*now));
+    Modifications::new_single(Op::Delete, subject)
+        .audit("resource_group::delete", caller, None, None, vec![Permission::ResourceGroupDelete.into()], now)
+}
+
+/// Link a `ResourceSpec` to a `ResourceGroup`.
+///
+/// A resource spec can only belong to one group, so `links` must hold the
+/// spec's existing links: if any of them are still in place, we return
+/// `Error::ResourceGroupConflict` (unlink the spec first to move it to another
+/// group).
+pub fn link(caller: &User, id: ResourceGroupLinkID, group: &ResourceGroup, spec: &ResourceSpec, links: &[ResourceGroupLink], now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ResourceGroupLink)?;
+    if group.is_deleted() {
+        Err(Error::ObjectIsDeleted("resource_group".into()))?;
+    }
+    if !group.is_active() {
+        Err(Error::ObjectIsInactive("resource_group".into()))?;
+    }
+    if spec.is_deleted() {
+        Err(Error::ObjectIsDeleted("resource_spec".into()))?;
+    }
+    if links.iter().any(|link| link.resource_spec_id() == spec.id() && !link.is_deleted()) {
+        Err(Error::ResourceGroupConflict(spec.id().clone()))?;
+    }
+    let model = ResourceGroupLink::builder()
+        .id(id)
+        .group_id(group.id().clone())
+        .resource_spec_id(spec.id().clone())
+        .active(true)
+        .created(*now)
+        .updated(*now)
+        .build()
+        .map_err(Error::BuilderFailed)?;
+    Modifications::new_single(Op::Create, model)
+        .audit("resource_group::link", caller, None, None, vec![Permission::ResourceGroupLink.into()], now)
+}
+
+/// Remove a `ResourceSpec` from its `ResourceGroup` (by deleting the link).
+pub fn unlink(caller: &User, mut subject: ResourceGroupLink, now: &DateTime<Utc>) -> Result<Modifications> {
+    caller.access_check(Permission::ResourceGroupLink)?;
+    if subject.is_deleted() {
+        Err(Error::ObjectIsDeleted("resource_group_link".into()))?;
+    }
+    subject.set_deleted(Some(*now));
+    Modifications::new_single(Op::Delete, subject)
+        .audit("resource_group::unlink", caller, None, None, vec![Permission::ResourceGroupLink.into()], now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::{DenyReason, Role},
+        models::{
+            company::CompanyID,
+            resource_spec::ResourceSpecID,
+        },
+        util::{self, test::{self, *}},
+    };
+    use rust_decimal_macros::*;
+
+    #[test]
+    fn can_create() {
+        let id = ResourceGroupID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        state.user_mut().set_roles(vec![Role::SuperAdmin]);
+
+        let testfn = |state: &TestState<ResourceGroup, ResourceGroup>| {
+            create(state.user(), id.clone(), "iron", Some("measured in kg".into()), dec!(0.5), true, &now)
+        };
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+
+        let group = mods[0].clone().expect_op::<ResourceGroup>(Op::Create).unwrap();
+        assert_eq!(group.id(), &id);
+        assert_eq!(group.name(), "iron");
+        assert_eq!(group.note(), &Some("measured in kg".into()));
+        assert_eq!(group.credit_cost_per_unit(), &dec!(0.5));
+        assert_eq!(group.active(), &true);
+
+        let res = create(state.user(), id.clone(), "iron", None, dec!(-0.5), true, &now);
+        assert_eq!(res, Err(Error::NegativeCosts));
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_update() {
+        let id = ResourceGroupID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        state.user_mut().set_roles(vec![Role::SuperAdmin]);
+
+        let mods = create(state.user(), id.clone(), "iron", None, dec!(0.5), true, &now).unwrap().into_vec();
+        let group = mods[0].clone().expect_op::<ResourceGroup>(Op::Create).unwrap();
+        state.model = Some(group);
+
+        let now2 = util::time::now();
+        let testfn = |state: &TestState<ResourceGroup, ResourceGroup>| {
+            update(state.user(), state.model().clone(), Some("iron ore".into()), None, Some(dec!(0.75)), None, &now2)
+        };
+        test::double_deleted_tester(&state, "resource_group", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        let group2 = mods[0].clone().expect_op::<ResourceGroup>(Op::Update).unwrap();
+        assert_eq!(group2.created(), &now);
+        assert_eq!(group2.updated(), &now2);
+        assert_eq!(group2.name(), "iron ore");
+        assert_eq!(group2.note(), &None);
+        assert_eq!(group2.credit_cost_per_unit(), &dec!(0.75));
+        let paths = mods[0].diff().unwrap().iter().map(|diff| diff.path()).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["credit_cost_per_unit", "name", "updated", "version"]);
+
+        let res = update(state.user(), state.model().clone(), None, None, Some(dec!(-1)), None, &now2);
+        assert_eq!(res, Err(Error::NegativeCosts));
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_delete() {
+        let id = ResourceGroupID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        state.user_mut().set_roles(vec![Role::SuperAdmin]);
+
+        let mods = create(state.user(), id.clone(), "iron", None, dec!(0.5), true, &now).unwrap().into_vec();
+        let group = mods[0].clone().expect_op::<ResourceGroup>(Op::Create).unwrap();
+        state.model = Some(group);
+
+        let now2 = util::time::now();
+        let testfn = |state: &TestState<ResourceGroup, ResourceGroup>| {
+            delete(state.user(), state.model().clone(), &now2)
+        };
+        test::double_deleted_tester(&state, "resource_group", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let group2 = mods[0].clone().expect_op::<ResourceGroup>(Op::Delete).unwrap();
+        assert_eq!(group2.id(), &id);
+        assert_eq!(group2.deleted(), &Some(now2));
+
+        let mut state2 = state.clone();
+        state2.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state2);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+
+    #[test]
+    fn can_link_unlink() {
+        let id = ResourceGroupLinkID::create();
+        let now = util::time::now();
+        let mut state = TestState::standard(vec![], &now);
+        state.user_mut().set_roles(vec![Role::SuperAdmin]);
+
+        let mods = create(state.user(), ResourceGroupID::create(), "iron", None, dec!(0.5), true, &now).unwrap().into_vec();
+        let group = mods[0].clone().expect_op::<ResourceGroup>(Op::Create).unwrap();
+        let spec = make_resource_spec(&ResourceSpecID::create(), &CompanyID::create(), "mined iron", &now);
+        state.model = Some(group);
+        state.model2 = Some(spec);
+
+        let testfn = |state: &TestState<ResourceGroup, ResourceSpec>| {
+            link(state.user(), id.clone(), state.model(), state.model2(), &[], &now)
+        };
+        test::double_deleted_tester(&state, "resource_group", &testfn);
+
+        let mods = testfn(&state).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let group_link = mods[0].clone().expect_op::<ResourceGroupLink>(Op::Create).unwrap();
+        assert_eq!(group_link.id(), &id);
+        assert_eq!(group_link.group_id(), state.model().id());
+        assert_eq!(group_link.resource_spec_id(), state.model2().id());
+        assert_eq!(group_link.active(), &true);
+
+        let mut state2 = state.clone();
+        state2.model_mut().set_active(false);
+        let res = testfn(&state2);
+        assert_eq!(res, Err(Error::ObjectIsInactive("resource_group".into())));
+
+        let mut state3 = state.clone();
+        state3.model2_mut().set_deleted(Some(now));
+        let res = testfn(&state3);
+        assert_eq!(res, Err(Error::ObjectIsDeleted("resource_spec".into())));
+
+        let mut state4 = state.clone();
+        state4.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state4);
+        expect_denied(res, DenyReason::MissingPermission);
+
+        // a spec can only be in one group at a time
+        let mut existing = make_resource_group_link(&ResourceGroupLinkID::create(), &ResourceGroupID::create(), state.model2().id(), &now);
+        let other = make_resource_group_link(&ResourceGroupLinkID::create(), state.model().id(), &ResourceSpecID::create(), &now);
+        let res = link(state.user(), id.clone(), state.model(), state.model2(), &[other.clone(), existing.clone()], &now);
+        assert_eq!(res, Err(Error::ResourceGroupConflict(state.model2().id().clone())));
+        existing.set_deleted(Some(now));
+        assert!(link(state.user(), id.clone(), state.model(), state.model2(), &[other, existing], &now).is_ok());
+
+        let now2 = util::time::now();
+        let mut state5: TestState<ResourceGroupLink, ResourceGroupLink> = TestState::standard(vec![], &now);
+        state5.user_mut().set_roles(vec![Role::SuperAdmin]);
+        state5.model = Some(group_link);
+        let testfn = |state: &TestState<ResourceGroupLink, ResourceGroupLink>| {
+            unlink(state.user(), state.model().clone(), &now2)
+        };
+        test::double_deleted_tester(&state5, "resource_group_link", &testfn);
+
+        let mods = testfn(&state5).unwrap().into_vec();
+        assert_eq!(mods.len(), 2);
+        let group_link2 = mods[0].clone().expect_op::<ResourceGroupLink>(Op::Delete).unwrap();
+        assert_eq!(group_link2.deleted(), &Some(now2));
+
+        state5.user_mut().set_roles(vec![Role::User]);
+        let res = testfn(&state5);
+        expect_denied(res, DenyReason::MissingPermission);
+    }
+}
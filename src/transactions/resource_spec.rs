@@ -31,7 +31,7 @@ use vf_rs::vf;
 /// Create a new ResourceSpec
 pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company, id: ResourceSpecID, name: T, note: T, classifications: Vec<Url>, default_unit_of_effort: Option<Unit>, default_unit_of_resource: Option<Unit>, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateResourceSpecs)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ResourceSpecCreate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ResourceSpecCreate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -53,16 +53,18 @@ pub fn create<T: Into<String>>(caller: &User, member: &Member, company: &Company
         .updated(now.clone())
         .build()
         .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+    Modifications::new_single(Op::Create, model)
+        .audit("resource_spec::create", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateResourceSpecs.into(), CompanyPermission::ResourceSpecCreate.into()], now)
 }
 
 /// Update a resource spec
 pub fn update(caller: &User, member: &Member, company: &Company, mut subject: ResourceSpec, name: Option<String>, note: Option<String>, classifications: Option<Vec<Url>>, default_unit_of_effort: Option<Unit>, default_unit_of_resource: Option<Unit>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateResourceSpecs)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ResourceSpecUpdate)?;
+    member.access_check(caller.id(), company, CompanyPermission::ResourceSpecUpdate, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
+    let original = subject.clone();
     if let Some(name) = name {
         subject.inner_mut().set_name(name);
     }
@@ -82,13 +84,14 @@ pub fn update(caller: &User, member: &Member, company: &Company, mut subject: Re
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("resource_spec::update", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateResourceSpecs.into(), CompanyPermission::ResourceSpecUpdate.into()], now)
 }
 
 /// Delete a resource spec
 pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: ResourceSpec, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::CompanyUpdateResourceSpecs)?;
-    member.access_check(caller.id(), company.id(), CompanyPermission::ResourceSpecDelete)?;
+    member.access_check(caller.id(), company, CompanyPermission::ResourceSpecDelete, now)?;
     if !company.is_active() {
         Err(Error::ObjectIsInactive("company".into()))?;
     }
@@ -96,7 +99,8 @@ pub fn delete(caller: &User, member: &Member, company: &Company, mut subject: Re
         Err(Error::ObjectIsDeleted("resource_spec".into()))?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("resource_spec::delete", caller, Some(member), Some(company.id()), vec![Permission::CompanyUpdateResourceSpecs.into(), CompanyPermission::ResourceSpecDelete.into()], now)
 }
 
 #[cfg(test)]
@@ -121,7 +125,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let recspec = mods[0].clone().expect_op::<ResourceSpec>(Op::Create).unwrap();
         assert_eq!(recspec.id(), &id);
@@ -153,7 +157,7 @@ mod tests {
         test::standard_transaction_tests(&state, &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let recspec2 = mods[0].clone().expect_op::<ResourceSpec>(Op::Update).unwrap();
         assert_eq!(recspec2.id(), &id);
@@ -186,7 +190,7 @@ mod tests {
         test::double_deleted_tester(&state, "resource_spec", &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let recspec2 = mods[0].clone().expect_op::<ResourceSpec>(Op::Delete).unwrap();
         assert_eq!(recspec2.id(), &id);
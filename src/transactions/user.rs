@@ -6,7 +6,7 @@
 
 use chrono::{DateTime, Utc};
 use crate::{
-    access::{self, Permission, Role},
+    access::{self, AccessDenied, DenyReason, Permission, Role},
     error::{Error, Result},
     models::{
         Op,
@@ -17,8 +17,8 @@ use crate::{
 };
 
 /// Create a user (private implementation, meant to be wrapped).
-fn create_inner<T: Into<String>>(id: UserID, roles: Vec<Role>, email: T, name: T, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
-    let model = User::builder()
+fn create_inner<T: Into<String>>(id: UserID, roles: Vec<Role>, email: T, name: T, active: bool, now: &DateTime<Utc>) -> Result<User> {
+    User::builder()
         .id(id)
         .roles(roles)
         .email(email)
@@ -27,14 +27,16 @@ fn create_inner<T: Into<String>>(id: UserID, roles: Vec<Role>, email: T, name: T
         .created(now.clone())
         .updated(now.clone())
         .build()
-        .map_err(|e| Error::BuilderFailed(e))?;
-    Ok(Modifications::new_single(Op::Create, model))
+        .map_err(Error::BuilderFailed)
 }
 
 /// Create a new user with a `Role::User` role. No permissions required.
 pub fn create<T: Into<String>>(id: UserID, email: T, name: T, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     access::guest_check(Permission::UserCreate)?;
-    create_inner(id, vec![Role::User], email, name, active, now)
+    let model = create_inner(id, vec![Role::User], email, name, active, now)?;
+    // the new user is their own caller
+    Modifications::new_single(Op::Create, model.clone())
+        .audit("user::create", &model, None, None, vec![Permission::UserCreate.into()], now)
 }
 
 /// Create a new user with a specific set of permissions using a current user as
@@ -42,22 +44,26 @@ pub fn create<T: Into<String>>(id: UserID, email: T, name: T, active: bool, now:
 /// `Permission::UserCreate` permission.
 pub fn create_permissioned<T: Into<String>>(caller: &User, id: UserID, roles: Vec<Role>, email: T, name: T, active: bool, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::UserAdminCreate)?;
-    create_inner(id, roles, email, name, active, now)
+    let model = create_inner(id, roles, email, name, active, now)?;
+    Modifications::new_single(Op::Create, model)
+        .audit("user::create_permissioned", caller, None, None, vec![Permission::UserAdminCreate.into()], now)
 }
 
 /// Update a user object
 pub fn update(caller: &User, mut subject: User, email: Option<String>, name: Option<String>, active: Option<bool>, now: &DateTime<Utc>) -> Result<Modifications> {
-    caller.access_check(Permission::UserAdminUpdate)
+    let permission = caller.access_check(Permission::UserAdminUpdate)
+        .map(|_| Permission::UserAdminUpdate)
         .or_else(|_| {
             caller.access_check(Permission::UserUpdate)
                 .and_then(|_| {
                     if caller.id() == subject.id() {
-                        Ok(())
+                        Ok(Permission::UserUpdate)
                     } else {
-                        Err(Error::InsufficientPrivileges)
+                        Err(AccessDenied::for_actor(DenyReason::WrongUser, caller.id().clone()).into())
                     }
                 })
         })?;
+    let original = subject.clone();
     if let Some(email) = email {
         subject.set_email(email);
     }
@@ -68,15 +74,18 @@ pub fn update(caller: &User, mut subject: User, email: Option<String>, name: Opt
         subject.set_active(active);
     }
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("user::update", caller, None, None, vec![permission.into()], now)
 }
 
 /// Update a user's roles
 pub fn set_roles(caller: &User, mut subject: User, roles: Vec<Role>, now: &DateTime<Utc>) -> Result<Modifications> {
     caller.access_check(Permission::UserSetRoles)?;
+    let original = subject.clone();
     subject.set_roles(roles);
     subject.set_updated(now.clone());
-    Ok(Modifications::new_single(Op::Update, subject))
+    Modifications::new_update(original, subject)
+        .audit("user::set_roles", caller, None, None, vec![Permission::UserSetRoles.into()], now)
 }
 
 /// Delete a user
@@ -86,7 +95,8 @@ pub fn delete(caller: &User, mut subject: User, now: &DateTime<Utc>) -> Result<M
         Err(Error::ObjectIsDeleted("user".into()))?;
     }
     subject.set_deleted(Some(now.clone()));
-    Ok(Modifications::new_single(Op::Delete, subject))
+    Modifications::new_single(Op::Delete, subject)
+        .audit("user::delete", caller, None, None, vec![Permission::UserDelete.into()], now)
 }
 
 #[cfg(test)]
@@ -105,7 +115,7 @@ mod tests {
         let id = UserID::create();
         let now = util::time::now();
         let mods = create(id.clone(), "zing@lyonbros.com", "leonard", true, &now).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let model = mods[0].clone().expect_op::<User>(Op::Create).unwrap();
         assert_eq!(model.id(), &id);
@@ -127,7 +137,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let model = mods[0].clone().expect_op::<User>(Op::Create).unwrap();
         assert_eq!(model.id(), &id);
         assert_eq!(model.email(), "zing@lyonbros.com");
@@ -137,7 +147,7 @@ mod tests {
         let mut state2 = state.clone();
         state2.user_mut().set_roles(vec![Role::User]);
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
     }
 
     #[test]
@@ -179,7 +189,7 @@ mod tests {
         let mut state3 = state.clone();
         state3.user = Some(user3.clone());
         let res = testfn(&state3);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::Inactive);
     }
 
     #[test]
@@ -197,7 +207,7 @@ mod tests {
         };
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
 
         let user2 = mods[0].clone().expect_op::<User>(Op::Update).unwrap();
         assert_eq!(user2.id(), &id);
@@ -211,13 +221,13 @@ mod tests {
         state2.user = Some(user2.clone());
         state2.model = Some(user2);
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::MissingPermission);
 
         // inactive users should not be able to run mods
         let mut state3 = state.clone();
         state3.user_mut().set_active(false);
         let res = testfn(&state3);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::Inactive);
     }
 
     #[test]
@@ -235,7 +245,7 @@ mod tests {
         test::double_deleted_tester(&state, "user", &testfn);
 
         let mods = testfn(&state).unwrap().into_vec();
-        assert_eq!(mods.len(), 1);
+        assert_eq!(mods.len(), 2);
         let user2 = mods[0].clone().expect_op::<User>(Op::Delete).unwrap();
         assert_eq!(user2.deleted(), &Some(now.clone()));
 
@@ -243,7 +253,7 @@ mod tests {
         state2.user = Some(user2.clone());
         state2.model = Some(user2);
         let res = testfn(&state2);
-        assert_eq!(res, Err(Error::InsufficientPrivileges));
+        expect_denied(res, DenyReason::Deleted);
     }
 }
 
@@ -61,6 +61,16 @@ pub fn inc_measure(measure: &mut Measure, inc_by: &Measure) -> Result<bool> {
     Ok(true)
 }
 
+/// Grab a Measure's numerical value as a Decimal.
+pub fn to_decimal(measure: &Measure) -> Result<Decimal> {
+    let num = NumericUnion::Decimal(Decimal::zero()).add(measure.has_numerical_value().clone())
+        .map_err(Error::NumericUnionOpError)?;
+    match num {
+        NumericUnion::Decimal(val) => Ok(val),
+        _ => Err(Error::NumericUnionOpError(format!("error converting to Decimal: {:?}", num))),
+    }
+}
+
 /// Either use the given `measure` if it exists, or create a measure of 0 and
 /// return it using the same units/numeric types as `default`.
 pub fn unwrap_or_zero(measure: &Option<Measure>, default: &Measure) -> Measure {
@@ -1,12 +1,12 @@
 use chrono::{DateTime, Utc};
 use crate::{
-    access::Role,
+    access::{DenyReason, Role},
     costs::Costs,
     error::{Error, Result},
     models::{
         Modifications,
 
-        agreement::{Agreement, AgreementID},
+        agreement::{Agreement, AgreementID, Signature},
         company::{Company, CompanyID, Permission as CompanyPermission},
         lib::{
             agent::AgentID,
@@ -17,12 +17,15 @@ use crate::{
         process::{Process, ProcessID},
         process_spec::{ProcessSpec, ProcessSpecID},
         resource::{Resource, ResourceID},
+        resource_group::{ResourceGroup, ResourceGroupID},
+        resource_group_link::{ResourceGroupLink, ResourceGroupLinkID},
         resource_spec::{ResourceSpec, ResourceSpecID},
         user::{User, UserID},
     },
     util,
 };
 use om2::Measure;
+use rust_decimal::prelude::*;
 use vf_rs::{vf, geo::SpatialThing};
 
 #[derive(Clone, Debug, PartialEq, getset::Setters, derive_builder::Builder)]
@@ -163,20 +166,20 @@ pub(crate) fn permissions_checks<M1, M2, F>(state: &TestState<M1, M2>, testfn: &
     let mut state1 = state.clone();
     state1.member_mut().set_permissions(vec![]);
     let res = testfn(&state1);
-    assert_eq!(res, Err(Error::InsufficientPrivileges));
+    expect_denied(res, DenyReason::MissingPermission);
 
     // test that a user with no permissions cannot perform this action
     let mut state2 = state.clone();
     state2.user_mut().set_roles(vec![]);
     let res = testfn(&state1);
-    assert_eq!(res, Err(Error::InsufficientPrivileges));
+    expect_denied(res, DenyReason::MissingPermission);
 
     // test that when a user's id and member's agent id don't match we cannot
     // perform this action
     let mut state3 = state.clone();
     state3.user_mut().set_id(UserID::new("gee-i-hope-nobody-else-uses-this-exact-id-in-a-test-lol"));
     let res = testfn(&state3);
-    assert_eq!(res, Err(Error::InsufficientPrivileges));
+    expect_denied(res, DenyReason::WrongUser);
 }
 
 pub(crate) fn double_deleted_tester<M1, M2, F, S>(state: &TestState<M1, M2>, tystr: S, testfn: &F)
@@ -191,6 +194,16 @@ pub(crate) fn double_deleted_tester<M1, M2, F, S>(state: &TestState<M1, M2>, tys
     assert_eq!(res, Err(Error::ObjectIsDeleted(tystr.into())));
 }
 
+/// Assert that a result is an `InsufficientPrivileges` error with the given
+/// reason.
+#[track_caller]
+pub(crate) fn expect_denied<T: std::fmt::Debug>(res: Result<T>, reason: DenyReason) {
+    match res {
+        Err(Error::InsufficientPrivileges(denied)) => assert_eq!(denied.reason(), &reason, "access denied for the wrong reason: {:?}", denied),
+        _ => panic!("expected access denied ({:?}), got {:?}", reason, res),
+    }
+}
+
 pub(crate) fn standard_transaction_tests<M1, M2, F>(state: &TestState<M1, M2>, testfn: &F)
     where M1: Model,
           M2: Model,
@@ -200,7 +213,11 @@ pub(crate) fn standard_transaction_tests<M1, M2, F>(state: &TestState<M1, M2>, t
     permissions_checks(state, testfn);
 }
 
+/// Creates an agreement that all participants have signed (ie, it's binding).
 pub fn make_agreement<T: Into<String>>(id: &AgreementID, participants: &Vec<AgentID>, name: T, note: T, now: &DateTime<Utc>) -> Agreement {
+    let signatures = participants.iter()
+        .map(|participant| Signature::new(participant.clone(), UserID::create(), MemberID::create(), 0, *now))
+        .collect::<Vec<_>>();
     Agreement::builder()
         .id(id.clone())
         .inner(
@@ -211,6 +228,7 @@ pub fn make_agreement<T: Into<String>>(id: &AgreementID, participants: &Vec<Agen
                 .build().unwrap()
         )
         .participants(participants.clone())
+        .signatures(signatures)
         .active(true)
         .created(now.clone())
         .updated(now.clone())
@@ -291,6 +309,28 @@ pub fn make_resource(id: &ResourceID, company_id: &CompanyID, quantity: &Measure
         .build().unwrap()
 }
 
+pub fn make_resource_group<T: Into<String>>(id: &ResourceGroupID, name: T, credit_cost_per_unit: Decimal, now: &DateTime<Utc>) -> ResourceGroup {
+    ResourceGroup::builder()
+        .id(id.clone())
+        .name(name)
+        .credit_cost_per_unit(credit_cost_per_unit)
+        .active(true)
+        .created(*now)
+        .updated(*now)
+        .build().unwrap()
+}
+
+pub fn make_resource_group_link(id: &ResourceGroupLinkID, group_id: &ResourceGroupID, resource_spec_id: &ResourceSpecID, now: &DateTime<Utc>) -> ResourceGroupLink {
+    ResourceGroupLink::builder()
+        .id(id.clone())
+        .group_id(group_id.clone())
+        .resource_spec_id(resource_spec_id.clone())
+        .active(true)
+        .created(*now)
+        .updated(*now)
+        .build().unwrap()
+}
+
 pub fn make_resource_spec<T: Into<String>>(id: &ResourceSpecID, company_id: &CompanyID, name: T, now: &DateTime<Utc>) -> ResourceSpec {
     ResourceSpec::builder()
         .id(id.clone())
@@ -0,0 +1,806 @@
+//! Converts our models to and from [ValueFlows] JSON-LD, so Basis data can be
+//! shared with other VF-speaking systems.
+//!
+//! [export] renders events, resources, processes, commitments, intents,
+//! agreements, and agents (companies, users, and members) as a JSON-LD
+//! document with a `@context` and an `@graph` of nodes. Each node gets an IRI
+//! built from a base IRI, the kind of model it is, and its ID (ie,
+//! `https://basis.example/processes/1234`), and links between models become
+//! references to those IRIs (a process's company becomes its `inScopeOf`).
+//! Companies are exported as `foaf:Organization`, users as `foaf:Person`, and
+//! members as `vf:AgentRelationship`. Measures use the [OM 2] vocabulary, the
+//! same as VF does. Any other models (accounts, proposals, etc) and any fields
+//! that are specific to Basis (costs, custody, permissions, etc) are left out.
+//!
+//! [import] goes the other way, turning a VF document back into models. VF has
+//! no notion of a lot of what Basis tracks, so each imported model comes with
+//! notes about what was lost along the way:
+//!
+//! - `ImportNoteKind::Dropped` means the document had a property that Basis
+//!   can't represent (ie, an event's `resourceClassifiedAs`), so it was left out
+//! - `ImportNoteKind::Defaulted` means the model has a field that VF doesn't
+//!   carry (ie, an event's `move_costs`), so it was given a default value
+//!
+//! IRIs that start with the base IRI are mapped back onto IDs. Any other IRIs
+//! are used as IDs verbatim, and links to agents that aren't ours are resolved
+//! using the agent nodes in the document.
+//!
+//! ```rust
+//! use basis_core::{
+//!     models::Model,
+//!     transactions,
+//!     valueflows,
+//! };
+//! use chrono::Utc;
+//!
+//! let now = Utc::now();
+//! let user = transactions::user::create("1234".into(), "andrew@lyonbros.com", "andrew", true, &now).unwrap()
+//!     .into_vec().remove(0).into_pair().1;
+//! let doc = valueflows::export(&vec![user], "https://basis.example/");
+//! assert_eq!(doc["@graph"][0]["@id"], "https://basis.example/users/1234");
+//! assert_eq!(doc["@graph"][0]["@type"], "foaf:Person");
+//!
+//! let import = valueflows::import(&doc, "https://basis.example/", &now).unwrap();
+//! match &import.models()[0] {
+//!     Model::User(user) => assert_eq!(user.name(), "andrew"),
+//!     _ => panic!("expected a user"),
+//! }
+//! // VF agents don't have emails
+//! assert_eq!(import.notes()[0].field(), "email");
+//! ```
+//!
+//! [ValueFlows]: https://www.valueflo.ws/
+//! [OM 2]: http://www.ontology-of-units-of-measure.org/
+//! [export]: fn.export.html
+//! [import]: fn.import.html
+
+use chrono::{DateTime, Utc};
+use crate::{
+    error::{Error, Result},
+    models::{
+        Model,
+        lib::agent::AgentID,
+    },
+};
+use getset::Getters;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// The ValueFlows namespace
+pub const VF: &str = "https://w3id.org/valueflows#";
+/// The OM 2 (units of measure) namespace
+pub const OM2: &str = "http://www.ontology-of-units-of-measure.org/resource/om-2/";
+/// The FOAF namespace, which VF uses for people and organizations
+pub const FOAF: &str = "http://xmlns.com/foaf/0.1/";
+/// The XML schema namespace, used for typing numbers
+pub const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// Whether an imported model lost a property or gained a default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImportNoteKind {
+    /// The document had a property that Basis can't represent, which was left
+    /// out of the model.
+    Dropped,
+    /// The model has a field VF doesn't carry, which was given a default.
+    Defaulted,
+}
+
+/// Notes something that didn't make it across when importing a node.
+#[derive(Clone, Debug, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct ImportNote {
+    /// The IRI of the node being imported
+    node: String,
+    /// The field in question. For dropped properties this is the property as
+    /// it appeared in the document (ie `resourceClassifiedAs`), and for
+    /// defaulted fields it's the model's field (ie `move_costs`).
+    field: String,
+    /// What happened to the field
+    kind: ImportNoteKind,
+}
+
+/// The result of importing a VF document.
+#[derive(Clone, Debug, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct Import {
+    /// The models we were able to build from the document
+    models: Vec<Model>,
+    /// Any properties that were dropped or fields that were defaulted
+    notes: Vec<ImportNote>,
+}
+
+/// How a VF field links to something outside of its node.
+enum Link {
+    /// Links to another one of our models, which lives in the given IRI
+    /// collection
+    Model(&'static str),
+    /// Links to an agent (company, member, or user)
+    Agent,
+    /// Links to an arbitrary IRI (a classification, an image, etc)
+    Iri,
+    /// Links to an OM 2 unit
+    Unit,
+    /// Links to a VF action
+    Action,
+}
+
+/// The kinds of nodes we can import, by their (unprefixed) JSON-LD type.
+const NODE_TYPES: &[&str] = &[
+    "AgentRelationship",
+    "Agreement",
+    "Commitment",
+    "EconomicEvent",
+    "EconomicResource",
+    "Intent",
+    "Organization",
+    "Person",
+    "Process",
+];
+
+/// The IRI collections that hold agents, and what kind of agent each holds.
+const AGENT_COLLECTIONS: &[(&str, &str)] = &[
+    ("companies", "company"),
+    ("members", "member"),
+    ("users", "user"),
+];
+
+/// Grab how a (snake-cased) VF field links outside its node, if it does.
+fn link(field: &str) -> Option<Link> {
+    let link = match field {
+        "input_of" | "output_of" | "stage" => Link::Model("processes"),
+        "resource_inventoried_as" | "to_resource_inventoried_as" | "contained_in" => Link::Model("resources"),
+        "resource_conforms_to" | "conforms_to" => Link::Model("resource-specs"),
+        "realization_of" | "clause_of" => Link::Model("agreements"),
+        "triggered_by" => Link::Model("events"),
+        "based_on" => Link::Model("process-specs"),
+        "provider" | "receiver" | "primary_accountable" | "in_scope_of" | "subject" | "object" => Link::Agent,
+        "agreed_in" | "classified_as" | "resource_classified_as" | "image" => Link::Iri,
+        "unit_of_effort" => Link::Unit,
+        "action" => Link::Action,
+        _ => return None,
+    };
+    Some(link)
+}
+
+/// The VF properties that our models fill with `()`, meaning we have nowhere
+/// to put them, keyed by node type.
+fn unrepresentable(node_type: &str) -> &'static [&'static str] {
+    match node_type {
+        "AgentRelationship" => &["in_scope_of", "relationship"],
+        "Commitment" => &["independent_demand_of", "resource_classified_as"],
+        "EconomicEvent" | "Intent" => &["resource_classified_as"],
+        "Process" => &["nested_in", "planned_within"],
+        _ => &[],
+    }
+}
+
+/// The fields of our models that VF doesn't carry, keyed by node type.
+fn defaulted(node_type: &str) -> &'static [&'static str] {
+    match node_type {
+        "AgentRelationship" => &["class"],
+        "Agreement" => &["participants"],
+        "Commitment" | "EconomicEvent" | "Intent" => &["move_costs"],
+        "EconomicResource" => &["in_custody_of", "costs"],
+        "Organization" | "Person" => &["email"],
+        "Process" => &["costs"],
+        _ => &[],
+    }
+}
+
+/// The `@context` for our exported documents.
+fn context() -> Value {
+    json!({
+        "@vocab": VF,
+        "vf": VF,
+        "om2": OM2,
+        "foaf": FOAF,
+        "xsd": XSD,
+    })
+}
+
+/// Convert a snake-cased field name into a camel-cased VF property.
+fn to_camel(field: &str) -> String {
+    let mut camel = String::with_capacity(field.len());
+    let mut upper = false;
+    for ch in field.chars() {
+        if ch == '_' {
+            upper = true;
+        } else if upper {
+            camel.extend(ch.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(ch);
+        }
+    }
+    camel
+}
+
+/// Convert a camel-cased VF property into a snake-cased field name.
+fn to_snake(property: &str) -> String {
+    let mut snake = String::with_capacity(property.len() + 4);
+    for ch in property.chars() {
+        if ch.is_uppercase() {
+            snake.push('_');
+            snake.extend(ch.to_lowercase());
+        } else {
+            snake.push(ch);
+        }
+    }
+    snake
+}
+
+/// Strip a namespace (in either its prefixed or full form) off of a term.
+fn term<'a>(val: &'a str, prefix: &str, namespace: &str) -> &'a str {
+    val.strip_prefix(prefix)
+        .or_else(|| val.strip_prefix(namespace))
+        .unwrap_or(val)
+}
+
+/// Build the IRI for a model.
+fn iri(base: &str, collection: &str, id: &str) -> String {
+    format!("{}{}/{}", base, collection, id)
+}
+
+/// Build the IRI for an agent.
+fn agent_iri(base: &str, agent: &AgentID) -> String {
+    match agent {
+        AgentID::CompanyID(id) => iri(base, "companies", id.as_str()),
+        AgentID::MemberID(id) => iri(base, "members", id.as_str()),
+        AgentID::UserID(id) => iri(base, "users", id.as_str()),
+    }
+}
+
+/// Export a collection of models as a ValueFlows JSON-LD document, using
+/// `base` as the prefix for each model's IRI.
+///
+/// Models that VF has no equivalent for are skipped.
+pub fn export(models: &[Model], base: &str) -> Value {
+    let graph = models.iter()
+        .filter_map(|model| export_node(model, base))
+        .collect::<Vec<_>>();
+    json!({
+        "@context": context(),
+        "@graph": graph,
+    })
+}
+
+/// Render a single model as a JSON-LD node.
+fn export_node(model: &Model, base: &str) -> Option<Value> {
+    let (node_type, collection, id, fields) = match model {
+        Model::Agreement(agreement) => ("Agreement", "agreements", agreement.id().as_str(), serde_json::to_value(agreement.inner())),
+        Model::Commitment(commitment) => ("Commitment", "commitments", commitment.id().as_str(), serde_json::to_value(commitment.inner())),
+        Model::Company(company) => ("foaf:Organization", "companies", company.id().as_str(), serde_json::to_value(company.inner())),
+        Model::Event(event) => ("EconomicEvent", "events", event.id().as_str(), serde_json::to_value(event.inner())),
+        Model::Intent(intent) => ("Intent", "intents", intent.id().as_str(), serde_json::to_value(intent.inner())),
+        Model::Member(member) => ("AgentRelationship", "members", member.id().as_str(), serde_json::to_value(member.inner())),
+        Model::Process(process) => {
+            // a process is always in the scope of its company, even if VF
+            // isn't told about it
+            let mut inner = process.inner().clone();
+            if inner.in_scope_of().is_empty() {
+                inner.set_in_scope_of(vec![process.company_id().clone().into()]);
+            }
+            ("Process", "processes", process.id().as_str(), serde_json::to_value(inner))
+        }
+        Model::Resource(resource) => ("EconomicResource", "resources", resource.id().as_str(), serde_json::to_value(resource.inner())),
+        Model::User(user) => ("foaf:Person", "users", user.id().as_str(), Ok(json!({"name": user.name()}))),
+        _ => return None,
+    };
+    let mut node = Map::new();
+    node.insert("@id".into(), json!(iri(base, collection, id)));
+    node.insert("@type".into(), json!(node_type));
+    if let Ok(Value::Object(fields)) = fields {
+        for (field, val) in fields.iter().filter(|(_, val)| !val.is_null()) {
+            node.insert(to_camel(field), export_value(base, field, val));
+        }
+    }
+    Some(Value::Object(node))
+}
+
+/// Render a field's value as JSON-LD.
+fn export_value(base: &str, field: &str, val: &Value) -> Value {
+    if let Value::Array(vals) = val {
+        return Value::Array(vals.iter().map(|val| export_value(base, field, val)).collect());
+    }
+    match (link(field), val) {
+        (Some(Link::Model(collection)), Value::String(id)) => json!({"@id": iri(base, collection, id)}),
+        (Some(Link::Agent), Value::Object(_)) => {
+            match serde_json::from_value::<AgentID>(val.clone()) {
+                Ok(agent) => json!({"@id": agent_iri(base, &agent)}),
+                Err(_) => val.clone(),
+            }
+        }
+        (Some(Link::Iri), Value::String(iri)) => json!({"@id": iri}),
+        (Some(Link::Unit), Value::String(unit)) => json!({"@id": format!("om2:{}", unit)}),
+        (Some(Link::Action), Value::String(action)) => json!({"@id": format!("vf:{}", action)}),
+        (_, Value::Object(map)) if map.contains_key("has_unit") => export_measure(map),
+        (_, Value::Object(map)) => {
+            let nested = map.iter()
+                .filter(|(_, val)| !val.is_null())
+                .map(|(key, val)| (to_camel(key), export_value(base, key, val)))
+                .collect::<Map<_, _>>();
+            Value::Object(nested)
+        }
+        _ => val.clone(),
+    }
+}
+
+/// Render a measure as an `om2:Measure`, typing its numerical value with the
+/// XSD type matching how the number is stored.
+fn export_measure(measure: &Map<String, Value>) -> Value {
+    let unit = measure.get("has_unit").and_then(|unit| unit.as_str()).unwrap_or("one");
+    let (number_type, number) = measure.get("has_numerical_value")
+        .and_then(|num| num.as_object())
+        .and_then(|num| num.iter().next())
+        .map(|(number_type, number)| (number_type.as_str(), number.clone()))
+        .unwrap_or(("decimal", json!(0)));
+    json!({
+        "@type": "om2:Measure",
+        "om2:hasUnit": {"@id": format!("om2:{}", unit)},
+        "om2:hasNumericalValue": {
+            "@value": number,
+            "@type": format!("xsd:{}", number_type),
+        },
+    })
+}
+
+/// Import a ValueFlows JSON-LD document, either one with an `@graph` or a
+/// single node (or list of nodes), as a set of models. `base` is the prefix
+/// used for our own IRIs, and `now` is used for the models' created/updated
+/// times.
+///
+/// Nodes with a type we don't know how to import are noted as dropped.
+pub fn import(doc: &Value, base: &str, now: &DateTime<Utc>) -> Result<Import> {
+    let nodes = match doc.get("@graph").unwrap_or(doc) {
+        Value::Array(nodes) => nodes.iter().collect::<Vec<_>>(),
+        node @ Value::Object(_) => vec![node],
+        _ => Err(Error::ImportFailed("document has no nodes".into()))?,
+    };
+    let nodes = nodes.into_iter()
+        .map(|node| node.as_object().ok_or_else(|| Error::ImportFailed("node is not an object".into())))
+        .collect::<Result<Vec<_>>>()?;
+
+    // agents that aren't ours can only be told apart by their node's type
+    let agents = nodes.iter()
+        .filter_map(|node| {
+            let id = node.get("@id")?.as_str()?;
+            let kind = match node_type(node)? {
+                "AgentRelationship" => "member",
+                "Organization" => "company",
+                "Person" => "user",
+                _ => return None,
+            };
+            Some((id.to_string(), kind))
+        })
+        .collect::<HashMap<_, _>>();
+    let importer = Importer { base, agents };
+
+    let mut models = Vec::with_capacity(nodes.len());
+    let mut notes = Vec::new();
+    for node in nodes {
+        let (model, node_notes) = importer.node(node, now)?;
+        if let Some(model) = model {
+            models.push(model);
+        }
+        notes.extend(node_notes);
+    }
+    Ok(Import { models, notes })
+}
+
+/// Grab the (unprefixed) type of a node, if it's one we can import.
+fn node_type(node: &Map<String, Value>) -> Option<&'static str> {
+    let types = match node.get("@type")? {
+        Value::String(node_type) => vec![node_type.as_str()],
+        Value::Array(types) => types.iter().filter_map(|node_type| node_type.as_str()).collect(),
+        _ => vec![],
+    };
+    types.into_iter()
+        .map(|node_type| term(term(node_type, "vf:", VF), "foaf:", FOAF))
+        .find_map(|node_type| NODE_TYPES.iter().find(|known| **known == node_type).copied())
+}
+
+/// Grab the IRI out of a reference, which is either `{"@id": ...}` or a bare
+/// string.
+fn reference(val: &Value) -> Result<&str> {
+    val.get("@id").unwrap_or(val).as_str()
+        .ok_or_else(|| Error::ImportFailed(format!("expected a reference, found {}", val)))
+}
+
+/// Merge a set of fields into a model's JSON and deserialize it.
+fn build<T: DeserializeOwned>(mut model: Value, fields: Value) -> Result<T> {
+    if let (Value::Object(model), Value::Object(fields)) = (&mut model, fields) {
+        model.extend(fields);
+    }
+    serde_json::from_value(model).map_err(|e| Error::ImportFailed(format!("{}", e)))
+}
+
+/// Holds what we need to know to map IRIs back onto IDs.
+struct Importer<'a> {
+    /// The prefix for our IRIs
+    base: &'a str,
+    /// Agent IRIs from the document, and which kind of agent they point to
+    agents: HashMap<String, &'static str>,
+}
+
+impl<'a> Importer<'a> {
+    /// Split one of our IRIs into its collection and ID. IRIs that aren't ours
+    /// are used as the ID.
+    fn parse<'b>(&self, iri: &'b str) -> (Option<&'b str>, &'b str) {
+        iri.strip_prefix(self.base)
+            .and_then(|rest| {
+                let mut parts = rest.splitn(2, '/');
+                Some((parts.next()?, parts.next()?))
+            })
+            .filter(|(_, id)| !id.is_empty())
+            .map(|(collection, id)| (Some(collection), id))
+            .unwrap_or((None, iri))
+    }
+
+    /// Turn an agent IRI into an `AgentID` (in its serialized form).
+    fn agent(&self, iri: &str) -> Result<Value> {
+        let (collection, id) = self.parse(iri);
+        let kind = AGENT_COLLECTIONS.iter()
+            .find(|(agent_collection, _)| Some(*agent_collection) == collection)
+            .map(|(_, kind)| *kind)
+            .or_else(|| self.agents.get(iri).copied())
+            .ok_or_else(|| Error::ImportFailed(format!("can't tell what kind of agent {} is", iri)))?;
+        let id = if collection.is_some() { id } else { iri };
+        Ok(json!({ kind: id }))
+    }
+
+    /// Map a JSON-LD value back onto a (snake-cased) field's value.
+    fn value(&self, field: &str, val: &Value) -> Result<Value> {
+        if let Value::Array(vals) = val {
+            let vals = vals.iter()
+                .map(|val| self.value(field, val))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(Value::Array(vals));
+        }
+        if let Some(literal) = val.get("@value") {
+            return self.value(field, literal);
+        }
+        let val = match (link(field), val) {
+            (Some(Link::Model(_)), _) => json!(self.parse(reference(val)?).1),
+            (Some(Link::Agent), _) => self.agent(reference(val)?)?,
+            (Some(Link::Iri), _) => json!(reference(val)?),
+            (Some(Link::Unit), _) => json!(term(reference(val)?, "om2:", OM2)),
+            (Some(Link::Action), _) => json!(term(reference(val)?, "vf:", VF)),
+            (_, Value::Object(map)) if map.keys().any(|key| term(key, "om2:", OM2) == "hasUnit") => import_measure(map)?,
+            (_, Value::Object(map)) => {
+                let mut nested = Map::new();
+                for (key, val) in map.iter().filter(|(key, _)| !key.starts_with('@')) {
+                    let key = to_snake(term(key, "vf:", VF));
+                    let val = self.value(&key, val)?;
+                    nested.insert(key, val);
+                }
+                Value::Object(nested)
+            }
+            _ => val.clone(),
+        };
+        Ok(val)
+    }
+
+    /// Import a single node. Returns `None` for the model if the node isn't a
+    /// type we can import.
+    fn node(&self, node: &Map<String, Value>, now: &DateTime<Utc>) -> Result<(Option<Model>, Vec<ImportNote>)> {
+        let node_iri = node.get("@id")
+            .and_then(|id| id.as_str())
+            .ok_or_else(|| Error::ImportFailed("node is missing its @id".into()))?;
+        let note = |field: &str, kind: ImportNoteKind| ImportNote { node: node_iri.into(), field: field.into(), kind };
+        let node_type = match node_type(node) {
+            Some(node_type) => node_type,
+            None => return Ok((None, vec![note("@type", ImportNoteKind::Dropped)])),
+        };
+
+        let mut fields = Map::new();
+        for (key, val) in node.iter().filter(|(key, _)| !key.starts_with('@')) {
+            let field = to_snake(term(key, "vf:", VF));
+            if unrepresentable(node_type).contains(&field.as_str()) {
+                continue;
+            }
+            let val = self.value(&field, val)?;
+            fields.insert(field, val);
+        }
+        let model = json!({
+            "id": self.parse(node_iri).1,
+            "active": true,
+            "created": now,
+            "updated": now,
+        });
+        let model = match node_type {
+            "AgentRelationship" => {
+                let class = match fields.get("subject").and_then(|subject| subject.as_object()) {
+                    Some(subject) if subject.contains_key("company") => json!({"Company": {}}),
+                    Some(subject) if subject.contains_key("user") => json!({"User": {}}),
+                    _ => Err(Error::ImportFailed(format!("{} must have a company or user as its subject", node_iri)))?,
+                };
+                fields.insert("relationship".into(), Value::Null);
+                Model::Member(build(model, json!({"inner": fields, "class": class}))?)
+            }
+            "Agreement" => Model::Agreement(build(model, json!({"inner": fields}))?),
+            "Commitment" => Model::Commitment(build(model, json!({"inner": fields, "move_costs": {}}))?),
+            "EconomicEvent" => Model::Event(build(model, json!({"inner": fields}))?),
+            "EconomicResource" => {
+                let custody = fields.get("primary_accountable").cloned()
+                    .ok_or_else(|| Error::MissingFields(vec!["primary_accountable".into()]))?;
+                Model::Resource(build(model, json!({"inner": fields, "in_custody_of": custody, "costs": {}}))?)
+            }
+            "Intent" => Model::Intent(build(model, json!({"inner": fields}))?),
+            "Organization" => Model::Company(build(model, json!({"inner": fields, "email": ""}))?),
+            "Person" => {
+                let name = fields.get("name").cloned()
+                    .ok_or_else(|| Error::MissingFields(vec!["name".into()]))?;
+                Model::User(build(model, json!({"name": name, "email": ""}))?)
+            }
+            "Process" => {
+                let company_id = fields.get("in_scope_of")
+                    .and_then(|scopes| scopes.as_array())
+                    .and_then(|scopes| scopes.iter().find_map(|scope| scope.get("company")))
+                    .cloned()
+                    .ok_or_else(|| Error::MissingFields(vec!["company_id".into()]))?;
+                Model::Process(build(model, json!({"inner": fields, "company_id": company_id, "costs": {}}))?)
+            }
+            _ => Err(Error::ImportFailed(format!("unknown node type {}", node_type)))?,
+        };
+
+        // anything that doesn't survive a round trip was dropped
+        let exported = export_node(&model, self.base)
+            .and_then(|exported| exported.as_object().cloned())
+            .unwrap_or_default()
+            .keys()
+            .map(|key| to_snake(key))
+            .collect::<Vec<_>>();
+        let mut notes = node.keys()
+            .filter(|key| !key.starts_with('@'))
+            .filter(|key| !exported.contains(&to_snake(term(key, "vf:", VF))))
+            .map(|key| note(key, ImportNoteKind::Dropped))
+            .collect::<Vec<_>>();
+        notes.extend(defaulted(node_type).iter().map(|field| note(field, ImportNoteKind::Defaulted)));
+        Ok((Some(model), notes))
+    }
+}
+
+/// Turn an `om2:Measure` node back into a measure. Numbers typed as
+/// `xsd:integer`, `xsd:decimal`, etc keep their type, and untyped numbers are
+/// read as integers if they're whole and decimals otherwise.
+fn import_measure(measure: &Map<String, Value>) -> Result<Value> {
+    let get = |name: &str| measure.iter()
+        .find(|(key, _)| term(key, "om2:", OM2) == name)
+        .map(|(_, val)| val);
+    let unit = get("hasUnit")
+        .ok_or_else(|| Error::MissingFields(vec!["om2:hasUnit".into()]))
+        .and_then(reference)
+        .map(|unit| term(unit, "om2:", OM2))?;
+    let number = get("hasNumericalValue")
+        .ok_or_else(|| Error::MissingFields(vec!["om2:hasNumericalValue".into()]))?;
+    let (number_type, number) = match number.get("@value") {
+        Some(val) => {
+            let number_type = number.get("@type").and_then(|ty| ty.as_str()).map(|ty| term(ty, "xsd:", XSD));
+            (number_type, val)
+        }
+        None => (None, number),
+    };
+    // JSON-LD literals are often given as strings
+    let number = match number {
+        Value::String(num) => serde_json::from_str::<Value>(num)
+            .map_err(|_| Error::ImportFailed(format!("invalid number {}", num)))?,
+        _ => number.clone(),
+    };
+    let number_type = match number_type {
+        Some(number_type) => number_type,
+        None if number.is_i64() || number.is_u64() => "integer",
+        None => "decimal",
+    };
+    Ok(json!({
+        "has_numerical_value": { number_type: number },
+        "has_unit": unit,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        costs::Costs,
+        models::{
+            agreement::AgreementID,
+            company::CompanyID,
+            event::Event,
+            lib::agent::Agent,
+            member::MemberID,
+            occupation::OccupationID,
+            process::ProcessID,
+            resource::ResourceID,
+            resource_spec::ResourceSpecID,
+            user::UserID,
+        },
+        util::{self, test::*},
+    };
+    use om2::{Measure, Unit};
+    use rust_decimal_macros::*;
+    use vf_rs::vf;
+
+    const BASE: &str = "https://basis.example/";
+
+    fn make_models(now: &DateTime<Utc>) -> Vec<Model> {
+        let company = make_company(&CompanyID::new("jerry's"), "jerry's widgets", now);
+        let user = make_user(&UserID::new("jerry"), None, now);
+        let member = make_member_worker(&MemberID::new("jerry-worker"), user.id(), company.id(), &OccupationID::new("machinist"), vec![], now);
+        let process = make_process(&ProcessID::new("make-widgets"), company.id(), "make widgets", &Costs::new_with_labor("machinist", dec!(10)), now);
+        let resource = make_resource(&ResourceID::new("widgets"), company.id(), &Measure::new(dec!(15), Unit::One), &Costs::new_with_labor("machinist", dec!(4)), now);
+        let agreement = make_agreement(&AgreementID::new("order"), &vec![company.agent_id()], "order 1234", "widgets, please", now);
+        let event = Event::builder()
+            .id("produce-widgets")
+            .inner(
+                vf::EconomicEvent::builder()
+                    .action(vf::Action::Produce)
+                    .output_of(Some(process.id().clone()))
+                    .provider(company.agent_id())
+                    .receiver(company.agent_id())
+                    .realization_of(Some(agreement.id().clone()))
+                    .resource_conforms_to(Some(ResourceSpecID::new("widget")))
+                    .resource_inventoried_as(Some(resource.id().clone()))
+                    .resource_quantity(Some(Measure::new(dec!(5.5), Unit::Kilogram)))
+                    .build().unwrap()
+            )
+            .move_costs(Some(Costs::new_with_labor("machinist", dec!(2))))
+            .active(true)
+            .created(*now)
+            .updated(*now)
+            .build().unwrap();
+        vec![company.into(), user.into(), member.into(), process.into(), resource.into(), agreement.into(), event.into()]
+    }
+
+    #[test]
+    fn exports() {
+        let now = util::time::now();
+        let models = make_models(&now);
+        let doc = export(&models, BASE);
+        assert_eq!(doc["@context"]["@vocab"], VF);
+        let graph = doc["@graph"].as_array().unwrap();
+        assert_eq!(graph.len(), 7);
+
+        assert_eq!(graph[0]["@id"], "https://basis.example/companies/jerry's");
+        assert_eq!(graph[0]["@type"], "foaf:Organization");
+        assert_eq!(graph[0]["name"], "jerry's widgets");
+        assert_eq!(graph[0].get("email"), None);
+        assert_eq!(graph[2]["@type"], "AgentRelationship");
+        assert_eq!(graph[2]["subject"], json!({"@id": "https://basis.example/users/jerry"}));
+        assert_eq!(graph[2]["object"], json!({"@id": "https://basis.example/companies/jerry's"}));
+        assert_eq!(graph[3]["inScopeOf"], json!([{"@id": "https://basis.example/companies/jerry's"}]));
+        assert_eq!(graph[3].get("costs"), None);
+        assert_eq!(graph[4]["primaryAccountable"], json!({"@id": "https://basis.example/companies/jerry's"}));
+
+        let event = &graph[6];
+        assert_eq!(event["@id"], "https://basis.example/events/produce-widgets");
+        assert_eq!(event["@type"], "EconomicEvent");
+        assert_eq!(event["action"], json!({"@id": "vf:produce"}));
+        assert_eq!(event["outputOf"], json!({"@id": "https://basis.example/processes/make-widgets"}));
+        assert_eq!(event["realizationOf"], json!({"@id": "https://basis.example/agreements/order"}));
+        assert_eq!(event["resourceConformsTo"], json!({"@id": "https://basis.example/resource-specs/widget"}));
+        assert_eq!(event["resourceQuantity"], json!({
+            "@type": "om2:Measure",
+            "om2:hasUnit": {"@id": "om2:kilogram"},
+            "om2:hasNumericalValue": {"@value": 5.5, "@type": "xsd:decimal"},
+        }));
+        assert_eq!(event.get("moveCosts"), None);
+
+        let doc = export(&[make_process_spec(&"widget-spec".into(), &CompanyID::new("jerry's"), "widgets", true, &now).into()], BASE);
+        assert_eq!(doc["@graph"], json!([]));
+    }
+
+    #[test]
+    fn round_trips() {
+        let now = util::time::now();
+        let models = make_models(&now);
+        let imported = import(&export(&models, BASE), BASE, &now).unwrap();
+        assert_eq!(imported.models().len(), models.len());
+        for (imported, model) in imported.models().iter().zip(models.iter()) {
+            assert_eq!(export_node(imported, BASE), export_node(model, BASE));
+        }
+        match (&imported.models()[6], &models[6]) {
+            (Model::Event(imported), Model::Event(event)) => {
+                assert_eq!(imported.id(), event.id());
+                assert_eq!(imported.inner(), event.inner());
+                assert_eq!(imported.move_costs(), &None);
+            }
+            _ => panic!("expected events"),
+        }
+        match &imported.models()[3] {
+            Model::Process(process) => assert_eq!(process.company_id(), &CompanyID::new("jerry's")),
+            _ => panic!("expected a process"),
+        }
+        match &imported.models()[2] {
+            Model::Member(member) => assert_eq!(member.inner().subject(), &UserID::new("jerry").into()),
+            _ => panic!("expected a member"),
+        }
+
+        let notes = imported.notes().iter()
+            .filter(|note| note.node() == "https://basis.example/events/produce-widgets")
+            .collect::<Vec<_>>();
+        assert_eq!(notes, vec![&ImportNote {
+            node: "https://basis.example/events/produce-widgets".into(),
+            field: "move_costs".into(),
+            kind: ImportNoteKind::Defaulted,
+        }]);
+        assert!(imported.notes().iter().all(|note| note.kind() == &ImportNoteKind::Defaulted));
+    }
+
+    #[test]
+    fn imports_foreign_documents() {
+        let now = util::time::now();
+        let doc = json!({
+            "@context": {"vf": VF, "om2": OM2},
+            "@graph": [
+                {
+                    "@id": "https://other.example/agents/alice",
+                    "@type": "vf:Person",
+                    "vf:name": "alice",
+                },
+                {
+                    "@id": "https://other.example/agents/bakery",
+                    "@type": ["vf:Agent", "vf:Organization"],
+                    "vf:name": "the bakery",
+                    "vf:note": "bread!",
+                },
+                {
+                    "@id": "https://other.example/events/1",
+                    "@type": "vf:EconomicEvent",
+                    "vf:action": {"@id": "https://w3id.org/valueflows#transfer-custody"},
+                    "vf:provider": {"@id": "https://other.example/agents/bakery"},
+                    "vf:receiver": "https://other.example/agents/alice",
+                    "vf:resourceClassifiedAs": [{"@id": "https://www.wikidata.org/wiki/Q7802"}],
+                    "vf:resourceQuantity": {
+                        "om2:hasUnit": {"@id": "om2:kilogram"},
+                        "om2:hasNumericalValue": 2,
+                    },
+                    "vf:hasPointInTime": "2020-06-01T12:00:00Z",
+                    "dfc:color": "brown",
+                },
+                {
+                    "@id": "https://other.example/plans/1",
+                    "@type": "vf:Plan",
+                },
+            ],
+        });
+        let imported = import(&doc, BASE, &now).unwrap();
+        assert_eq!(imported.models().len(), 3);
+        match &imported.models()[1] {
+            Model::Company(company) => {
+                assert_eq!(company.id(), &CompanyID::new("https://other.example/agents/bakery"));
+                assert_eq!(company.inner().note(), &Some("bread!".into()));
+            }
+            _ => panic!("expected a company"),
+        }
+        match &imported.models()[2] {
+            Model::Event(event) => {
+                assert_eq!(event.inner().action(), &vf::Action::TransferCustody);
+                assert_eq!(event.inner().provider(), &CompanyID::new("https://other.example/agents/bakery").into());
+                assert_eq!(event.inner().receiver(), &UserID::new("https://other.example/agents/alice").into());
+                assert_eq!(event.inner().resource_quantity(), &Some(Measure::new(2, Unit::Kilogram)));
+                assert_eq!(event.inner().has_point_in_time(), &Some("2020-06-01T12:00:00Z".parse().unwrap()));
+            }
+            _ => panic!("expected an event"),
+        }
+        let dropped = imported.notes().iter()
+            .filter(|note| note.kind() == &ImportNoteKind::Dropped)
+            .map(|note| (note.node().as_str(), note.field().as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(dropped, vec![
+            ("https://other.example/events/1", "dfc:color"),
+            ("https://other.example/events/1", "vf:resourceClassifiedAs"),
+            ("https://other.example/plans/1", "@type"),
+        ]);
+
+        let mut doc2 = doc.clone();
+        doc2["@graph"][2]["vf:provider"] = json!({"@id": "https://other.example/agents/who"});
+        let res = import(&doc2, BASE, &now);
+        assert_eq!(res, Err(Error::ImportFailed("can't tell what kind of agent https://other.example/agents/who is".into())));
+
+        let mut doc3 = doc.clone();
+        doc3["@graph"][2].as_object_mut().unwrap().remove("vf:action");
+        let res = import(&doc3, BASE, &now);
+        assert!(matches!(res, Err(Error::ImportFailed(_))));
+    }
+}